@@ -5,6 +5,7 @@
 #![deny(clippy::unused_async)]
 
 pub mod parser;
+mod printer;
 
 #[cfg(feature = "shell")]
 mod shell;
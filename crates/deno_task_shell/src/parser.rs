@@ -1,6 +1,8 @@
 // Copyright 2018-2024 the Deno authors. MIT license.
 
 use lazy_static::lazy_static;
+#[cfg(feature = "serialization")]
+use miette::IntoDiagnostic;
 use miette::{miette, Context, Result};
 use pest::iterators::Pair;
 use pest::pratt_parser::{Assoc, Op, PrattParser};
@@ -11,7 +13,10 @@ use thiserror::Error;
 // Shell grammar rules this is loosely based on:
 // https://pubs.opengroup.org/onlinepubs/009604499/utilities/xcu_chap02.html#tag_02_10_02
 
-#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(
+  feature = "serialization",
+  derive(serde::Serialize, serde::Deserialize)
+)]
 #[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
 #[derive(Debug, Clone, PartialEq, Eq, Error)]
 #[error("Invalid sequential list")]
@@ -19,7 +24,10 @@ pub struct SequentialList {
   pub items: Vec<SequentialListItem>,
 }
 
-#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(
+  feature = "serialization",
+  derive(serde::Serialize, serde::Deserialize)
+)]
 #[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
 #[derive(Debug, Clone, PartialEq, Eq, Error)]
 #[error("Invalid sequential list item")]
@@ -28,7 +36,42 @@ pub struct SequentialListItem {
   pub sequence: Sequence,
 }
 
-#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(
+  feature = "serialization",
+  derive(serde::Serialize, serde::Deserialize)
+)]
+#[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comment {
+  /// The text of the comment, including the `#`.
+  pub text: String,
+  /// Byte offset of the `#` in the input.
+  pub offset: usize,
+  /// Index of the item in the list the comment is attached to.
+  pub item: usize,
+  pub position: CommentPosition,
+}
+
+/// Where a comment is relative to the item it's attached to.
+#[cfg_attr(
+  feature = "serialization",
+  derive(serde::Serialize, serde::Deserialize)
+)]
+#[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommentPosition {
+  /// On its own line before the item.
+  Before,
+  /// After the item on the same line (ex. `echo hi # note`).
+  SameLine,
+  /// On its own line after the last item of the input.
+  After,
+}
+
+#[cfg_attr(
+  feature = "serialization",
+  derive(serde::Serialize, serde::Deserialize)
+)]
 #[cfg_attr(
   feature = "serialization",
   serde(rename_all = "camelCase", tag = "kind")
@@ -43,7 +86,10 @@ pub enum Sequence {
   BooleanList(Box<BooleanList>),
 }
 
-#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(
+  feature = "serialization",
+  derive(serde::Serialize, serde::Deserialize)
+)]
 #[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
 #[derive(Debug, Clone, PartialEq, Eq, Error)]
 #[error("Invalid pipeline")]
@@ -58,7 +104,10 @@ impl From<Pipeline> for Sequence {
   }
 }
 
-#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(
+  feature = "serialization",
+  derive(serde::Serialize, serde::Deserialize)
+)]
 #[cfg_attr(
   feature = "serialization",
   serde(rename_all = "camelCase", tag = "kind")
@@ -77,7 +126,10 @@ impl From<PipeSequence> for PipelineInner {
   }
 }
 
-#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(
+  feature = "serialization",
+  derive(serde::Serialize, serde::Deserialize)
+)]
 #[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Error)]
 pub enum BooleanListOperator {
@@ -101,7 +153,10 @@ impl BooleanListOperator {
   }
 }
 
-#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(
+  feature = "serialization",
+  derive(serde::Serialize, serde::Deserialize)
+)]
 #[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
 #[derive(Debug, Clone, PartialEq, Eq, Error)]
 #[error("Invalid boolean list")]
@@ -111,7 +166,10 @@ pub struct BooleanList {
   pub next: Sequence,
 }
 
-#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(
+  feature = "serialization",
+  derive(serde::Serialize, serde::Deserialize)
+)]
 #[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
 #[derive(Debug, Clone, PartialEq, Eq, Error)]
 #[error("Invalid pipe sequence")]
@@ -130,7 +188,10 @@ impl From<PipeSequence> for Sequence {
   }
 }
 
-#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(
+  feature = "serialization",
+  derive(serde::Serialize, serde::Deserialize)
+)]
 #[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Error)]
 pub enum PipeSequenceOperator {
@@ -140,7 +201,10 @@ pub enum PipeSequenceOperator {
   StdoutStderr,
 }
 
-#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(
+  feature = "serialization",
+  derive(serde::Serialize, serde::Deserialize)
+)]
 #[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
 #[derive(Debug, Clone, PartialEq, Eq, Error)]
 #[error("Invalid command")]
@@ -149,7 +213,10 @@ pub struct Command {
   pub redirect: Option<Redirect>,
 }
 
-#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(
+  feature = "serialization",
+  derive(serde::Serialize, serde::Deserialize)
+)]
 #[cfg_attr(
   feature = "serialization",
   serde(rename_all = "camelCase", tag = "kind")
@@ -160,6 +227,8 @@ pub enum CommandInner {
   Simple(SimpleCommand),
   #[error("Invalid subshell")]
   Subshell(Box<SequentialList>),
+  #[error("Invalid brace group")]
+  BraceGroup(Box<SequentialList>),
   #[error("Invalid if command")]
   If(IfClause),
   #[error("Invalid arithmetic expression")]
@@ -176,7 +245,10 @@ impl From<Command> for Sequence {
   }
 }
 
-#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(
+  feature = "serialization",
+  derive(serde::Serialize, serde::Deserialize)
+)]
 #[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
 #[derive(Debug, Clone, PartialEq, Eq, Error)]
 #[error("Invalid simple command")]
@@ -213,7 +285,10 @@ impl From<SimpleCommand> for Sequence {
   }
 }
 
-#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(
+  feature = "serialization",
+  derive(serde::Serialize, serde::Deserialize)
+)]
 #[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
 #[derive(Debug, PartialEq, Eq, Clone, Error)]
 #[error("Invalid if clause")]
@@ -223,7 +298,10 @@ pub struct IfClause {
   pub else_part: Option<ElsePart>,
 }
 
-#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(
+  feature = "serialization",
+  derive(serde::Serialize, serde::Deserialize)
+)]
 #[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
 #[derive(Debug, PartialEq, Eq, Clone, Error)]
 #[error("Invalid else part")]
@@ -232,7 +310,10 @@ pub enum ElsePart {
   Else(SequentialList),
 }
 
-#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(
+  feature = "serialization",
+  derive(serde::Serialize, serde::Deserialize)
+)]
 #[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
 #[derive(Debug, PartialEq, Eq, Clone, Error)]
 #[error("Invalid condition")]
@@ -240,7 +321,10 @@ pub struct Condition {
   pub condition_inner: ConditionInner,
 }
 
-#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(
+  feature = "serialization",
+  derive(serde::Serialize, serde::Deserialize)
+)]
 #[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
 #[derive(Debug, PartialEq, Eq, Clone, Error)]
 #[error("Invalid condition inner")]
@@ -254,9 +338,18 @@ pub enum ConditionInner {
     op: Option<UnaryOp>,
     right: Word,
   },
+  Not(Box<ConditionInner>),
+  Logical {
+    left: Box<ConditionInner>,
+    op: BooleanListOperator,
+    right: Box<ConditionInner>,
+  },
 }
 
-#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(
+  feature = "serialization",
+  derive(serde::Serialize, serde::Deserialize)
+)]
 #[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
 #[derive(Debug, PartialEq, Eq, Clone, Error)]
 #[error("Invalid binary operator")]
@@ -267,9 +360,18 @@ pub enum BinaryOp {
   LessThanOrEqual,
   GreaterThan,
   GreaterThanOrEqual,
+  /// `-nt`
+  NewerThan,
+  /// `-ot`
+  OlderThan,
+  /// `-ef`
+  SameFile,
 }
 
-#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(
+  feature = "serialization",
+  derive(serde::Serialize, serde::Deserialize)
+)]
 #[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
 #[derive(Debug, PartialEq, Eq, Clone, Error)]
 #[error("Invalid unary operator")]
@@ -299,7 +401,10 @@ pub enum UnaryOp {
   VariableNameReference,
 }
 
-#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(
+  feature = "serialization",
+  derive(serde::Serialize, serde::Deserialize)
+)]
 #[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
 #[derive(Debug, PartialEq, Eq, Clone, Error)]
 #[error("Invalid environment variable")]
@@ -314,7 +419,10 @@ impl EnvVar {
   }
 }
 
-#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(
+  feature = "serialization",
+  derive(serde::Serialize, serde::Deserialize)
+)]
 #[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
 #[derive(Debug, PartialEq, Eq, Clone, Error)]
 #[error("Invalid tilde prefix")]
@@ -332,7 +440,10 @@ impl TildePrefix {
   }
 }
 
-#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(
+  feature = "serialization",
+  derive(serde::Serialize, serde::Deserialize)
+)]
 #[derive(Debug, PartialEq, Eq, Clone, Error)]
 #[error("Invalid word")]
 pub struct Word(Vec<WordPart>);
@@ -365,7 +476,10 @@ impl Word {
   }
 }
 
-#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(
+  feature = "serialization",
+  derive(serde::Serialize, serde::Deserialize)
+)]
 #[cfg_attr(
   feature = "serialization",
   serde(rename_all = "camelCase", tag = "kind", content = "value")
@@ -374,16 +488,26 @@ impl Word {
 #[error("Invalid variable modifier")]
 pub enum VariableModifier {
   #[error("Invalid substring")]
-  Substring {
-    begin: Word,
-    length: Option<Word>,
+  Substring { begin: Word, length: Option<Word> },
+  /// `${VAR:-word}`, or `${VAR-word}` without the colon
+  DefaultValue {
+    value: Word,
+    /// Whether the colon form was used, which also treats an empty
+    /// value as unset
+    colon: bool,
   },
-  DefaultValue(Word),
-  AssignDefault(Word),
-  AlternateValue(Word),
+  /// `${VAR:=word}`, or `${VAR=word}` without the colon
+  AssignDefault { value: Word, colon: bool },
+  /// `${VAR:+word}`, or `${VAR+word}` without the colon
+  AlternateValue { value: Word, colon: bool },
+  /// `${#VAR}`
+  Length,
 }
 
-#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(
+  feature = "serialization",
+  derive(serde::Serialize, serde::Deserialize)
+)]
 #[cfg_attr(
   feature = "serialization",
   serde(rename_all = "camelCase", tag = "kind", content = "value")
@@ -404,16 +528,29 @@ pub enum WordPart {
   Arithmetic(Arithmetic),
   #[error("Invalid exit status")]
   ExitStatus,
+  /// `$LINENO`, with the line it's on.
+  #[error("Invalid line number")]
+  LineNumber(usize),
+  /// An extended glob like `@(a|b)` or `!(*.txt)`, the text of the pattern
+  /// along with the variables within it.
+  #[error("Invalid extended glob")]
+  ExtGlob(Vec<WordPart>),
 }
 
-#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(
+  feature = "serialization",
+  derive(serde::Serialize, serde::Deserialize)
+)]
 #[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
 #[derive(Debug, Clone, PartialEq, Eq, Error)]
 #[error("Invalid arithmetic sequence")]
 pub struct Arithmetic {
   pub parts: Vec<ArithmeticPart>,
 }
-#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(
+  feature = "serialization",
+  derive(serde::Serialize, serde::Deserialize)
+)]
 #[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
 #[derive(Debug, Clone, PartialEq, Eq, Error)]
 #[error("Invalid arithmetic part")]
@@ -449,6 +586,11 @@ pub enum ArithmeticPart {
     operator: UnaryArithmeticOp,
     operand: Box<ArithmeticPart>,
   },
+  #[error("Invalid pre arithmetic expression")]
+  PreArithmeticExpr {
+    operator: PostArithmeticOp,
+    operand: Box<ArithmeticPart>,
+  },
   #[error("Invalid post arithmetic expression")]
   PostArithmeticExpr {
     operand: Box<ArithmeticPart>,
@@ -460,7 +602,10 @@ pub enum ArithmeticPart {
   Number(String),
 }
 
-#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(
+  feature = "serialization",
+  derive(serde::Serialize, serde::Deserialize)
+)]
 #[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Hash, Copy, Ord)]
 pub enum BinaryArithmeticOp {
@@ -479,7 +624,10 @@ pub enum BinaryArithmeticOp {
   LogicalOr,  // ||
 }
 
-#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(
+  feature = "serialization",
+  derive(serde::Serialize, serde::Deserialize)
+)]
 #[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
 #[derive(Debug, Clone, PartialEq, Eq, Copy)]
 pub enum AssignmentOp {
@@ -496,7 +644,10 @@ pub enum AssignmentOp {
   BitwiseOrAssign,  // |=
 }
 
-#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(
+  feature = "serialization",
+  derive(serde::Serialize, serde::Deserialize)
+)]
 #[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
 #[derive(Debug, Clone, PartialEq, Eq, Copy)]
 pub enum UnaryArithmeticOp {
@@ -506,15 +657,23 @@ pub enum UnaryArithmeticOp {
   BitwiseNot, // ~
 }
 
-#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(
+  feature = "serialization",
+  derive(serde::Serialize, serde::Deserialize)
+)]
 #[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
+/// The `++` and `--` operators, which are used both before (`++i`) and
+/// after (`i++`) a variable.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum PostArithmeticOp {
   Increment, // ++
   Decrement, // --
 }
 
-#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(
+  feature = "serialization",
+  derive(serde::Serialize, serde::Deserialize)
+)]
 #[cfg_attr(
   feature = "serialization",
   serde(rename_all = "camelCase", tag = "kind", content = "fd")
@@ -527,7 +686,10 @@ pub enum RedirectFd {
   StdoutStderr,
 }
 
-#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(
+  feature = "serialization",
+  derive(serde::Serialize, serde::Deserialize)
+)]
 #[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
 #[derive(Debug, Clone, PartialEq, Eq, Error)]
 #[error("Invalid redirect")]
@@ -537,7 +699,10 @@ pub struct Redirect {
   pub io_file: IoFile,
 }
 
-#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(
+  feature = "serialization",
+  derive(serde::Serialize, serde::Deserialize)
+)]
 #[cfg_attr(
   feature = "serialization",
   serde(rename_all = "camelCase", tag = "kind", content = "value")
@@ -550,7 +715,10 @@ pub enum IoFile {
   Fd(u32),
 }
 
-#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(
+  feature = "serialization",
+  derive(serde::Serialize, serde::Deserialize)
+)]
 #[cfg_attr(
   feature = "serialization",
   serde(rename_all = "camelCase", tag = "kind", content = "value")
@@ -563,7 +731,10 @@ pub enum RedirectOp {
   Output(RedirectOpOutput),
 }
 
-#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(
+  feature = "serialization",
+  derive(serde::Serialize, serde::Deserialize)
+)]
 #[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
 #[derive(Debug, Clone, PartialEq, Eq, Error)]
 pub enum RedirectOpInput {
@@ -571,7 +742,10 @@ pub enum RedirectOpInput {
   Redirect,
 }
 
-#[cfg_attr(feature = "serialization", derive(serde::Serialize))]
+#[cfg_attr(
+  feature = "serialization",
+  derive(serde::Serialize, serde::Deserialize)
+)]
 #[cfg_attr(feature = "serialization", serde(rename_all = "camelCase"))]
 #[derive(Debug, Clone, PartialEq, Eq, Error)]
 pub enum RedirectOpOutput {
@@ -579,6 +753,8 @@ pub enum RedirectOpOutput {
   Overwrite,
   #[error("Invalid append redirect")]
   Append,
+  #[error("Invalid clobber redirect")]
+  Clobber,
 }
 
 lazy_static! {
@@ -633,6 +809,266 @@ pub fn parse(input: &str) -> Result<SequentialList> {
   parse_file(pairs.next().unwrap())
 }
 
+/// Parses the input like [`parse`], but also returns the comments between
+/// the top level items, attached to the nearest item, for tools like
+/// formatters. The list prints back with them using
+/// [`SequentialList::to_shell_string_with_comments`].
+///
+/// Comments within compound commands (ex. the body of an `if`) can't be
+/// attached to an item, so they're an error. Comments in input without any
+/// items are dropped.
+pub fn parse_with_comments(
+  input: &str,
+) -> Result<(SequentialList, Vec<Comment>)> {
+  let mut pairs = ShellParser::parse(Rule::FILE, input).map_err(|e| {
+    miette::Error::new(e.into_miette()).context("Failed to parse input")
+  })?;
+  let file = pairs.next().unwrap();
+  let mut spans = Vec::new();
+  for pair in item_pairs(&file) {
+    let start = pair.as_span().start();
+    // the span of the item includes the comments skipped after it, so
+    // it ends with the last token it has instead
+    let mut end = start;
+    for token in pair.clone().into_inner().flatten() {
+      if token.clone().into_inner().next().is_some() {
+        continue;
+      }
+      // only comments are skipped between the tokens that contain a `#`
+      if let Some(index) = input[end..token.as_span().start()].find('#') {
+        return Err(nested_comment_error(input, end + index));
+      }
+      end = token.as_span().end();
+    }
+    if end == start {
+      end = pair.as_span().end();
+    }
+    spans.push((start, end));
+  }
+  let list = parse_file(file)?;
+  if spans.len() != list.items.len() {
+    return Err(miette!(
+      "Found {} items in the input, but parsed {}",
+      spans.len(),
+      list.items.len()
+    ));
+  }
+  let comments = attach_comments(input, &spans)?;
+  Ok((list, comments))
+}
+
+fn nested_comment_error(input: &str, offset: usize) -> miette::Report {
+  let text = input[offset..].lines().next().unwrap_or_default();
+  miette!(
+    "Comments within compound commands are not supported: {}",
+    text
+  )
+}
+
+/// Parses the input like [`parse`], along with the text of each top level
+/// item as it was written, which `set -v` prints.
+pub fn parse_with_source(input: &str) -> Result<(SequentialList, Vec<String>)> {
+  let mut pairs = ShellParser::parse(Rule::FILE, input).map_err(|e| {
+    miette::Error::new(e.into_miette()).context("Failed to parse input")
+  })?;
+  let file = pairs.next().unwrap();
+  let texts = item_pairs(&file)
+    .map(|pair| {
+      let span = pair.as_span();
+      let mut text = span.as_str().trim_end().to_string();
+      // the `&` of an async item is the separator after it
+      if input[span.end()..]
+        .trim_start_matches([' ', '\t'])
+        .starts_with('&')
+      {
+        text.push_str(" &");
+      }
+      text
+    })
+    .collect();
+  Ok((parse_file(file)?, texts))
+}
+
+/// The pairs of the top level items of the file.
+fn item_pairs<'a>(
+  file: &Pair<'a, Rule>,
+) -> impl Iterator<Item = Pair<'a, Rule>> {
+  file
+    .clone()
+    .into_inner()
+    .next()
+    .unwrap()
+    .into_inner()
+    .filter(|pair| pair.as_rule() == Rule::list)
+    .flat_map(|list| list.into_inner())
+    .filter(|pair| pair.as_rule() == Rule::and_or)
+}
+
+/// Attaches the comments found between the items to the nearest item.
+fn attach_comments(
+  input: &str,
+  spans: &[(usize, usize)],
+) -> Result<Vec<Comment>> {
+  let mut comments = Vec::new();
+  if spans.is_empty() {
+    return Ok(comments);
+  }
+  for index in 0..=spans.len() {
+    let start = if index == 0 { 0 } else { spans[index - 1].1 };
+    let end = spans.get(index).map(|span| span.0).unwrap_or(input.len());
+    for (text, offset, on_item_line) in find_comments(input, start, end)? {
+      let (item, position) = if index > 0 && on_item_line {
+        (index - 1, CommentPosition::SameLine)
+      } else if index < spans.len() {
+        (index, CommentPosition::Before)
+      } else {
+        (index - 1, CommentPosition::After)
+      };
+      comments.push(Comment {
+        text: text.to_string(),
+        offset,
+        item,
+        position,
+      });
+    }
+  }
+  Ok(comments)
+}
+
+/// Finds the comments between the last token of an item and the next
+/// item, along with whether they're on the line the item ends on.
+///
+/// Besides separators, whitespace and comments, this text may hold the
+/// end of the previous item that has no tokens (ex. `fi` or `)`). The
+/// comments before that end are within the item, so they're an error.
+fn find_comments(
+  input: &str,
+  start: usize,
+  end: usize,
+) -> Result<Vec<(&str, usize, bool)>> {
+  let mut comments = Vec::new();
+  let mut on_item_line = true;
+  let mut offset = start;
+  while let Some(c) = input[offset..end].chars().next() {
+    match c {
+      '#' => {
+        let comment_end = input[offset..end]
+          .find(['\r', '\n'])
+          .map(|len| offset + len)
+          .unwrap_or(end);
+        comments.push((&input[offset..comment_end], offset, on_item_line));
+        offset = comment_end;
+        continue;
+      }
+      '\n' => on_item_line = false,
+      ';' | '&' => {}
+      c if c.is_whitespace() => {}
+      _ => {
+        if let Some((_, comment_offset, _)) = comments.first() {
+          return Err(nested_comment_error(input, *comment_offset));
+        }
+        on_item_line = true;
+      }
+    }
+    offset += c.len_utf8();
+  }
+  Ok(comments)
+}
+
+/// Deserializes a list that was serialized with
+/// [`SequentialList::to_json`], ex. after a tool modified it.
+#[cfg(feature = "serialization")]
+pub fn parse_from_json(json: &str) -> Result<SequentialList> {
+  serde_json::from_str(json)
+    .into_diagnostic()
+    .context("Failed to parse JSON")
+}
+
+#[cfg(feature = "serialization")]
+impl SequentialList {
+  /// Serializes the list to JSON, which [`parse_from_json`] reads back.
+  pub fn to_json(&self) -> Result<String> {
+    serde_json::to_string(self).into_diagnostic()
+  }
+
+  /// Serializes the list to indented JSON, ex. to inspect the syntax tree.
+  pub fn to_json_pretty(&self) -> Result<String> {
+    serde_json::to_string_pretty(self).into_diagnostic()
+  }
+}
+
+/// A failure to parse the input along with where it happened, so that
+/// editors can highlight the failing location.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("{message} at line {line}, column {column}")]
+pub struct ShellParseError {
+  /// Byte offset of the failing location in the input.
+  pub offset: usize,
+  /// 1-based line of the failing location.
+  pub line: usize,
+  /// 1-based column of the failing location, in characters.
+  pub column: usize,
+  /// Description of what was expected.
+  pub message: String,
+}
+
+impl From<pest::error::Error<Rule>> for ShellParseError {
+  fn from(err: pest::error::Error<Rule>) -> Self {
+    let offset = match err.location {
+      pest::error::InputLocation::Pos(pos) => pos,
+      pest::error::InputLocation::Span((start, _)) => start,
+    };
+    let (line, column) = match err.line_col {
+      pest::error::LineColLocation::Pos(pos) => pos,
+      pest::error::LineColLocation::Span(start, _) => start,
+    };
+    ShellParseError {
+      offset,
+      line,
+      column,
+      message: err.variant.message().to_string(),
+    }
+  }
+}
+
+/// Parses the input like [`parse`], but returns the location of the
+/// failure in a [`ShellParseError`].
+///
+/// The errors found after the grammar matched (ex. an unsupported
+/// compound command) have no location, so they're reported at the
+/// start of the input.
+pub fn parse_detailed(input: &str) -> Result<SequentialList, ShellParseError> {
+  let mut pairs = ShellParser::parse(Rule::FILE, input)?;
+  parse_file(pairs.next().unwrap()).map_err(|err| ShellParseError {
+    offset: 0,
+    line: 1,
+    column: 1,
+    message: err.to_string(),
+  })
+}
+
+/// Parses the contents of an arithmetic expression without the
+/// surrounding `$((` and `))` (e.g. the value assigned to a variable
+/// declared with `declare -i`).
+pub fn parse_arithmetic(input: &str) -> Result<Arithmetic> {
+  let input = input.trim();
+  let mut pairs = ShellParser::parse(Rule::arithmetic_sequence, input)
+    .map_err(|e| {
+      miette::Error::new(e.into_miette())
+        .context("Failed to parse arithmetic expression")
+    })?;
+  let pair = pairs.next().unwrap();
+  if pair.as_span().end() != input.len() {
+    return Err(miette!(
+      "Failed to parse arithmetic expression: unexpected input '{}'",
+      &input[pair.as_span().end()..]
+    ));
+  }
+  Ok(Arithmetic {
+    parts: parse_arithmetic_sequence(pair)?,
+  })
+}
+
 fn parse_file(pairs: Pair<Rule>) -> Result<SequentialList> {
   parse_complete_command(pairs.into_inner().next().unwrap())
 }
@@ -659,6 +1095,36 @@ fn parse_complete_command(pair: Pair<Rule>) -> Result<SequentialList> {
   Ok(SequentialList { items })
 }
 
+fn parse_sub_command(pair: Pair<Rule>) -> Result<SequentialList> {
+  let inner = pair.into_inner().next().unwrap();
+  if inner.as_rule() != Rule::file_read_command {
+    return parse_complete_command(inner);
+  }
+
+  // `$(< file)` is a command without a name that only redirects its input
+  let filename = inner
+    .into_inner()
+    .nth(1)
+    .ok_or_else(|| miette!("Expected filename after <"))?;
+  let command = Command {
+    inner: CommandInner::Simple(SimpleCommand {
+      env_vars: Vec::new(),
+      args: Vec::new(),
+    }),
+    redirect: Some(Redirect {
+      maybe_fd: None,
+      op: RedirectOp::Input(RedirectOpInput::Redirect),
+      io_file: IoFile::Word(parse_word(filename)?),
+    }),
+  };
+  Ok(SequentialList {
+    items: vec![SequentialListItem {
+      is_async: false,
+      sequence: command.into(),
+    }],
+  })
+}
+
 fn parse_list(
   pair: Pair<Rule>,
   items: &mut Vec<SequentialListItem>,
@@ -741,39 +1207,43 @@ fn parse_term(
 
 fn parse_and_or(pair: Pair<Rule>) -> Result<Sequence> {
   assert!(pair.as_rule() == Rule::and_or);
-  let mut items = pair.into_inner();
-  let first_item = items.next().unwrap();
-  let mut current = match first_item.as_rule() {
-    Rule::ASSIGNMENT_WORD => parse_shell_var(first_item)?,
-    Rule::pipeline => parse_pipeline(first_item)?,
-    _ => unreachable!(),
-  };
-
-  match items.next() {
-    Some(next_item) => {
-      if next_item.as_rule() == Rule::ASSIGNMENT_WORD {
-        return Err(miette!(
-          "Multiple assignment words before && or || is not supported yet"
-        ));
-      } else {
-        let op = match next_item.as_str() {
-          "&&" => BooleanListOperator::And,
-          "||" => BooleanListOperator::Or,
-          _ => unreachable!(),
-        };
+  // `&&` and `||` have the same precedence and group from the left, so
+  // `a || b && c` is `(a || b) && c`
+  let mut pair = pair;
+  let mut current: Option<(Sequence, BooleanListOperator)> = None;
+  loop {
+    let mut items = pair.into_inner();
+    let first_item = items.next().unwrap();
+    let item = match first_item.as_rule() {
+      Rule::ASSIGNMENT_WORD => parse_shell_var(first_item)?,
+      Rule::pipeline => parse_pipeline(first_item)?,
+      _ => unreachable!(),
+    };
+    let item = match current {
+      Some((current, op)) => Sequence::BooleanList(Box::new(BooleanList {
+        current,
+        op,
+        next: item,
+      })),
+      None => item,
+    };
 
-        let next_item = items.next().unwrap();
-        let next = parse_and_or(next_item)?;
-        current =
-          Sequence::BooleanList(Box::new(BooleanList { current, op, next }));
-      }
-    }
-    None => {
-      return Ok(current);
+    let Some(next_item) = items.next() else {
+      return Ok(item);
+    };
+    if next_item.as_rule() == Rule::ASSIGNMENT_WORD {
+      return Err(miette!(
+        "Multiple assignment words before && or || is not supported yet"
+      ));
     }
+    let op = match next_item.as_str() {
+      "&&" => BooleanListOperator::And,
+      "||" => BooleanListOperator::Or,
+      _ => unreachable!(),
+    };
+    current = Some((item, op));
+    pair = items.next().unwrap();
   }
-
-  Ok(current)
 }
 
 fn parse_shell_var(pair: Pair<Rule>) -> Result<Sequence> {
@@ -866,10 +1336,20 @@ fn parse_pipe_sequence(pair: Pair<Rule>) -> Result<PipelineInner> {
 }
 
 fn parse_command(pair: Pair<Rule>) -> Result<Command> {
-  let inner = pair.into_inner().next().unwrap();
+  let mut pairs = pair.into_inner();
+  let inner = pairs.next().unwrap();
   match inner.as_rule() {
     Rule::simple_command => parse_simple_command(inner),
-    Rule::compound_command => parse_compound_command(inner),
+    Rule::compound_command => {
+      let mut command = parse_compound_command(inner)?;
+      if let Some(redirect_list) = pairs.next() {
+        // like simple commands, only the last redirect is used
+        for redirect in redirect_list.into_inner() {
+          command.redirect = Some(parse_io_redirect(redirect)?);
+        }
+      }
+      Ok(command)
+    }
     Rule::function_definition => {
       Err(miette!("Function definitions are not supported yet"))
     }
@@ -938,9 +1418,7 @@ fn parse_simple_command(pair: Pair<Rule>) -> Result<Command> {
 fn parse_compound_command(pair: Pair<Rule>) -> Result<Command> {
   let inner = pair.into_inner().next().unwrap();
   match inner.as_rule() {
-    Rule::brace_group => {
-      Err(miette!("Unsupported compound command brace_group"))
-    }
+    Rule::brace_group => parse_brace_group(inner),
     Rule::subshell => parse_subshell(inner),
     Rule::for_clause => Err(miette!("Unsupported compound command for_clause")),
     Rule::case_clause => {
@@ -986,6 +1464,23 @@ fn parse_subshell(pair: Pair<Rule>) -> Result<Command> {
   }
 }
 
+fn parse_brace_group(pair: Pair<Rule>) -> Result<Command> {
+  let mut items = Vec::new();
+  match pair
+    .into_inner()
+    .find(|pair| pair.as_rule() == Rule::compound_list)
+  {
+    Some(inner) => {
+      parse_compound_list(inner, &mut items)?;
+      Ok(Command {
+        inner: CommandInner::BraceGroup(Box::new(SequentialList { items })),
+        redirect: None,
+      })
+    }
+    None => Err(miette!("Unexpected end of input in brace group")),
+  }
+}
+
 fn parse_if_clause(pair: Pair<Rule>) -> Result<IfClause> {
   let mut inner = pair.into_inner();
   let condition = inner
@@ -1060,16 +1555,83 @@ fn parse_conditional_expression(pair: Pair<Rule>) -> Result<Condition> {
     .next()
     .ok_or_else(|| miette!("Expected conditional expression content"))?;
 
-  match inner.as_rule() {
+  Ok(Condition {
+    condition_inner: parse_condition_inner(inner)?,
+  })
+}
+
+fn parse_condition_inner(pair: Pair<Rule>) -> Result<ConditionInner> {
+  match pair.as_rule() {
+    Rule::conditional_or_expression
+    | Rule::conditional_and_expression
+    | Rule::test_or_expression
+    | Rule::test_and_expression => {
+      let op = if matches!(
+        pair.as_rule(),
+        Rule::conditional_or_expression | Rule::test_or_expression
+      ) {
+        BooleanListOperator::Or
+      } else {
+        BooleanListOperator::And
+      };
+      let mut inner = pair.into_inner();
+      let first = inner
+        .next()
+        .ok_or_else(|| miette!("Expected conditional expression"))?;
+      let mut left = parse_condition_inner(first)?;
+      while let Some(operator) = inner.next() {
+        if !matches!(
+          operator.as_rule(),
+          Rule::AND_IF | Rule::OR_IF | Rule::TEST_AND | Rule::TEST_OR
+        ) {
+          return Err(miette!(
+            "Unexpected rule in conditional expression: {:?}",
+            operator.as_rule()
+          ));
+        }
+        let next = inner.next().ok_or_else(|| {
+          miette!(
+            "Expected conditional expression after {}",
+            operator.as_str()
+          )
+        })?;
+        left = ConditionInner::Logical {
+          left: Box::new(left),
+          op,
+          right: Box::new(parse_condition_inner(next)?),
+        };
+      }
+      Ok(left)
+    }
+    Rule::conditional_not_expression | Rule::test_not_expression => {
+      let mut inner = pair.into_inner();
+      let first = inner
+        .next()
+        .ok_or_else(|| miette!("Expected conditional expression"))?;
+      if first.as_rule() == Rule::Bang {
+        let operand = inner
+          .next()
+          .ok_or_else(|| miette!("Expected conditional expression after !"))?;
+        Ok(ConditionInner::Not(Box::new(parse_condition_inner(
+          operand,
+        )?)))
+      } else {
+        parse_condition_inner(first)
+      }
+    }
     Rule::unary_conditional_expression => {
-      parse_unary_conditional_expression(inner)
+      Ok(parse_unary_conditional_expression(pair)?.condition_inner)
     }
     Rule::binary_conditional_expression => {
-      parse_binary_conditional_expression(inner)
+      Ok(parse_binary_conditional_expression(pair)?.condition_inner)
     }
+    Rule::UNQUOTED_PENDING_WORD => Ok(ConditionInner::Unary {
+      op: None,
+      right: parse_word(pair)?,
+    }),
     _ => Err(miette!(
       "Unexpected rule in conditional expression: {:?}",
-      inner.as_rule()
+      pair.as_rule()
     )),
   }
 }
@@ -1095,6 +1657,7 @@ fn parse_unary_conditional_expression(pair: Pair<Rule>) -> Result<Condition> {
       "-b" => UnaryOp::BlockSpecial,
       "-c" => UnaryOp::CharSpecial,
       "-d" => UnaryOp::Directory,
+      "-e" => UnaryOp::FileExists,
       "-f" => UnaryOp::RegularFile,
       "-g" => UnaryOp::SetGroupId,
       "-h" => UnaryOp::SymbolicLink,
@@ -1135,7 +1698,12 @@ fn parse_unary_conditional_expression(pair: Pair<Rule>) -> Result<Condition> {
     }
   };
 
-  let right = parse_word(operand)?;
+  // the operand of `-v` and `-R` is a variable name and not expanded
+  let right = if operand.as_rule() == Rule::VARIABLE {
+    Word::new_word(operand.as_str())
+  } else {
+    parse_word(operand)?
+  };
 
   Ok(Condition {
     condition_inner: ConditionInner::Unary {
@@ -1186,6 +1754,17 @@ fn parse_binary_conditional_expression(pair: Pair<Rule>) -> Result<Condition> {
         ))
       }
     },
+    Rule::binary_file_conditional_op => match operator.as_str() {
+      "-nt" => BinaryOp::NewerThan,
+      "-ot" => BinaryOp::OlderThan,
+      "-ef" => BinaryOp::SameFile,
+      _ => {
+        return Err(miette!(
+          "Unexpected file conditional operator: {}",
+          operator.as_str()
+        ))
+      }
+    },
     _ => {
       return Err(miette!(
         "Unexpected operator rule: {:?}",
@@ -1240,8 +1819,7 @@ fn parse_word(pair: Pair<Rule>) -> Result<Word> {
             }
           }
           Rule::SUB_COMMAND => {
-            let command =
-              parse_complete_command(part.into_inner().next().unwrap())?;
+            let command = parse_sub_command(part)?;
             parts.push(WordPart::Command(command));
           }
           Rule::VARIABLE_EXPANSION => {
@@ -1260,6 +1838,11 @@ fn parse_word(pair: Pair<Rule>) -> Result<Word> {
             let arithmetic_expression = parse_arithmetic_expression(part)?;
             parts.push(WordPart::Arithmetic(arithmetic_expression));
           }
+          Rule::EXTGLOB_PATTERN => {
+            let mut pattern_parts = Vec::new();
+            parse_extglob_pattern(part, &mut pattern_parts)?;
+            parts.push(WordPart::ExtGlob(pattern_parts));
+          }
           _ => {
             return Err(miette!(
               "Unexpected rule in UNQUOTED_PENDING_WORD: {:?}",
@@ -1403,6 +1986,13 @@ fn parse_arithmetic_sequence(pair: Pair<Rule>) -> Result<Vec<ArithmeticPart>> {
 }
 
 fn parse_arithmetic_expr(pair: Pair<Rule>) -> Result<ArithmeticPart> {
+  // the operands of unary and conditional expressions are single primaries
+  // instead of an `arithmetic_expr`
+  let pairs = if pair.as_rule() == Rule::arithmetic_expr {
+    pair.into_inner().collect::<Vec<_>>()
+  } else {
+    vec![pair]
+  };
   ARITHMETIC_PARSER
     .map_primary(|primary| match primary.as_rule() {
       Rule::parentheses_expr => {
@@ -1485,7 +2075,7 @@ fn parse_arithmetic_expr(pair: Pair<Rule>) -> Result<ArithmeticPart> {
         right: Box::new(rhs?),
       })
     })
-    .parse(pair.into_inner())
+    .parse(pairs.into_iter())
 }
 
 fn parse_unary_arithmetic_expr(pair: Pair<Rule>) -> Result<ArithmeticPart> {
@@ -1493,7 +2083,10 @@ fn parse_unary_arithmetic_expr(pair: Pair<Rule>) -> Result<ArithmeticPart> {
   let first = inner.next().unwrap();
 
   match first.as_rule() {
-    Rule::unary_arithmetic_op => {
+    Rule::unary_plus
+    | Rule::unary_minus
+    | Rule::logical_not
+    | Rule::bitwise_not => {
       let op = parse_unary_arithmetic_op(first)?;
       let operand = parse_arithmetic_expr(inner.next().unwrap())?;
       Ok(ArithmeticPart::UnaryArithmeticExpr {
@@ -1504,9 +2097,9 @@ fn parse_unary_arithmetic_expr(pair: Pair<Rule>) -> Result<ArithmeticPart> {
     Rule::post_arithmetic_op => {
       let operand = parse_arithmetic_expr(inner.next().unwrap())?;
       let op = parse_post_arithmetic_op(first)?;
-      Ok(ArithmeticPart::PostArithmeticExpr {
-        operand: Box::new(operand),
+      Ok(ArithmeticPart::PreArithmeticExpr {
         operator: op,
+        operand: Box::new(operand),
       })
     }
     _ => {
@@ -1544,14 +2137,65 @@ fn parse_post_arithmetic_op(pair: Pair<Rule>) -> Result<PostArithmeticOp> {
   }
 }
 
+fn parse_extglob_pattern(
+  pair: Pair<Rule>,
+  parts: &mut Vec<WordPart>,
+) -> Result<()> {
+  fn push_text(parts: &mut Vec<WordPart>, text: &str) {
+    if text.is_empty() {
+      return;
+    }
+    match parts.last_mut() {
+      Some(WordPart::Text(prev)) => prev.push_str(text),
+      _ => parts.push(WordPart::Text(text.to_string())),
+    }
+  }
+
+  let text = pair.as_str();
+  let start = pair.as_span().start();
+  // the text between the variables and nested patterns is kept as is
+  let mut text_start = 0;
+  for inner in pair.into_inner() {
+    let span = inner.as_span();
+    push_text(parts, &text[text_start..span.start() - start]);
+    text_start = span.end() - start;
+    match inner.as_rule() {
+      Rule::EXTGLOB_PATTERN => parse_extglob_pattern(inner, parts)?,
+      Rule::VARIABLE_EXPANSION => parts.push(parse_variable_expansion(inner)?),
+      _ => {
+        return Err(miette!(
+          "Unexpected rule in EXTGLOB_PATTERN: {:?}",
+          inner.as_rule()
+        ));
+      }
+    }
+  }
+  push_text(parts, &text[text_start..]);
+  Ok(())
+}
+
 fn parse_variable_expansion(part: Pair<Rule>) -> Result<WordPart> {
-  let mut inner = part.into_inner();
+  let mut inner = part.into_inner().peekable();
+  let is_length = inner
+    .next_if(|pair| pair.as_rule() == Rule::VAR_LENGTH)
+    .is_some();
   let variable = inner
     .next()
     .ok_or_else(|| miette!("Expected variable name"))?;
   let variable_name = variable.as_str().to_string();
+  let (line_number, _) = variable.line_col();
+  if is_length {
+    return Ok(WordPart::Variable(
+      variable_name,
+      Some(Box::new(VariableModifier::Length)),
+    ));
+  }
 
   let modifier = inner.next();
+  if variable_name == "LINENO" && modifier.is_none() {
+    // the line is only known while parsing, so it's kept with the expansion
+    return Ok(WordPart::LineNumber(line_number));
+  }
   let parsed_modifier = if let Some(modifier) = modifier {
     match modifier.as_rule() {
       Rule::VAR_SUBSTRING => {
@@ -1570,24 +2214,23 @@ fn parse_variable_expansion(part: Pair<Rule>) -> Result<WordPart> {
         Some(Box::new(VariableModifier::Substring { begin, length }))
       }
       Rule::VAR_DEFAULT_VALUE => {
+        let colon = modifier.as_str().starts_with(':');
         let value = if let Some(val) = modifier.into_inner().next() {
           parse_word(val)?
         } else {
           Word::new_empty()
         };
-        Some(Box::new(VariableModifier::DefaultValue(value)))
+        Some(Box::new(VariableModifier::DefaultValue { value, colon }))
       }
       Rule::VAR_ASSIGN_DEFAULT => {
-        let value = modifier.into_inner().next().unwrap();
-        Some(Box::new(VariableModifier::AssignDefault(parse_word(
-          value,
-        )?)))
+        let colon = modifier.as_str().starts_with(':');
+        let value = parse_word(modifier.into_inner().next().unwrap())?;
+        Some(Box::new(VariableModifier::AssignDefault { value, colon }))
       }
       Rule::VAR_ALTERNATE_VALUE => {
-        let value = modifier.into_inner().next().unwrap();
-        Some(Box::new(VariableModifier::AlternateValue(parse_word(
-          value,
-        )?)))
+        let colon = modifier.as_str().starts_with(':');
+        let value = parse_word(modifier.into_inner().next().unwrap())?;
+        Some(Box::new(VariableModifier::AlternateValue { value, colon }))
       }
       _ => {
         return Err(miette!(
@@ -1631,8 +2274,7 @@ fn parse_quoted_word(pair: Pair<Rule>) -> Result<WordPart> {
             }
           }
           Rule::SUB_COMMAND => {
-            let command =
-              parse_complete_command(part.into_inner().next().unwrap())?;
+            let command = parse_sub_command(part)?;
             parts.push(WordPart::Command(command));
           }
           Rule::VARIABLE_EXPANSION => {
@@ -1762,6 +2404,7 @@ fn parse_io_file(pair: Pair<Rule>) -> Result<(RedirectOp, IoFile)> {
     Rule::LESS => RedirectOp::Input(RedirectOpInput::Redirect),
     Rule::GREAT => RedirectOp::Output(RedirectOpOutput::Overwrite),
     Rule::DGREAT => RedirectOp::Output(RedirectOpOutput::Append),
+    Rule::CLOBBER => RedirectOp::Output(RedirectOpOutput::Clobber),
     Rule::LESSAND | Rule::GREATAND => {
       // For these operators, the target must be a number (fd)
       let target = filename.as_str();
@@ -1828,7 +2471,187 @@ mod test {
     );
 
     assert!(parse("echo \"foo\" > out.txt").is_ok());
+
+    // commands starting with a reserved word
+    assert!(parse("find . -name '*.txt'").is_ok());
+    assert!(parse("done_task && iffy").is_ok());
+    assert!(parse("fi").is_err());
+    assert!(parse("done").is_err());
+    assert!(parse("elif").is_err());
+    assert_eq!(
+      parse("while false; do echo; done")
+        .err()
+        .unwrap()
+        .to_string(),
+      "Unsupported compound command while_clause"
+    );
+    assert!(parse("then; echo").is_err());
+    assert!(parse("{ test").is_err());
+
+    assert_eq!(
+      parse("{ cd dir; }").unwrap(),
+      SequentialList {
+        items: vec![SequentialListItem {
+          is_async: false,
+          sequence: Command {
+            inner: CommandInner::BraceGroup(Box::new(SequentialList {
+              items: vec![SequentialListItem {
+                is_async: false,
+                sequence: SimpleCommand {
+                  env_vars: vec![],
+                  args: vec![Word::new_word("cd"), Word::new_word("dir")],
+                }
+                .into(),
+              }],
+            })),
+            redirect: None,
+          }
+          .into(),
+        }],
+      },
+    );
+
+    assert_eq!(
+      parse("echo $(< file.txt)").unwrap(),
+      SequentialList {
+        items: vec![SequentialListItem {
+          is_async: false,
+          sequence: SimpleCommand {
+            env_vars: vec![],
+            args: vec![
+              Word::new_word("echo"),
+              Word::new(vec![WordPart::Command(SequentialList {
+                items: vec![SequentialListItem {
+                  is_async: false,
+                  sequence: Command {
+                    inner: CommandInner::Simple(SimpleCommand {
+                      env_vars: vec![],
+                      args: vec![],
+                    }),
+                    redirect: Some(Redirect {
+                      maybe_fd: None,
+                      op: RedirectOp::Input(RedirectOpInput::Redirect),
+                      io_file: IoFile::Word(Word::new_word("file.txt")),
+                    }),
+                  }
+                  .into(),
+                }],
+              })]),
+            ],
+          }
+          .into(),
+        }],
+      },
+    );
+  }
+  #[test]
+  fn test_comments() {
+    let simple_command = |args: Vec<Word>| SequentialList {
+      items: vec![SequentialListItem {
+        is_async: false,
+        sequence: SimpleCommand {
+          env_vars: vec![],
+          args,
+        }
+        .into(),
+      }],
+    };
+
+    assert_eq!(
+      parse("echo hi # this is a comment").unwrap(),
+      simple_command(vec![Word::new_word("echo"), Word::new_word("hi")]),
+    );
+    assert_eq!(
+      parse("echo a#b").unwrap(),
+      simple_command(vec![Word::new_word("echo"), Word::new_word("a#b")]),
+    );
+    assert_eq!(
+      parse("# comment\necho hi # other comment\n# last").unwrap(),
+      simple_command(vec![Word::new_word("echo"), Word::new_word("hi")]),
+    );
+    assert!(parse("# comment").unwrap().items.is_empty());
+
+    // comments are only kept when asked for
+    let (list, comments) = parse_with_comments("echo hi # note").unwrap();
+    assert_eq!(list, parse("echo hi").unwrap());
+    assert_eq!(
+      comments,
+      vec![Comment {
+        text: "# note".to_string(),
+        offset: 8,
+        item: 0,
+        position: CommentPosition::SameLine,
+      }]
+    );
+    let (_, comments) =
+      parse_with_comments("# first\necho a; echo b # b\n\n# last\n").unwrap();
+    assert_eq!(
+      comments
+        .iter()
+        .map(|comment| (comment.text.as_str(), comment.item, comment.position))
+        .collect::<Vec<_>>(),
+      vec![
+        ("# first", 0, CommentPosition::Before),
+        ("# b", 1, CommentPosition::SameLine),
+        ("# last", 1, CommentPosition::After),
+      ]
+    );
+    let (list, comments) = parse_with_comments("# comment").unwrap();
+    assert!(list.items.is_empty());
+    assert!(comments.is_empty());
+    // comments within compound commands can't be attached to an item
+    for input in [
+      "if [[ a == b ]]; then\n  echo # inner\nfi # outer",
+      "if [[ a == b ]]; then # inner\n  echo\nfi",
+      "{\n  # inner\n  echo a\n  echo b\n}",
+      "echo $(echo # inner\n) # outer",
+    ] {
+      assert_eq!(
+        parse_with_comments(input).err().unwrap().to_string(),
+        "Comments within compound commands are not supported: # inner",
+        "{input}"
+      );
+    }
+    let (list, comments) = parse_with_comments(
+      "if [[ a == b ]]; then\n  echo 'a # b' ${#c}\nfi # outer",
+    )
+    .unwrap();
+    assert_eq!(list.items.len(), 1);
+    assert_eq!(
+      comments
+        .iter()
+        .map(|comment| (comment.text.as_str(), comment.position))
+        .collect::<Vec<_>>(),
+      vec![("# outer", CommentPosition::SameLine)],
+    );
+    assert_eq!(
+      parse("echo ${#VAR}").unwrap(),
+      simple_command(vec![
+        Word::new_word("echo"),
+        Word::new(vec![WordPart::Variable(
+          "VAR".to_string(),
+          Some(Box::new(VariableModifier::Length)),
+        )]),
+      ]),
+    );
+  }
+
+  #[test]
+  fn test_parse_with_source() {
+    let input =
+      "echo   \"a\"  $X; sleep 1&\nif [[ a == a ]]; then\n  echo b\nfi\n";
+    let (list, texts) = parse_with_source(input).unwrap();
+    assert_eq!(list, parse(input).unwrap());
+    assert_eq!(
+      texts,
+      vec![
+        "echo   \"a\"  $X",
+        "sleep 1 &",
+        "if [[ a == a ]]; then\n  echo b\nfi",
+      ]
+    );
   }
+
   #[test]
   fn test_sequential_list() {
     let parse_and_create = |input: &str| -> Result<SequentialList> {
@@ -1907,24 +2730,24 @@ mod test {
         SequentialListItem {
           is_async: false,
           sequence: Sequence::BooleanList(Box::new(BooleanList {
-            current: Sequence::ShellVar(EnvVar::new(
-              "ENV7".to_string(),
-              Word::new_word("other"),
-            )),
-            op: BooleanListOperator::And,
-            next: Sequence::BooleanList(Box::new(BooleanList {
-              current: SimpleCommand {
-                env_vars: vec![],
-                args: vec![Word::new_word("command8")],
-              }
-              .into(),
-              op: BooleanListOperator::Or,
+            current: Sequence::BooleanList(Box::new(BooleanList {
+              current: Sequence::ShellVar(EnvVar::new(
+                "ENV7".to_string(),
+                Word::new_word("other"),
+              )),
+              op: BooleanListOperator::And,
               next: SimpleCommand {
                 env_vars: vec![],
-                args: vec![Word::new_word("command9")],
+                args: vec![Word::new_word("command8")],
               }
               .into(),
             })),
+            op: BooleanListOperator::Or,
+            next: SimpleCommand {
+              env_vars: vec![],
+              args: vec![Word::new_word("command9")],
+            }
+            .into(),
           })),
         },
         SequentialListItem {
@@ -2119,6 +2942,23 @@ mod test {
     assert_eq!(result, expected);
   }
 
+  #[test]
+  fn test_parse_detailed() {
+    // the missing quote is reported at the end of the input
+    let err = parse_detailed("echo \"unterminated").err().unwrap();
+    assert_eq!((err.offset, err.line, err.column), (18, 1, 19));
+    assert!(!err.message.is_empty());
+
+    let err = parse_detailed("echo 1\necho 2 |").err().unwrap();
+    assert_eq!((err.offset, err.line, err.column), (15, 2, 9));
+    assert_eq!(
+      err.to_string(),
+      format!("{} at line 2, column 9", err.message)
+    );
+
+    assert_eq!(parse_detailed("echo 1").unwrap(), parse("echo 1").unwrap());
+  }
+
   #[test]
   fn test_env_var() {
     let parse_and_create = |input: &str| -> Result<EnvVar, miette::Error> {
@@ -2195,6 +3035,30 @@ mod test {
     );
   }
 
+  #[test]
+  fn test_line_number() {
+    let list = parse("echo\necho $LINENO \"$LINENO\"").unwrap();
+    let SequentialList { items } = list;
+    let Sequence::Pipeline(Pipeline {
+      inner:
+        PipelineInner::Command(Command {
+          inner: CommandInner::Simple(command),
+          ..
+        }),
+      ..
+    }) = &items[1].sequence
+    else {
+      panic!("expected a simple command");
+    };
+    assert_eq!(
+      command.args[1..],
+      [
+        Word::new(vec![WordPart::LineNumber(2)]),
+        Word::new(vec![WordPart::Quoted(vec![WordPart::LineNumber(2)])]),
+      ]
+    );
+  }
+
   #[cfg(feature = "serialization")]
   #[test]
   fn serializes_command_to_json() {
@@ -2388,6 +3252,62 @@ mod test {
     );
   }
 
+  #[cfg(feature = "serialization")]
+  #[test]
+  fn round_trips_json() {
+    let list = parse(
+      r#"A=1 cmd "a $B" ${C:-d} 2>&1 | cat > out && (echo $((1 + 2))) &
+if [[ -f file ]]; then echo yes; else echo no; fi
+echo $LINENO"#,
+    )
+    .unwrap();
+    let json = list.to_json().unwrap();
+    assert_eq!(parse_from_json(&json).unwrap(), list);
+    assert!(parse_from_json("{}").is_err());
+  }
+
+  #[cfg(feature = "serialization")]
+  #[test]
+  fn serializes_to_pretty_json() {
+    let json = parse("echo hi").unwrap().to_json_pretty().unwrap();
+    assert_eq!(
+      json,
+      r#"{
+  "items": [
+    {
+      "isAsync": false,
+      "sequence": {
+        "kind": "pipeline",
+        "negated": false,
+        "inner": {
+          "kind": "command",
+          "inner": {
+            "kind": "simple",
+            "envVars": [],
+            "args": [
+              [
+                {
+                  "kind": "text",
+                  "value": "echo"
+                }
+              ],
+              [
+                {
+                  "kind": "text",
+                  "value": "hi"
+                }
+              ]
+            ]
+          },
+          "redirect": null
+        }
+      }
+    }
+  ]
+}"#
+    );
+  }
+
   #[cfg(feature = "serialization")]
   #[track_caller]
   fn assert_json_equals(
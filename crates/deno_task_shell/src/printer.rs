@@ -0,0 +1,742 @@
+// Copyright 2018-2024 the Deno authors. MIT license.
+
+//! Renders the parsed AST back to shell text.
+//!
+//! The output is canonical rather than a copy of the original input (ex.
+//! `a&&b` becomes `a && b`), but it parses back to the same AST.
+
+use crate::parser::Arithmetic;
+use crate::parser::ArithmeticPart;
+use crate::parser::AssignmentOp;
+use crate::parser::BinaryArithmeticOp;
+use crate::parser::BinaryOp;
+use crate::parser::Command;
+use crate::parser::CommandInner;
+use crate::parser::Comment;
+use crate::parser::CommentPosition;
+use crate::parser::ConditionInner;
+use crate::parser::ElsePart;
+use crate::parser::EnvVar;
+use crate::parser::IfClause;
+use crate::parser::IoFile;
+use crate::parser::PipeSequenceOperator;
+use crate::parser::PipelineInner;
+use crate::parser::PostArithmeticOp;
+use crate::parser::Redirect;
+use crate::parser::RedirectFd;
+use crate::parser::RedirectOp;
+use crate::parser::RedirectOpInput;
+use crate::parser::RedirectOpOutput;
+use crate::parser::Sequence;
+use crate::parser::SequentialList;
+use crate::parser::SequentialListItem;
+use crate::parser::UnaryArithmeticOp;
+use crate::parser::UnaryOp;
+use crate::parser::VariableModifier;
+use crate::parser::Word;
+use crate::parser::WordPart;
+
+impl SequentialList {
+  /// Renders the list as shell text that parses back to the same list.
+  pub fn to_shell_string(&self) -> String {
+    let mut out = String::new();
+    write_list(&mut out, self);
+    out
+  }
+
+  /// Renders the list like [`SequentialList::to_shell_string`], with the
+  /// comments returned by
+  /// [`parse_with_comments`](crate::parser::parse_with_comments).
+  pub fn to_shell_string_with_comments(&self, comments: &[Comment]) -> String {
+    let mut out = String::new();
+    write_list_with_comments(&mut out, self, comments);
+    out
+  }
+}
+
+impl SequentialListItem {
+  /// Renders the item as shell text, with a trailing `&` when it's async.
+  pub fn to_shell_string(&self) -> String {
+    let mut out = String::new();
+    write_sequence(&mut out, &self.sequence);
+    if self.is_async {
+      out.push_str(" &");
+    }
+    out
+  }
+}
+
+impl Command {
+  /// Renders the command as shell text.
+  pub fn to_shell_string(&self) -> String {
+    let mut out = String::new();
+    write_command(&mut out, self);
+    out
+  }
+}
+
+impl Redirect {
+  /// Renders the redirect as shell text (ex. `2> file.txt`).
+  pub fn to_shell_string(&self) -> String {
+    let mut out = String::new();
+    write_redirect(&mut out, self);
+    out
+  }
+}
+
+impl Word {
+  /// Renders the word as shell text, quoting as needed.
+  pub fn to_shell_string(&self) -> String {
+    let mut out = String::new();
+    write_word(&mut out, self.parts(), TextContext::Unquoted);
+    out
+  }
+}
+
+fn write_list(out: &mut String, list: &SequentialList) {
+  write_list_with_comments(out, list, &[]);
+}
+
+fn write_list_with_comments(
+  out: &mut String,
+  list: &SequentialList,
+  comments: &[Comment],
+) {
+  let has_comments =
+    |index: usize| comments.iter().any(|comment| comment.item == index);
+  for (i, item) in list.items.iter().enumerate() {
+    if i > 0 {
+      let previous = &list.items[i - 1];
+      // a comment runs until the end of the line
+      out.push_str(if has_comments(i - 1) || has_comments(i) {
+        "\n"
+      } else if previous.is_async {
+        // an async item is already terminated by its `&`
+        " "
+      } else {
+        "; "
+      });
+    }
+    write_comments(out, comments, i, CommentPosition::Before);
+    write_sequence(out, &item.sequence);
+    if item.is_async {
+      out.push_str(" &");
+    }
+    write_comments(out, comments, i, CommentPosition::SameLine);
+    write_comments(out, comments, i, CommentPosition::After);
+  }
+}
+
+fn write_comments(
+  out: &mut String,
+  comments: &[Comment],
+  item: usize,
+  position: CommentPosition,
+) {
+  let comments = comments
+    .iter()
+    .filter(|comment| comment.item == item && comment.position == position);
+  for comment in comments {
+    match position {
+      CommentPosition::Before => {
+        out.push_str(&comment.text);
+        out.push('\n');
+      }
+      CommentPosition::SameLine => {
+        out.push(' ');
+        out.push_str(&comment.text);
+      }
+      CommentPosition::After => {
+        out.push('\n');
+        out.push_str(&comment.text);
+      }
+    }
+  }
+}
+
+/// Writes the list so that a keyword like `fi` can follow it.
+fn write_terminated_list(out: &mut String, list: &SequentialList) {
+  write_list(out, list);
+  if list.items.last().is_some_and(|item| !item.is_async) {
+    out.push(';');
+  }
+}
+
+fn write_sequence(out: &mut String, sequence: &Sequence) {
+  match sequence {
+    Sequence::ShellVar(var) => write_env_var(out, var),
+    Sequence::Pipeline(pipeline) => {
+      if pipeline.negated {
+        out.push_str("! ");
+      }
+      write_pipeline_inner(out, &pipeline.inner);
+    }
+    Sequence::BooleanList(list) => {
+      write_sequence(out, &list.current);
+      out.push(' ');
+      out.push_str(list.op.as_str());
+      out.push(' ');
+      write_sequence(out, &list.next);
+    }
+  }
+}
+
+fn write_pipeline_inner(out: &mut String, inner: &PipelineInner) {
+  match inner {
+    PipelineInner::Command(command) => write_command(out, command),
+    PipelineInner::PipeSequence(sequence) => {
+      write_command(out, &sequence.current);
+      out.push_str(match sequence.op {
+        PipeSequenceOperator::Stdout => " | ",
+        PipeSequenceOperator::StdoutStderr => " |& ",
+      });
+      write_pipeline_inner(out, &sequence.next);
+    }
+  }
+}
+
+fn write_command(out: &mut String, command: &Command) {
+  let start = out.len();
+  match &command.inner {
+    CommandInner::Simple(simple) => {
+      for var in &simple.env_vars {
+        push_separated(out, start);
+        write_env_var(out, var);
+      }
+      for arg in &simple.args {
+        push_separated(out, start);
+        if arg.parts().is_empty() {
+          out.push_str("''");
+        } else {
+          write_word(out, arg.parts(), TextContext::Unquoted);
+        }
+      }
+    }
+    CommandInner::Subshell(list) => {
+      out.push('(');
+      write_list(out, list);
+      out.push(')');
+    }
+    CommandInner::BraceGroup(list) => {
+      out.push_str("{ ");
+      write_terminated_list(out, list);
+      out.push_str(" }");
+    }
+    CommandInner::If(if_clause) => {
+      out.push_str("if ");
+      write_if_clause(out, if_clause);
+      out.push_str(" fi");
+    }
+    CommandInner::ArithmeticExpression(arithmetic) => {
+      out.push_str("((");
+      write_arithmetic(out, arithmetic);
+      out.push_str("))");
+    }
+  }
+  if let Some(redirect) = &command.redirect {
+    push_separated(out, start);
+    write_redirect(out, redirect);
+  }
+}
+
+/// Separates the next piece of a command from the previous one.
+fn push_separated(out: &mut String, start: usize) {
+  if out.len() > start {
+    out.push(' ');
+  }
+}
+
+fn write_if_clause(out: &mut String, if_clause: &IfClause) {
+  out.push_str("[[ ");
+  write_condition(out, &if_clause.condition.condition_inner);
+  out.push_str(" ]]; then ");
+  write_terminated_list(out, &if_clause.then_body);
+  match &if_clause.else_part {
+    Some(ElsePart::Elif(if_clause)) => {
+      out.push_str(" elif ");
+      write_if_clause(out, if_clause);
+    }
+    Some(ElsePart::Else(list)) => {
+      out.push_str(" else ");
+      write_terminated_list(out, list);
+    }
+    None => {}
+  }
+}
+
+fn write_condition(out: &mut String, condition: &ConditionInner) {
+  match condition {
+    ConditionInner::Binary { left, op, right } => {
+      write_word(out, left.parts(), TextContext::Unquoted);
+      out.push_str(match op {
+        BinaryOp::Equal => " == ",
+        BinaryOp::NotEqual => " != ",
+        BinaryOp::LessThan => " -lt ",
+        BinaryOp::LessThanOrEqual => " -le ",
+        BinaryOp::GreaterThan => " -gt ",
+        BinaryOp::GreaterThanOrEqual => " -ge ",
+        BinaryOp::NewerThan => " -nt ",
+        BinaryOp::OlderThan => " -ot ",
+        BinaryOp::SameFile => " -ef ",
+      });
+      write_word(out, right.parts(), TextContext::Unquoted);
+    }
+    ConditionInner::Unary { op, right } => {
+      if let Some(op) = op {
+        out.push_str(unary_op_str(op));
+        out.push(' ');
+      }
+      write_word(out, right.parts(), TextContext::Unquoted);
+    }
+    ConditionInner::Not(inner) => {
+      out.push_str("! ");
+      write_condition(out, inner);
+    }
+    ConditionInner::Logical { left, op, right } => {
+      write_condition(out, left);
+      out.push(' ');
+      out.push_str(op.as_str());
+      out.push(' ');
+      write_condition(out, right);
+    }
+  }
+}
+
+fn unary_op_str(op: &UnaryOp) -> &'static str {
+  match op {
+    UnaryOp::FileExists => "-e",
+    UnaryOp::BlockSpecial => "-b",
+    UnaryOp::CharSpecial => "-c",
+    UnaryOp::Directory => "-d",
+    UnaryOp::RegularFile => "-f",
+    UnaryOp::SetGroupId => "-g",
+    UnaryOp::SymbolicLink => "-L",
+    UnaryOp::StickyBit => "-k",
+    UnaryOp::NamedPipe => "-p",
+    UnaryOp::Readable => "-r",
+    UnaryOp::SizeNonZero => "-s",
+    UnaryOp::TerminalFd => "-t",
+    UnaryOp::SetUserId => "-u",
+    UnaryOp::Writable => "-w",
+    UnaryOp::Executable => "-x",
+    UnaryOp::OwnedByEffectiveGroupId => "-G",
+    UnaryOp::ModifiedSinceLastRead => "-N",
+    UnaryOp::OwnedByEffectiveUserId => "-O",
+    UnaryOp::Socket => "-S",
+    UnaryOp::NonEmptyString => "-n",
+    UnaryOp::EmptyString => "-z",
+    UnaryOp::VariableSet => "-v",
+    UnaryOp::VariableNameReference => "-R",
+  }
+}
+
+fn write_env_var(out: &mut String, var: &EnvVar) {
+  out.push_str(&var.name);
+  out.push('=');
+  write_word(out, var.value.parts(), TextContext::Unquoted);
+}
+
+fn write_redirect(out: &mut String, redirect: &Redirect) {
+  match &redirect.maybe_fd {
+    Some(RedirectFd::Fd(fd)) => out.push_str(&fd.to_string()),
+    Some(RedirectFd::StdoutStderr) => out.push('&'),
+    None => {}
+  }
+  out.push_str(match redirect.op {
+    RedirectOp::Input(RedirectOpInput::Redirect) => "<",
+    RedirectOp::Output(RedirectOpOutput::Overwrite) => ">",
+    RedirectOp::Output(RedirectOpOutput::Append) => ">>",
+    RedirectOp::Output(RedirectOpOutput::Clobber) => ">|",
+  });
+  match &redirect.io_file {
+    IoFile::Fd(fd) => {
+      out.push('&');
+      out.push_str(&fd.to_string());
+    }
+    IoFile::Word(word) => {
+      out.push(' ');
+      write_word(out, word.parts(), TextContext::Unquoted);
+    }
+  }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum TextContext {
+  /// A word outside of quotes.
+  Unquoted,
+  /// Within double quotes.
+  DoubleQuoted,
+  /// The word of a variable modifier (ex. `${VAR:-word}`).
+  Parameter,
+  /// Within an extended glob (ex. `@(a|$B)`).
+  ExtGlob,
+}
+
+fn write_word(out: &mut String, parts: &[WordPart], context: TextContext) {
+  for (i, part) in parts.iter().enumerate() {
+    match part {
+      WordPart::Text(text) => write_text(out, text, context),
+      WordPart::Variable(name, modifier) => write_variable(
+        out,
+        name,
+        modifier.as_deref(),
+        continues_name(parts.get(i + 1)),
+      ),
+      WordPart::LineNumber(_) => {
+        write_variable(out, "LINENO", None, continues_name(parts.get(i + 1)))
+      }
+      WordPart::Command(list) => {
+        out.push_str("$(");
+        write_list(out, list);
+        out.push(')');
+      }
+      WordPart::Quoted(parts) => write_quoted(out, parts),
+      WordPart::ExtGlob(parts) => write_word(out, parts, TextContext::ExtGlob),
+      WordPart::Tilde(tilde) => {
+        out.push('~');
+        if let Some(user) = &tilde.user {
+          out.push_str(user);
+        }
+      }
+      WordPart::Arithmetic(arithmetic) => {
+        out.push_str("$((");
+        write_arithmetic(out, arithmetic);
+        out.push_str("))");
+      }
+      WordPart::ExitStatus => out.push_str("$?"),
+    }
+  }
+}
+
+/// Whether the part would be read as more of a preceding variable's name.
+fn continues_name(part: Option<&WordPart>) -> bool {
+  match part {
+    Some(WordPart::Text(text)) => {
+      text.starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_')
+    }
+    _ => false,
+  }
+}
+
+fn write_quoted(out: &mut String, parts: &[WordPart]) {
+  match parts {
+    // single quotes keep the text as is
+    [WordPart::Text(text)] if !text.contains('\'') => {
+      out.push('\'');
+      out.push_str(text);
+      out.push('\'');
+    }
+    [] => out.push_str("''"),
+    _ => {
+      out.push('"');
+      write_word(out, parts, TextContext::DoubleQuoted);
+      out.push('"');
+    }
+  }
+}
+
+fn write_variable(
+  out: &mut String,
+  name: &str,
+  modifier: Option<&VariableModifier>,
+  next_continues_name: bool,
+) {
+  let Some(modifier) = modifier else {
+    if next_continues_name {
+      out.push_str(&format!("${{{name}}}"));
+    } else {
+      out.push('$');
+      out.push_str(name);
+    }
+    return;
+  };
+
+  out.push_str("${");
+  if *modifier == VariableModifier::Length {
+    out.push('#');
+    out.push_str(name);
+    out.push('}');
+    return;
+  }
+  out.push_str(name);
+  match modifier {
+    VariableModifier::Substring { begin, length } => {
+      out.push(':');
+      write_word(out, begin.parts(), TextContext::Parameter);
+      if let Some(length) = length {
+        out.push(':');
+        write_word(out, length.parts(), TextContext::Parameter);
+      }
+    }
+    VariableModifier::DefaultValue { value, colon } => {
+      out.push_str(if *colon { ":-" } else { "-" });
+      write_word(out, value.parts(), TextContext::Parameter);
+    }
+    VariableModifier::AssignDefault { value, colon } => {
+      out.push_str(if *colon { ":=" } else { "=" });
+      write_word(out, value.parts(), TextContext::Parameter);
+    }
+    VariableModifier::AlternateValue { value, colon } => {
+      out.push_str(if *colon { ":+" } else { "+" });
+      write_word(out, value.parts(), TextContext::Parameter);
+    }
+    VariableModifier::Length => unreachable!(),
+  }
+  out.push('}');
+}
+
+fn write_text(out: &mut String, text: &str, context: TextContext) {
+  match context {
+    // the parser keeps the escapes of double quoted text and the pattern's
+    // text is unescaped, so they're written back as is
+    TextContext::DoubleQuoted | TextContext::ExtGlob => out.push_str(text),
+    TextContext::Unquoted | TextContext::Parameter => {
+      for c in text.chars() {
+        match (c, context) {
+          ('$', _) | ('}', TextContext::Parameter) => {
+            out.push('\\');
+            out.push(c);
+          }
+          (' ' | '"' | '(' | ')' | '`', TextContext::Unquoted) => {
+            out.push('\\');
+            out.push(c);
+          }
+          // these have no escape, so they're quoted instead
+          ('\'', _) => out.push_str("\"'\""),
+          (
+            '\t' | '\n' | '<' | '>' | '|' | '&' | ';' | '\\',
+            TextContext::Unquoted,
+          )
+          | ('"' | ':', TextContext::Parameter) => {
+            out.push('\'');
+            out.push(c);
+            out.push('\'');
+          }
+          _ => out.push(c),
+        }
+      }
+    }
+  }
+}
+
+fn write_arithmetic(out: &mut String, arithmetic: &Arithmetic) {
+  for (i, part) in arithmetic.parts.iter().enumerate() {
+    if i > 0 {
+      out.push_str(", ");
+    }
+    write_arithmetic_part(out, part);
+  }
+}
+
+fn write_arithmetic_part(out: &mut String, part: &ArithmeticPart) {
+  // the parentheses are in the AST, so the parts are written as is
+  match part {
+    ArithmeticPart::ParenthesesExpr(arithmetic) => {
+      out.push('(');
+      write_arithmetic(out, arithmetic);
+      out.push(')');
+    }
+    ArithmeticPart::VariableAssignment { name, op, value } => {
+      out.push_str(name);
+      out.push_str(match op {
+        AssignmentOp::Assign => " = ",
+        AssignmentOp::MultiplyAssign => " *= ",
+        AssignmentOp::DivideAssign => " /= ",
+        AssignmentOp::ModuloAssign => " %= ",
+        AssignmentOp::AddAssign => " += ",
+        AssignmentOp::SubtractAssign => " -= ",
+        AssignmentOp::LeftShiftAssign => " <<= ",
+        AssignmentOp::RightShiftAssign => " >>= ",
+        AssignmentOp::BitwiseAndAssign => " &= ",
+        AssignmentOp::BitwiseXorAssign => " ^= ",
+        AssignmentOp::BitwiseOrAssign => " |= ",
+      });
+      write_arithmetic_part(out, value);
+    }
+    ArithmeticPart::TripleConditionalExpr {
+      condition,
+      true_expr,
+      false_expr,
+    } => {
+      write_arithmetic_part(out, condition);
+      out.push_str(" ? ");
+      write_arithmetic_part(out, true_expr);
+      out.push_str(" : ");
+      write_arithmetic_part(out, false_expr);
+    }
+    ArithmeticPart::BinaryArithmeticExpr {
+      left,
+      operator,
+      right,
+    } => {
+      write_arithmetic_part(out, left);
+      out.push_str(match operator {
+        BinaryArithmeticOp::Add => " + ",
+        BinaryArithmeticOp::Subtract => " - ",
+        BinaryArithmeticOp::Multiply => " * ",
+        BinaryArithmeticOp::Divide => " / ",
+        BinaryArithmeticOp::Modulo => " % ",
+        BinaryArithmeticOp::Power => " ** ",
+        BinaryArithmeticOp::LeftShift => " << ",
+        BinaryArithmeticOp::RightShift => " >> ",
+        BinaryArithmeticOp::BitwiseAnd => " & ",
+        BinaryArithmeticOp::BitwiseXor => " ^ ",
+        BinaryArithmeticOp::BitwiseOr => " | ",
+        BinaryArithmeticOp::LogicalAnd => " && ",
+        BinaryArithmeticOp::LogicalOr => " || ",
+      });
+      write_arithmetic_part(out, right);
+    }
+    ArithmeticPart::BinaryConditionalExpr {
+      left,
+      operator,
+      right,
+    } => {
+      write_arithmetic_part(out, left);
+      out.push_str(match operator {
+        BinaryOp::Equal => " == ",
+        BinaryOp::NotEqual => " != ",
+        BinaryOp::LessThan => " < ",
+        BinaryOp::LessThanOrEqual => " <= ",
+        BinaryOp::GreaterThan => " > ",
+        BinaryOp::GreaterThanOrEqual => " >= ",
+        BinaryOp::NewerThan => " -nt ",
+        BinaryOp::OlderThan => " -ot ",
+        BinaryOp::SameFile => " -ef ",
+      });
+      write_arithmetic_part(out, right);
+    }
+    ArithmeticPart::UnaryArithmeticExpr { operator, operand } => {
+      out.push(match operator {
+        UnaryArithmeticOp::Plus => '+',
+        UnaryArithmeticOp::Minus => '-',
+        UnaryArithmeticOp::LogicalNot => '!',
+        UnaryArithmeticOp::BitwiseNot => '~',
+      });
+      write_arithmetic_part(out, operand);
+    }
+    ArithmeticPart::PreArithmeticExpr { operator, operand } => {
+      out.push_str(match operator {
+        PostArithmeticOp::Increment => "++",
+        PostArithmeticOp::Decrement => "--",
+      });
+      write_arithmetic_part(out, operand);
+    }
+    ArithmeticPart::PostArithmeticExpr { operand, operator } => {
+      write_arithmetic_part(out, operand);
+      out.push_str(match operator {
+        PostArithmeticOp::Increment => "++",
+        PostArithmeticOp::Decrement => "--",
+      });
+    }
+    ArithmeticPart::Variable(name) => out.push_str(name),
+    ArithmeticPart::Number(number) => out.push_str(number),
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use crate::parser::parse;
+  use pretty_assertions::assert_eq;
+
+  #[track_caller]
+  fn assert_round_trips(input: &str, expected: &str) {
+    let list = parse(input).unwrap();
+    let text = list.to_shell_string();
+    assert_eq!(text, expected);
+    assert_eq!(parse(&text).unwrap(), list, "re-parsing {text}");
+  }
+
+  #[test]
+  fn round_trips_commands() {
+    assert_round_trips("echo   1", "echo 1");
+    assert_round_trips("A=1 B=2 cmd; C=3", "A=1 B=2 cmd; C=3");
+    assert_round_trips("a&&b||c", "a && b || c");
+    assert_round_trips("sleep 1 & echo 2;", "sleep 1 & echo 2");
+    assert_round_trips("! a | b |& c", "! a | b |& c");
+    assert_round_trips("(cd dir && ls) | cat", "(cd dir && ls) | cat");
+    assert_round_trips("(cd dir) 2> err", "(cd dir) 2> err");
+    assert_round_trips("{ cd dir\nls &\n} > out", "{ cd dir; ls & } > out");
+    assert_round_trips(
+      "((a = 1 + (2 * b), c - 1))",
+      "((a = 1 + (2 * b), c - 1))",
+    );
+  }
+
+  #[test]
+  fn prints_comments() {
+    use crate::parser::parse_with_comments;
+
+    let input = "# setup\nA=1 # one\necho $A; echo b\n# done";
+    let print = |input: &str| {
+      let (list, comments) = parse_with_comments(input).unwrap();
+      list.to_shell_string_with_comments(&comments)
+    };
+    let text = print(input);
+    assert_eq!(text, "# setup\nA=1 # one\necho $A\necho b\n# done");
+    assert_eq!(print(&text), text);
+    let (list, _) = parse_with_comments(input).unwrap();
+    assert_eq!(list.to_shell_string(), "A=1; echo $A; echo b");
+  }
+
+  #[test]
+  fn round_trips_redirects() {
+    assert_round_trips("cmd > out.txt", "cmd > out.txt");
+    assert_round_trips("cmd 2>> err.txt", "cmd 2>> err.txt");
+    assert_round_trips("cmd &> all.txt", "cmd &> all.txt");
+    assert_round_trips("cmd >| out.txt", "cmd >| out.txt");
+    assert_round_trips("cmd 2>&1", "cmd 2>&1");
+    assert_round_trips("cmd < in.txt", "cmd < in.txt");
+    assert_round_trips("echo $(< file.txt)", "echo $(< file.txt)");
+  }
+
+  #[test]
+  fn round_trips_words() {
+    assert_round_trips(
+      "echo $LINENO ${LINENO}x \"$LINENO\"",
+      "echo $LINENO ${LINENO}x \"$LINENO\"",
+    );
+    assert_round_trips("echo 'a b' \"c $D\"", "echo 'a b' \"c $D\"");
+    assert_round_trips(
+      r#"echo "it's" 'say "hi"'"#,
+      r#"echo "it's" 'say "hi"'"#,
+    );
+    assert_round_trips(
+      r#"echo a\ b \$HOME \(x\)"#,
+      r#"echo a\ b \$HOME \(x\)"#,
+    );
+    assert_round_trips("echo ${A}b $A-b", "echo ${A}b $A-b");
+    assert_round_trips(
+      "echo ${A:-d} ${A:=$B} ${A:+x} ${A:1:2} ${#A}",
+      "echo ${A:-d} ${A:=$B} ${A:+x} ${A:1:2} ${#A}",
+    );
+    assert_round_trips(
+      "echo ${A-d} ${A=$B} ${A+x}",
+      "echo ${A-d} ${A=$B} ${A+x}",
+    );
+    assert_round_trips("echo $(echo $?) ~ ~/dir", "echo $(echo $?) ~ ~/dir");
+    assert_round_trips("echo $((1 + 2))", "echo $((1 + 2))");
+    assert_round_trips("echo $((i++ + --j))", "echo $((i++ + --j))");
+    assert_round_trips(
+      "echo !(*.txt) a@(b|+(c))d @\\(x\\)",
+      "echo !(*.txt) a@(b|+(c))d @\\(x\\)",
+    );
+    assert_round_trips("echo @($A|${B}c|*.$C)", "echo @($A|${B}c|*.$C)");
+  }
+
+  #[test]
+  fn round_trips_if() {
+    assert_round_trips(
+      "if [[ -f file && $A == 1 ]]; then echo a; elif [[ ! -z $B ]]; then echo b; else echo c; fi",
+      "if [[ -f file && $A == 1 ]]; then echo a; elif [[ ! -z $B ]]; then echo b; else echo c; fi",
+    );
+    assert_round_trips(
+      "if [[ $A -le 2 ]]\nthen\n  echo a\n  echo b\nfi",
+      "if [[ $A -le 2 ]]; then echo a; echo b; fi",
+    );
+    assert_round_trips(
+      "if [[ a -nt b || a -ot b || a -ef b ]]; then echo a; fi",
+      "if [[ a -nt b || a -ot b || a -ef b ]]; then echo a; fi",
+    );
+  }
+}
@@ -1,12 +1,14 @@
 // Copyright 2018-2024 the Deno authors. MIT license.
 
 use std::borrow::Cow;
+use std::collections::HashMap;
 use std::io::BufRead;
 use std::io::BufReader;
 use std::io::Read;
 use std::path::Path;
 use std::path::PathBuf;
 
+use crate::shell::types::EnvChange;
 use crate::shell::types::ShellState;
 use crate::ExecutableCommand;
 use crate::ExecuteResult;
@@ -33,6 +35,13 @@ pub fn execute_unresolved_command_name(
       match resolve_command(&command_name, &mut context, &args).await {
         Ok(command_path) => command_path,
         Err(ResolveCommandError::CommandPath(err)) => {
+          if let ResolveCommandPathError::CommandNotFound(name) = &err {
+            if let Some(hook) = context.state.command_not_found_hook() {
+              if let Some(result) = hook(name, &args) {
+                return result;
+              }
+            }
+          }
           let _ = context.stderr.write_line(&format!("{}", err));
           return ExecuteResult::Continue(
             err.exit_code(),
@@ -73,7 +82,7 @@ enum CommandName {
 
 struct ResolvedCommand<'a> {
   command_name: CommandName,
-  args: Cow<'a, Vec<String>>,
+  args: Cow<'a, [String]>,
 }
 
 #[derive(Error, Debug)]
@@ -110,7 +119,7 @@ impl FailedShebangError {
 async fn resolve_command<'a>(
   command_name: &UnresolvedCommandName,
   context: &mut ShellCommandContext,
-  original_args: &'a Vec<String>,
+  original_args: &'a [String],
 ) -> Result<ResolvedCommand<'a>, ResolveCommandError> {
   let command_path = match resolve_command_path(
     &command_name.name,
@@ -121,6 +130,20 @@ async fn resolve_command<'a>(
     Err(err) => return Err(err.into()),
   };
 
+  if let Some((executable, env_vars)) =
+    resolve_command_wrapper(&command_path, &context.state)
+  {
+    for (name, value) in env_vars {
+      context
+        .state
+        .apply_change(&EnvChange::SetEnvVar(name, value));
+    }
+    return Ok(ResolvedCommand {
+      command_name: CommandName::Resolved(executable),
+      args: Cow::Borrowed(original_args),
+    });
+  }
+
   // only bother checking for a shebang when the path has a slash
   // in it because for global commands someone on Windows likely
   // won't have a script with a shebang in it on Windows
@@ -159,6 +182,19 @@ async fn resolve_command<'a>(
   })
 }
 
+/// Gets the executable to start instead of a registered wrapper script
+/// along with the variables it runs with, which are the exported
+/// variables of the shell with the ones of the wrapper on top.
+fn resolve_command_wrapper(
+  command_path: &Path,
+  state: &ShellState,
+) -> Option<(PathBuf, HashMap<String, String>)> {
+  let wrapper = state.command_wrapper(command_path)?;
+  let mut env_vars = state.env_vars().clone();
+  env_vars.extend(wrapper.env_vars.clone());
+  Some((wrapper.executable.clone(), env_vars))
+}
+
 async fn parse_shebang_args(
   text: &str,
   context: &mut ShellCommandContext,
@@ -194,6 +230,7 @@ async fn parse_shebang_args(
   let cmd = match cmd.inner {
     crate::parser::CommandInner::Simple(cmd) => cmd,
     crate::parser::CommandInner::Subshell(_) => return err_unsupported(text),
+    crate::parser::CommandInner::BraceGroup(_) => return err_unsupported(text),
     crate::parser::CommandInner::If(_) => return err_unsupported(text),
     crate::parser::CommandInner::ArithmeticExpression(_) => {
       return err_unsupported(text)
@@ -282,23 +319,13 @@ fn resolve_command_path_inner(
   }
 
   // now search based on the current environment state
-  let mut search_dirs = vec![base_dir.to_path_buf()];
-  if let Some(path) = state.get_var("PATH") {
-    for folder in path.split(if cfg!(windows) { ';' } else { ':' }) {
-      search_dirs.push(PathBuf::from(folder));
-    }
-  }
   let path_exts = if cfg!(windows) {
     let uc_command_name = command_name.to_uppercase();
     let path_ext = state
       .get_var("PATHEXT")
       .map(|s| s.as_str())
       .unwrap_or(".EXE;.CMD;.BAT;.COM");
-    let command_exts = path_ext
-      .split(';')
-      .map(|s| s.trim().to_uppercase())
-      .filter(|s| !s.is_empty())
-      .collect::<Vec<_>>();
+    let command_exts = path_extensions(path_ext);
     if command_exts.is_empty()
       || command_exts
         .iter()
@@ -312,7 +339,7 @@ fn resolve_command_path_inner(
     None
   };
 
-  for search_dir in search_dirs {
+  let find_in_dir = |search_dir: &Path| {
     let paths = if let Some(path_exts) = &path_exts {
       let mut paths = Vec::new();
       for path_ext in path_exts {
@@ -322,13 +349,25 @@ fn resolve_command_path_inner(
     } else {
       vec![search_dir.join(command_name)]
     };
-    for path in paths {
+    paths.into_iter().find(|path| {
       // don't use tokio::fs::metadata here as it was never returning
       // in some circumstances for some reason
-      if let Ok(metadata) = std::fs::metadata(&path) {
-        if metadata.is_file() {
-          return Ok(path);
-        }
+      std::fs::metadata(path).is_ok_and(|metadata| metadata.is_file())
+    })
+  };
+
+  if let Some(path) = find_in_dir(base_dir) {
+    return Ok(path);
+  }
+  if let Some(search_path) = state.get_var("PATH") {
+    // the locations found in `$PATH` are remembered (see `hash`)
+    if let Some(path) = state.hashed_command_path(command_name, search_path) {
+      return Ok(path);
+    }
+    for folder in search_path.split(if cfg!(windows) { ';' } else { ':' }) {
+      if let Some(path) = find_in_dir(Path::new(folder)) {
+        state.hash_command(command_name, &path, search_path);
+        return Ok(path);
       }
     }
   }
@@ -337,6 +376,19 @@ fn resolve_command_path_inner(
   ))
 }
 
+/// Splits `PATHEXT` into the extensions to try, with the batch scripts
+/// last so that `foo.exe` is preferred over a slower `foo.bat` wrapper.
+fn path_extensions(path_ext: &str) -> Vec<String> {
+  let mut exts = path_ext
+    .split(';')
+    .map(|s| s.trim().to_uppercase())
+    .filter(|s| !s.is_empty())
+    .collect::<Vec<_>>();
+  // the sort is stable, so the order of `PATHEXT` is kept otherwise
+  exts.sort_by_key(|ext| ext == ".BAT" || ext == ".CMD");
+  exts
+}
+
 struct Shebang {
   string_split: bool,
   command: String,
@@ -383,6 +435,7 @@ fn resolve_shebang(
 #[cfg(test)]
 mod local_test {
   use super::*;
+  use crate::CommandWrapper;
 
   #[test]
   fn should_resolve_current_exe_path_for_deno() {
@@ -405,6 +458,85 @@ mod local_test {
     assert_eq!(path, PathBuf::from("/bin/deno.exe"));
   }
 
+  #[test]
+  fn should_resolve_wrapper_to_its_executable() {
+    let cwd = std::env::current_dir().unwrap();
+    let mut state = ShellState::new(
+      HashMap::from([
+        ("PATH".to_string(), "/bin".to_string()),
+        ("FOO".to_string(), "shell".to_string()),
+      ]),
+      &cwd,
+      Default::default(),
+    );
+    let wrapper = cwd.join("env/bin/vim.bat");
+    assert_eq!(resolve_command_wrapper(&wrapper, &state), None);
+
+    state.set_command_wrapper(
+      &wrapper,
+      CommandWrapper {
+        executable: cwd.join("env/Library/bin/vim.exe"),
+        env_vars: HashMap::from([
+          ("FOO".to_string(), "wrapper".to_string()),
+          ("CONDA_PREFIX".to_string(), "env".to_string()),
+        ]),
+      },
+    );
+    let (executable, env_vars) =
+      resolve_command_wrapper(&wrapper, &state).unwrap();
+    assert_eq!(executable, cwd.join("env/Library/bin/vim.exe"));
+    let mut env_vars = env_vars.into_iter().collect::<Vec<_>>();
+    env_vars.sort();
+    assert_eq!(
+      env_vars,
+      vec![
+        ("CONDA_PREFIX".to_string(), "env".to_string()),
+        ("FOO".to_string(), "wrapper".to_string()),
+        ("PATH".to_string(), "/bin".to_string()),
+        // `$PWD` is set by the shell
+        ("PWD".to_string(), cwd.display().to_string()),
+      ]
+    );
+  }
+
+  #[test]
+  fn should_try_batch_scripts_last() {
+    assert_eq!(
+      path_extensions(".COM;.BAT;.CMD;.exe;;.VBS"),
+      vec![".COM", ".EXE", ".VBS", ".BAT", ".CMD"]
+    );
+  }
+
+  #[cfg(windows)]
+  #[test]
+  fn should_prefer_exe_over_bat() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let dir = temp_dir.path();
+    std::fs::write(dir.join("foo.bat"), "").unwrap();
+    std::fs::write(dir.join("foo.exe"), "").unwrap();
+    let state = ShellState::new(
+      HashMap::from([
+        ("PATH".to_string(), dir.display().to_string()),
+        ("PATHEXT".to_string(), ".COM;.BAT;.CMD;.EXE".to_string()),
+      ]),
+      &std::env::current_dir().unwrap(),
+      Default::default(),
+    );
+    let cwd = std::env::current_dir().unwrap();
+    let path = resolve_command_path_inner("foo", &cwd, &state, || {
+      Ok(PathBuf::from("/bin/deno"))
+    })
+    .unwrap();
+    assert_eq!(path, dir.join("foo.exe"));
+
+    // an explicit extension is used as is
+    let path = resolve_command_path_inner("foo.bat", &cwd, &state, || {
+      Ok(PathBuf::from("/bin/deno"))
+    })
+    .unwrap();
+    assert_eq!(path, dir.join("foo.bat"));
+  }
+
   #[test]
   fn should_error_on_unknown_command() {
     let cwd = std::env::current_dir().unwrap();
@@ -26,8 +26,7 @@ impl ShellCommand for CatCommand {
     let result = match execute_cat(context) {
       Ok(result) => result,
       Err(err) => {
-        let _ = stderr.write_line(&format!("cat: {err}"));
-        ExecuteResult::from_exit_code(1)
+        ExecuteResult::from_exit_code(stderr.write_command_error("cat", &err))
       }
     };
     Box::pin(futures::future::ready(result))
@@ -24,37 +24,53 @@ impl ShellCommand for CdCommand {
     &self,
     mut context: ShellCommandContext,
   ) -> LocalBoxFuture<'static, ExecuteResult> {
-    let result = match execute_cd(context.state.cwd(), context.args) {
-      Ok(new_dir) => {
-        ExecuteResult::Continue(0, vec![EnvChange::Cd(new_dir)], Vec::new())
-      }
-      Err(err) => {
-        let _ = context.stderr.write_line(&format!("cd: {err}"));
-        ExecuteResult::Continue(1, Vec::new(), Vec::new())
-      }
+    // like `~`, `$HOME` is preferred over the user's home directory
+    let home = match context.state.get_var("HOME") {
+      Some(home) => Some(home.clone()),
+      None => dirs::home_dir().map(|dir| dir.display().to_string()),
     };
+    let result =
+      match execute_cd(context.state.cwd(), home.as_deref(), context.args) {
+        Ok(new_dir) => {
+          ExecuteResult::Continue(0, vec![EnvChange::Cd(new_dir)], Vec::new())
+        }
+        Err(err) => ExecuteResult::from_exit_code(
+          context.stderr.write_command_error("cd", &err),
+        ),
+      };
     Box::pin(futures::future::ready(result))
   }
 }
 
-fn execute_cd(cwd: &Path, args: Vec<String>) -> Result<PathBuf> {
-  // create a new vector to avoid modifying the original
-  let mut args = args;
-  if args.is_empty() {
-    // append `~` to args
-    args.push("~".to_string());
-  }
-  let path = parse_args(args.clone())?;
-  let new_dir = if path == "~" {
-    dirs::home_dir()
-      .ok_or_else(|| miette::miette!("Home directory not found"))?
-  } else {
-    cwd.join(&path)
+/// Resolves the directory to change to, bare `cd` goes to `home`.
+///
+/// By default the path is logical, `..` removes the previous component and
+/// symlinks are kept in `$PWD`. With `-P` all symlinks are resolved.
+fn execute_cd(
+  cwd: &Path,
+  home: Option<&str>,
+  args: Vec<String>,
+) -> Result<PathBuf> {
+  let flags = parse_args(args)?;
+  let path = match flags.path {
+    Some(path) => path,
+    None => match home {
+      Some(home) => home.to_string(),
+      None => bail!("HOME not set"),
+    },
   };
-  let new_dir = match new_dir.parse_dot() {
-    Ok(path) => path.to_path_buf(),
-    // fallback to canonicalize path just in case
-    Err(_) => fs_util::canonicalize_path(&new_dir)?,
+  let new_dir = cwd.join(&path);
+  let new_dir = if flags.physical {
+    match fs_util::canonicalize_path(&new_dir) {
+      Ok(new_dir) => new_dir,
+      Err(_) => bail!("{}: Not a directory", path),
+    }
+  } else {
+    match new_dir.parse_dot() {
+      Ok(path) => path.to_path_buf(),
+      // fallback to canonicalize path just in case
+      Err(_) => fs_util::canonicalize_path(&new_dir)?,
+    }
   };
   if !new_dir.is_dir() {
     bail!("{}: Not a directory", path)
@@ -62,27 +78,36 @@ fn execute_cd(cwd: &Path, args: Vec<String>) -> Result<PathBuf> {
   Ok(new_dir)
 }
 
-fn parse_args(args: Vec<String>) -> Result<String> {
+#[derive(Debug, PartialEq)]
+struct CdFlags {
+  /// `-P`, resolves the symlinks instead of keeping them (`-L`)
+  physical: bool,
+  path: Option<String>,
+}
+
+fn parse_args(args: Vec<String>) -> Result<CdFlags> {
   let args = parse_arg_kinds(&args);
+  let mut physical = false;
   let mut paths = Vec::new();
   for arg in args {
     match arg {
       ArgKind::Arg(arg) => {
         paths.push(arg);
       }
+      ArgKind::ShortFlag('L') => physical = false,
+      ArgKind::ShortFlag('P') => physical = true,
       _ => arg.bail_unsupported()?,
     }
   }
 
   if paths.len() > 1 {
     bail!("too many arguments")
-  } else if paths.is_empty() {
-    // not the case in actual cd, but it is most likely
-    // an error if someone does this in deno task
-    bail!("expected at least 1 argument")
   }
 
-  Ok(paths.remove(0).to_string())
+  Ok(CdFlags {
+    physical,
+    path: paths.pop().map(|path| path.to_string()),
+  })
 }
 
 #[cfg(test)]
@@ -94,7 +119,20 @@ mod test {
 
   #[test]
   fn parses_args() {
-    assert_eq!(parse_args(vec!["test".to_string()]).unwrap(), "test");
+    assert_eq!(
+      parse_args(vec!["test".to_string()]).unwrap(),
+      CdFlags {
+        physical: false,
+        path: Some("test".to_string()),
+      }
+    );
+    assert_eq!(
+      parse_args(vec!["-L".to_string(), "-P".to_string()]).unwrap(),
+      CdFlags {
+        physical: true,
+        path: None,
+      }
+    );
     assert_eq!(
       parse_args(vec!["a".to_string(), "b".to_string()])
         .err()
@@ -103,8 +141,11 @@ mod test {
       "too many arguments"
     );
     assert_eq!(
-      parse_args(vec![]).err().unwrap().to_string(),
-      "expected at least 1 argument"
+      parse_args(vec![]).unwrap(),
+      CdFlags {
+        physical: false,
+        path: None,
+      }
     );
     assert_eq!(
       parse_args(vec!["-a".to_string()])
@@ -129,7 +170,7 @@ mod test {
 
     // non-existent
     assert_eq!(
-      execute_cd(&dir_path, vec!["non-existent".to_string()])
+      execute_cd(&dir_path, None, vec!["non-existent".to_string()])
         .err()
         .unwrap()
         .to_string(),
@@ -139,7 +180,7 @@ mod test {
     // existent file
     fs::write(dir_path.join("file.txt"), "").unwrap();
     assert_eq!(
-      execute_cd(&dir_path, vec!["file.txt".to_string()])
+      execute_cd(&dir_path, None, vec!["file.txt".to_string()])
         .err()
         .unwrap()
         .to_string(),
@@ -150,8 +191,47 @@ mod test {
     let sub_dir_path = dir_path.join("sub_dir");
     fs::create_dir(&sub_dir_path).unwrap();
     assert_eq!(
-      execute_cd(&dir_path, vec!["sub_dir".to_string()]).unwrap(),
+      execute_cd(&dir_path, None, vec!["sub_dir".to_string()]).unwrap(),
       sub_dir_path
     );
+
+    // no argument
+    let home = sub_dir_path.to_string_lossy();
+    assert_eq!(
+      execute_cd(&dir_path, Some(&home), vec![]).unwrap(),
+      sub_dir_path
+    );
+    assert_eq!(
+      execute_cd(&dir_path, None, vec![])
+        .err()
+        .unwrap()
+        .to_string(),
+      "HOME not set"
+    );
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn keeps_symlinks_unless_physical() {
+    let dir = tempdir().unwrap();
+    let dir_path = fs_util::canonicalize_path(dir.path()).unwrap();
+    fs::create_dir(dir_path.join("main")).unwrap();
+    std::os::unix::fs::symlink(dir_path.join("main"), dir_path.join("link"))
+      .unwrap();
+
+    let link_path = dir_path.join("link");
+    assert_eq!(
+      execute_cd(&dir_path, None, vec!["link".to_string()]).unwrap(),
+      link_path
+    );
+    assert_eq!(
+      execute_cd(&link_path, None, vec!["..".to_string()]).unwrap(),
+      dir_path
+    );
+    assert_eq!(
+      execute_cd(&dir_path, None, vec!["-P".to_string(), "link".to_string()])
+        .unwrap(),
+      dir_path.join("main")
+    );
   }
 }
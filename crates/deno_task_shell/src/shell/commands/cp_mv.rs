@@ -45,8 +45,7 @@ async fn cp_command(
   match execute_cp(cwd, args).await {
     Ok(()) => ExecuteResult::from_exit_code(0),
     Err(err) => {
-      let _ = stderr.write_line(&format!("cp: {err}"));
-      ExecuteResult::from_exit_code(1)
+      ExecuteResult::from_exit_code(stderr.write_command_error("cp", &err))
     }
   }
 }
@@ -194,8 +193,7 @@ async fn mv_command(
   match execute_mv(cwd, args).await {
     Ok(()) => ExecuteResult::Continue(0, Vec::new(), Vec::new()),
     Err(err) => {
-      let _ = stderr.write_line(&format!("mv: {err}"));
-      ExecuteResult::Continue(1, Vec::new(), Vec::new())
+      ExecuteResult::from_exit_code(stderr.write_command_error("mv", &err))
     }
   }
 }
@@ -0,0 +1,136 @@
+// Copyright 2018-2024 the Deno authors. MIT license.
+
+use futures::future::LocalBoxFuture;
+use futures::FutureExt;
+use miette::bail;
+use miette::Result;
+
+use crate::shell::execute::evaluate_integer_value;
+use crate::shell::types::EnvChange;
+use crate::shell::types::ExecuteResult;
+use crate::shell::types::ShellState;
+
+use super::args::parse_arg_kinds;
+use super::args::ArgKind;
+use super::ShellCommand;
+use super::ShellCommandContext;
+
+pub struct DeclareCommand;
+
+impl ShellCommand for DeclareCommand {
+  fn execute(
+    &self,
+    mut context: ShellCommandContext,
+  ) -> LocalBoxFuture<'static, ExecuteResult> {
+    async move {
+      match execute_declare(context.args, &mut context.state).await {
+        Ok(changes) => ExecuteResult::Continue(0, changes, Vec::new()),
+        Err(err) => ExecuteResult::from_exit_code(
+          context.stderr.write_command_error("declare", &err),
+        ),
+      }
+    }
+    .boxed_local()
+  }
+}
+
+async fn execute_declare(
+  args: Vec<String>,
+  state: &mut ShellState,
+) -> Result<Vec<EnvChange>> {
+  let flags = parse_args(args)?;
+  let mut changes = Vec::new();
+  for (name, value) in flags.vars {
+    if let Some(integer) = flags.integer {
+      changes.push(EnvChange::SetIntegerAttribute(name.clone(), integer));
+    }
+    if let Some(value) = value {
+      let is_integer =
+        flags.integer.unwrap_or_else(|| state.is_integer_var(&name));
+      let value = if is_integer {
+        evaluate_integer_value(&value, state).await?
+      } else {
+        value
+      };
+      changes.push(EnvChange::SetShellVar(name, value));
+    }
+  }
+  Ok(changes)
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct DeclareFlags {
+  /// `Some(true)` for `-i` and `Some(false)` for `+i`
+  integer: Option<bool>,
+  vars: Vec<(String, Option<String>)>,
+}
+
+fn parse_args(args: Vec<String>) -> Result<DeclareFlags> {
+  let mut result = DeclareFlags::default();
+
+  for arg in parse_arg_kinds(&args) {
+    match arg {
+      ArgKind::ShortFlag('i') => result.integer = Some(true),
+      ArgKind::PlusFlag('i') => result.integer = Some(false),
+      ArgKind::Arg(arg) => {
+        let (name, value) = match arg.split_once('=') {
+          Some((name, value)) => (name, Some(value.to_string())),
+          None => (arg, None),
+        };
+        if !is_valid_name(name) {
+          bail!("'{}': not a valid identifier", arg);
+        }
+        result.vars.push((name.to_string(), value));
+      }
+      _ => arg.bail_unsupported()?,
+    }
+  }
+
+  Ok(result)
+}
+
+fn is_valid_name(name: &str) -> bool {
+  let mut chars = name.chars();
+  chars
+    .next()
+    .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+    && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use pretty_assertions::assert_eq;
+
+  #[test]
+  fn parses_args() {
+    assert_eq!(
+      parse_args(vec!["-i".to_string(), "x".to_string()]).unwrap(),
+      DeclareFlags {
+        integer: Some(true),
+        vars: vec![("x".to_string(), None)],
+      }
+    );
+    assert_eq!(
+      parse_args(vec!["+i".to_string(), "x=3+4".to_string()]).unwrap(),
+      DeclareFlags {
+        integer: Some(false),
+        vars: vec![("x".to_string(), Some("3+4".to_string()))],
+      }
+    );
+    assert_eq!(
+      parse_args(vec!["1x=2".to_string()])
+        .err()
+        .unwrap()
+        .to_string(),
+      "'1x=2': not a valid identifier"
+    );
+    assert_eq!(
+      parse_args(vec!["-a".to_string()])
+        .err()
+        .unwrap()
+        .to_string(),
+      "unsupported flag: -a"
+    );
+  }
+}
@@ -0,0 +1,52 @@
+// Copyright 2018-2024 the Deno authors. MIT license.
+
+use futures::future::LocalBoxFuture;
+use futures::FutureExt;
+
+use crate::shell::types::ExecuteResult;
+
+use super::ExecuteCommandArgsContext;
+use super::ShellCommand;
+use super::ShellCommandContext;
+
+/// Runs the command in place of the shell, which exits with its exit code.
+///
+/// A library can't replace the process, so embedders that can (like the
+/// `shell` binary) may register their own `exec`. A bare `exec` with
+/// redirects is handled by the executor since it changes the shell's
+/// streams.
+pub struct ExecCommand;
+
+impl ShellCommand for ExecCommand {
+  fn execute(
+    &self,
+    mut context: ShellCommandContext,
+  ) -> LocalBoxFuture<'static, ExecuteResult> {
+    if context.args.first().map(String::as_str) == Some("--") {
+      context.args.remove(0);
+    }
+    if context.args.is_empty() {
+      return Box::pin(futures::future::ready(ExecuteResult::from_exit_code(
+        0,
+      )));
+    }
+    let command = (context.execute_command_args)(ExecuteCommandArgsContext {
+      args: context.args,
+      state: context.state,
+      stdin: context.stdin,
+      stdout: context.stdout,
+      stderr: context.stderr,
+    });
+    async move {
+      match command.await {
+        ExecuteResult::Exit(code, handles) => {
+          ExecuteResult::Exit(code, handles)
+        }
+        ExecuteResult::Continue(code, _, handles) => {
+          ExecuteResult::Exit(code, handles)
+        }
+      }
+    }
+    .boxed_local()
+  }
+}
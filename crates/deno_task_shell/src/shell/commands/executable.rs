@@ -31,14 +31,21 @@ impl ShellCommand for ExecutableCommand {
     async move {
       let mut stderr = context.stderr;
       let mut sub_command = tokio::process::Command::new(&command_name);
+      if !context.state.inherit_env() {
+        // only the variables tracked by the shell are passed on, so that
+        // `unset` also applies to the process environment
+        sub_command.env_clear();
+      }
       let child = sub_command
         .current_dir(context.state.cwd())
         .args(context.args)
-        .env_clear()
         .envs(context.state.env_vars())
         .stdout(context.stdout.into_stdio())
         .stdin(context.stdin.into_stdio())
         .stderr(stderr.clone().into_stdio())
+        // don't leave the process running if this future is dropped
+        // before the child exits
+        .kill_on_drop(true)
         .spawn();
 
       let mut child = match child {
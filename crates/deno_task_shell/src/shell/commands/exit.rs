@@ -18,29 +18,39 @@ impl ShellCommand for ExitCommand {
     &self,
     mut context: ShellCommandContext,
   ) -> LocalBoxFuture<'static, ExecuteResult> {
-    let result = match execute_exit(context.args) {
-      Ok(code) => ExecuteResult::Exit(code, Vec::new()),
+    let last_exit_code = context
+      .state
+      .get_var("?")
+      .and_then(|code| code.parse().ok())
+      // the previous line of the interactive shell
+      .unwrap_or(context.state.last_command_exit_code());
+    let code = match execute_exit(context.args, last_exit_code) {
+      Ok(code) => code,
       Err(err) => {
-        context.stderr.write_line(&format!("exit: {err}")).unwrap();
-        ExecuteResult::Exit(2, Vec::new())
+        let _ = context.stderr.write_line(&format!("exit: {err}"));
+        2
       }
     };
+    // in a sourced file, the lists of the file stop and their changes
+    // still apply to the shell
+    let result = if context.state.exit_source() {
+      ExecuteResult::from_exit_code(code)
+    } else {
+      ExecuteResult::Exit(code, Vec::new())
+    };
     Box::pin(futures::future::ready(result))
   }
 }
 
-fn execute_exit(args: Vec<String>) -> Result<i32> {
-  let exit_code = parse_args(args)?;
-
-  Ok(if exit_code < 0 {
-    let code = -exit_code % 256;
-    256 - code
-  } else {
-    exit_code % 256
-  })
+/// Returns the exit code masked to 0-255, bare `exit` uses the exit code of
+/// the last command.
+fn execute_exit(args: Vec<String>, last_exit_code: i32) -> Result<i32> {
+  let exit_code = parse_args(args)?.unwrap_or(last_exit_code.into());
+  // like sh, the code wraps around (ex. -1 is 255)
+  Ok((exit_code & 0xFF) as i32)
 }
 
-fn parse_args(args: Vec<String>) -> Result<i32> {
+fn parse_args(args: Vec<String>) -> Result<Option<i64>> {
   let args = parse_arg_kinds(&args);
   let mut paths = Vec::new();
   for arg in args {
@@ -53,11 +63,11 @@ fn parse_args(args: Vec<String>) -> Result<i32> {
   }
 
   match paths.len() {
-    0 => Ok(1),
+    0 => Ok(None),
     1 => {
       let arg = paths.remove(0).to_string();
-      match arg.parse::<i32>() {
-        Ok(value) => Ok(value),
+      match arg.parse::<i64>() {
+        Ok(value) => Ok(Some(value)),
         Err(_) => bail!("numeric argument required."),
       }
     }
@@ -73,8 +83,8 @@ mod test {
 
   #[test]
   fn parses_args() {
-    assert_eq!(parse_args(vec![]).unwrap(), 1);
-    assert_eq!(parse_args(vec!["5".to_string()]).unwrap(), 5);
+    assert_eq!(parse_args(vec![]).unwrap(), None);
+    assert_eq!(parse_args(vec!["5".to_string()]).unwrap(), Some(5));
     assert_eq!(
       parse_args(vec!["test".to_string()])
         .err()
@@ -107,11 +117,14 @@ mod test {
 
   #[test]
   fn executes_exit() {
-    assert_eq!(execute_exit(vec![]).unwrap(), 1);
-    assert_eq!(execute_exit(vec!["0".to_string()]).unwrap(), 0);
-    assert_eq!(execute_exit(vec!["255".to_string()]).unwrap(), 255);
-    assert_eq!(execute_exit(vec!["256".to_string()]).unwrap(), 0);
-    assert_eq!(execute_exit(vec!["257".to_string()]).unwrap(), 1);
-    assert_eq!(execute_exit(vec!["-1".to_string()]).unwrap(), 255);
+    assert_eq!(execute_exit(vec![], 1).unwrap(), 1);
+    assert_eq!(execute_exit(vec![], 0).unwrap(), 0);
+    assert_eq!(execute_exit(vec!["0".to_string()], 1).unwrap(), 0);
+    assert_eq!(execute_exit(vec!["255".to_string()], 0).unwrap(), 255);
+    assert_eq!(execute_exit(vec!["256".to_string()], 0).unwrap(), 0);
+    assert_eq!(execute_exit(vec!["257".to_string()], 0).unwrap(), 1);
+    assert_eq!(execute_exit(vec!["-1".to_string()], 0).unwrap(), 255);
+    assert_eq!(execute_exit(vec!["-256".to_string()], 0).unwrap(), 0);
+    assert_eq!(execute_exit(vec!["4294967297".to_string()], 0).unwrap(), 1);
   }
 }
@@ -0,0 +1,269 @@
+// Copyright 2018-2024 the Deno authors. MIT license.
+
+use std::path::Path;
+
+use futures::future::LocalBoxFuture;
+use miette::bail;
+use miette::IntoDiagnostic;
+use miette::Result;
+
+use crate::shell::types::ExecuteResult;
+
+use super::args::parse_arg_kinds;
+use super::args::ArgKind;
+use super::ShellCommand;
+use super::ShellCommandContext;
+
+pub struct FindCommand;
+
+impl ShellCommand for FindCommand {
+  fn execute(
+    &self,
+    context: ShellCommandContext,
+  ) -> LocalBoxFuture<'static, ExecuteResult> {
+    let mut stderr = context.stderr.clone();
+    let result = match execute_find(context) {
+      Ok(result) => result,
+      Err(err) => {
+        ExecuteResult::from_exit_code(stderr.write_command_error("find", &err))
+      }
+    };
+    Box::pin(futures::future::ready(result))
+  }
+}
+
+fn execute_find(mut context: ShellCommandContext) -> Result<ExecuteResult> {
+  let flags = parse_args(std::mem::take(&mut context.args))?;
+  let mut exit_code = 0;
+  for root in &flags.paths {
+    let path = context.state.cwd().join(root);
+    let mut walker = Walker {
+      flags: &flags,
+      context: &mut context,
+      exit_code: 0,
+    };
+    if !walker.walk(root, &path, 0)? {
+      return Ok(ExecuteResult::for_cancellation());
+    }
+    exit_code = exit_code.max(walker.exit_code);
+  }
+  Ok(ExecuteResult::from_exit_code(exit_code))
+}
+
+struct Walker<'a> {
+  flags: &'a FindFlags,
+  context: &'a mut ShellCommandContext,
+  exit_code: i32,
+}
+
+impl Walker<'_> {
+  /// Prints the matching paths of the tree at `path`, which is displayed
+  /// as `display`. Returns `false` when cancelled.
+  fn walk(&mut self, display: &str, path: &Path, depth: usize) -> Result<bool> {
+    if self.context.state.token().is_cancelled() {
+      return Ok(false);
+    }
+    let metadata = match path.symlink_metadata() {
+      Ok(metadata) => metadata,
+      Err(err) => {
+        self
+          .context
+          .stderr
+          .write_line(&format!("find: '{display}': {err}"))?;
+        self.exit_code = 1;
+        return Ok(true);
+      }
+    };
+
+    if self.flags.matches(display, &metadata) {
+      self.context.stdout.write_line(display)?;
+    }
+
+    let can_descend = self
+      .flags
+      .max_depth
+      .map(|max_depth| depth < max_depth)
+      .unwrap_or(true);
+    if metadata.is_dir() && can_descend {
+      let entries = match std::fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(err) => {
+          self
+            .context
+            .stderr
+            .write_line(&format!("find: '{display}': {err}"))?;
+          self.exit_code = 1;
+          return Ok(true);
+        }
+      };
+      let mut names = entries
+        .map(|entry| entry.map(|entry| entry.file_name()))
+        .collect::<std::io::Result<Vec<_>>>()
+        .into_diagnostic()?;
+      // sort for a deterministic output
+      names.sort();
+      for name in names {
+        let child_display = format!(
+          "{}/{}",
+          display.trim_end_matches('/'),
+          name.to_string_lossy()
+        );
+        if !self.walk(&child_display, &path.join(&name), depth + 1)? {
+          return Ok(false);
+        }
+      }
+    }
+    Ok(true)
+  }
+}
+
+#[derive(Debug, PartialEq)]
+enum FileType {
+  /// `-type f`
+  File,
+  /// `-type d`
+  Directory,
+}
+
+#[derive(Debug)]
+struct FindFlags {
+  paths: Vec<String>,
+  name: Option<glob::Pattern>,
+  file_type: Option<FileType>,
+  max_depth: Option<usize>,
+}
+
+impl FindFlags {
+  fn matches(&self, display: &str, metadata: &std::fs::Metadata) -> bool {
+    if let Some(pattern) = &self.name {
+      // like find, the last component of the path as given is matched,
+      // so a root of `.` is matched as `.`
+      let trimmed = display.trim_end_matches('/');
+      let name = trimmed.rsplit('/').next().unwrap_or(trimmed);
+      if !pattern.matches(name) {
+        return false;
+      }
+    }
+    match self.file_type {
+      Some(FileType::File) => metadata.is_file(),
+      Some(FileType::Directory) => metadata.is_dir(),
+      None => true,
+    }
+  }
+}
+
+fn parse_args(args: Vec<String>) -> Result<FindFlags> {
+  let mut paths = Vec::new();
+  let mut name = None;
+  let mut file_type = None;
+  let mut max_depth = None;
+  // find's expressions are single dash words (ex. `-name`), so
+  // the arguments are matched as is instead of as short flags
+  let mut iterator = args.iter();
+  while let Some(arg) = iterator.next() {
+    let mut value = || match iterator.next() {
+      Some(value) => Ok(value),
+      None => bail!("missing argument to '{}'", arg),
+    };
+    let has_expression =
+      name.is_some() || file_type.is_some() || max_depth.is_some();
+    match arg.as_str() {
+      // like GNU find, `--` is only allowed before the paths
+      "--" if paths.is_empty() && !has_expression => {}
+      "-name" => {
+        let value = value()?;
+        name = Some(glob::Pattern::new(value).into_diagnostic()?);
+      }
+      "-type" => {
+        file_type = Some(match value()?.as_str() {
+          "f" => FileType::File,
+          "d" => FileType::Directory,
+          value => bail!("unsupported argument to -type: '{}'", value),
+        });
+      }
+      "-maxdepth" => {
+        let value = value()?;
+        max_depth = Some(match value.parse::<usize>() {
+          Ok(depth) => depth,
+          Err(_) => bail!("invalid argument '{}' to -maxdepth", value),
+        });
+      }
+      _ if arg.starts_with('-') && arg.len() > 1 => {
+        match parse_arg_kinds(std::slice::from_ref(arg))
+          .into_iter()
+          .next()
+        {
+          Some(arg @ ArgKind::LongFlag(_)) => arg.bail_unsupported()?,
+          // `--` after the paths ends up here as well
+          _ => bail!("unknown predicate '{}'", arg),
+        }
+      }
+      _ if has_expression => {
+        bail!("paths must precede expression: '{}'", arg)
+      }
+      _ => paths.push(arg.to_string()),
+    }
+  }
+
+  if paths.is_empty() {
+    paths.push(".".to_string());
+  }
+
+  Ok(FindFlags {
+    paths,
+    name,
+    file_type,
+    max_depth,
+  })
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use pretty_assertions::assert_eq;
+
+  fn parse_err(args: &[&str]) -> String {
+    parse_args(args.iter().map(|arg| arg.to_string()).collect())
+      .err()
+      .unwrap()
+      .to_string()
+  }
+
+  #[test]
+  fn parses_args() {
+    let flags = parse_args(vec![
+      "dir".to_string(),
+      "-name".to_string(),
+      "*.txt".to_string(),
+      "-type".to_string(),
+      "f".to_string(),
+      "-maxdepth".to_string(),
+      "2".to_string(),
+    ])
+    .unwrap();
+    assert_eq!(flags.paths, vec!["dir".to_string()]);
+    assert_eq!(flags.name.unwrap().as_str(), "*.txt");
+    assert_eq!(flags.file_type, Some(FileType::File));
+    assert_eq!(flags.max_depth, Some(2));
+
+    assert_eq!(parse_args(vec![]).unwrap().paths, vec![".".to_string()]);
+    assert_eq!(parse_err(&["-name"]), "missing argument to '-name'");
+    assert_eq!(
+      parse_err(&["-type", "l"]),
+      "unsupported argument to -type: 'l'"
+    );
+    assert_eq!(parse_err(&["-newer", "a"]), "unknown predicate '-newer'");
+    assert_eq!(parse_err(&["--help"]), "unsupported flag: --help");
+    assert_eq!(
+      parse_args(vec!["--".to_string(), "dir".to_string()])
+        .unwrap()
+        .paths,
+      vec!["dir".to_string()]
+    );
+    assert_eq!(parse_err(&["dir", "--"]), "unknown predicate '--'");
+    assert_eq!(
+      parse_err(&["-type", "f", "dir"]),
+      "paths must precede expression: 'dir'"
+    );
+  }
+}
@@ -0,0 +1,116 @@
+// Copyright 2018-2024 the Deno authors. MIT license.
+
+use futures::future::LocalBoxFuture;
+use miette::Result;
+
+use crate::shell::types::ExecuteResult;
+use crate::shell::types::ShellState;
+
+use super::args::parse_arg_kinds;
+use super::args::ArgKind;
+use super::ShellCommand;
+use super::ShellCommandContext;
+
+pub struct HashCommand;
+
+impl ShellCommand for HashCommand {
+  fn execute(
+    &self,
+    mut context: ShellCommandContext,
+  ) -> LocalBoxFuture<'static, ExecuteResult> {
+    let result = match execute_hash(&context.state, &context.args) {
+      Ok((lines, errors)) => {
+        for line in &lines {
+          let _ = context.stdout.write_line(line);
+        }
+        for error in &errors {
+          let _ = context.stderr.write_line(&format!("hash: {error}"));
+        }
+        ExecuteResult::from_exit_code(if errors.is_empty() { 0 } else { 1 })
+      }
+      Err(err) => ExecuteResult::from_exit_code(
+        context.stderr.write_command_error("hash", &err),
+      ),
+    };
+    Box::pin(futures::future::ready(result))
+  }
+}
+
+/// Returns the lines to print and the names that couldn't be found.
+fn execute_hash(
+  state: &ShellState,
+  args: &[String],
+) -> Result<(Vec<String>, Vec<String>)> {
+  let flags = parse_args(args)?;
+  if flags.reset {
+    state.clear_command_hash();
+  }
+
+  let mut errors = Vec::new();
+  for name in &flags.names {
+    if state.resolve_custom_command(name).is_some() {
+      // builtins aren't looked up in the path
+      continue;
+    }
+    // look up the command again, which remembers its location
+    state.forget_hashed_command(name);
+    if state.resolve_command_path(name).is_err() {
+      errors.push(format!("{name}: not found"));
+    }
+  }
+
+  let mut lines = Vec::new();
+  if !flags.reset && flags.names.is_empty() {
+    let commands = state.hashed_commands();
+    if commands.is_empty() {
+      lines.push("hash: hash table empty".to_string());
+    } else {
+      lines.push("hits\tcommand".to_string());
+      for (_, command) in commands {
+        lines.push(format!("{:>4}\t{}", command.hits, command.path.display()));
+      }
+    }
+  }
+  Ok((lines, errors))
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct HashFlags {
+  /// `-r`, forgets all locations
+  reset: bool,
+  names: Vec<String>,
+}
+
+fn parse_args(args: &[String]) -> Result<HashFlags> {
+  let mut flags = HashFlags::default();
+  for arg in parse_arg_kinds(args) {
+    match arg {
+      ArgKind::ShortFlag('r') => flags.reset = true,
+      ArgKind::Arg(name) => flags.names.push(name.to_string()),
+      _ => arg.bail_unsupported()?,
+    }
+  }
+  Ok(flags)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use pretty_assertions::assert_eq;
+
+  #[test]
+  fn parses_args() {
+    assert_eq!(
+      parse_args(&["-r".to_string(), "ls".to_string()]).unwrap(),
+      HashFlags {
+        reset: true,
+        names: vec!["ls".to_string()],
+      }
+    );
+    assert_eq!(parse_args(&[]).unwrap(), HashFlags::default());
+    assert_eq!(
+      parse_args(&["-p".to_string()]).err().unwrap().to_string(),
+      "unsupported flag: -p"
+    );
+  }
+}
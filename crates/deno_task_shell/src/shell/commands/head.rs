@@ -28,8 +28,7 @@ impl ShellCommand for HeadCommand {
     let result = match execute_head(context) {
       Ok(result) => result,
       Err(err) => {
-        let _ = stderr.write_line(&format!("head: {err}"));
-        ExecuteResult::from_exit_code(1)
+        ExecuteResult::from_exit_code(stderr.write_command_error("head", &err))
       }
     };
     Box::pin(futures::future::ready(result))
@@ -80,12 +79,92 @@ fn copy_lines<F: FnMut(&mut [u8]) -> Result<usize>>(
   Ok(ExecuteResult::from_exit_code(0))
 }
 
+fn copy_bytes<F: FnMut(&mut [u8]) -> Result<usize>>(
+  writer: &mut ShellPipeWriter,
+  max_bytes: u64,
+  cancellation_token: &CancellationToken,
+  mut read: F,
+  buffer_size: usize,
+) -> Result<ExecuteResult> {
+  let mut remaining_bytes = max_bytes;
+  let mut buffer = vec![0; buffer_size];
+  while remaining_bytes > 0 {
+    if cancellation_token.is_cancelled() {
+      return Ok(ExecuteResult::for_cancellation());
+    }
+    let read_bytes = read(&mut buffer)?;
+    if read_bytes == 0 {
+      break;
+    }
+    let write_bytes = read_bytes.min(remaining_bytes as usize);
+    writer.write_all(&buffer[..write_bytes])?;
+    remaining_bytes -= write_bytes as u64;
+  }
+
+  Ok(ExecuteResult::from_exit_code(0))
+}
+
+/// Copies all but the last bytes, which are held back until it's known
+/// that more input follows them.
+fn copy_all_but_last_bytes<F: FnMut(&mut [u8]) -> Result<usize>>(
+  writer: &mut ShellPipeWriter,
+  skipped_bytes: u64,
+  cancellation_token: &CancellationToken,
+  mut read: F,
+  buffer_size: usize,
+) -> Result<ExecuteResult> {
+  let skipped_bytes = skipped_bytes as usize;
+  let mut pending = Vec::new();
+  let mut buffer = vec![0; buffer_size];
+  loop {
+    if cancellation_token.is_cancelled() {
+      return Ok(ExecuteResult::for_cancellation());
+    }
+    let read_bytes = read(&mut buffer)?;
+    if read_bytes == 0 {
+      break;
+    }
+    pending.extend_from_slice(&buffer[..read_bytes]);
+    if pending.len() > skipped_bytes {
+      let write_bytes = pending.len() - skipped_bytes;
+      writer.write_all(&pending[..write_bytes])?;
+      pending.drain(..write_bytes);
+    }
+  }
+
+  Ok(ExecuteResult::from_exit_code(0))
+}
+
+fn copy_head<F: FnMut(&mut [u8]) -> Result<usize>>(
+  writer: &mut ShellPipeWriter,
+  count: HeadCount,
+  cancellation_token: &CancellationToken,
+  read: F,
+  buffer_size: usize,
+) -> Result<ExecuteResult> {
+  match count {
+    HeadCount::Lines(lines) => {
+      copy_lines(writer, lines, cancellation_token, read, buffer_size)
+    }
+    HeadCount::Bytes(bytes) => {
+      copy_bytes(writer, bytes, cancellation_token, read, buffer_size)
+    }
+    HeadCount::AllButLastBytes(bytes) => copy_all_but_last_bytes(
+      writer,
+      bytes,
+      cancellation_token,
+      read,
+      buffer_size,
+    ),
+  }
+}
+
 fn execute_head(mut context: ShellCommandContext) -> Result<ExecuteResult> {
   let flags = parse_args(context.args)?;
   if flags.path == "-" {
-    copy_lines(
+    copy_head(
       &mut context.stdout,
-      flags.lines,
+      flags.count,
       context.state.token(),
       |buf| context.stdin.read(buf),
       512,
@@ -93,9 +172,9 @@ fn execute_head(mut context: ShellCommandContext) -> Result<ExecuteResult> {
   } else {
     let path = flags.path;
     match File::open(context.state.cwd().join(&path)) {
-      Ok(mut file) => copy_lines(
+      Ok(mut file) => copy_head(
         &mut context.stdout,
-        flags.lines,
+        flags.count,
         context.state.token(),
         |buf| file.read(buf).into_diagnostic(),
         512,
@@ -108,15 +187,32 @@ fn execute_head(mut context: ShellCommandContext) -> Result<ExecuteResult> {
   }
 }
 
+#[derive(Debug, PartialEq)]
+enum HeadCount {
+  /// `-n`
+  Lines(u64),
+  /// `-c`
+  Bytes(u64),
+  /// `-c -N`, everything except the last N bytes
+  AllButLastBytes(u64),
+}
+
 #[derive(Debug, PartialEq)]
 struct HeadFlags {
   path: String,
-  lines: u64,
+  count: HeadCount,
+}
+
+fn parse_byte_count(text: &str) -> Result<HeadCount> {
+  Ok(match text.strip_prefix('-') {
+    Some(bytes) => HeadCount::AllButLastBytes(bytes.parse().into_diagnostic()?),
+    None => HeadCount::Bytes(text.parse().into_diagnostic()?),
+  })
 }
 
 fn parse_args(args: Vec<String>) -> Result<HeadFlags> {
   let mut path: Option<String> = None;
-  let mut lines: Option<u64> = None;
+  let mut count: Option<HeadCount> = None;
   let mut iterator = parse_arg_kinds(&args).into_iter();
   while let Some(arg) = iterator.next() {
     match arg {
@@ -132,15 +228,25 @@ fn parse_args(args: Vec<String>) -> Result<HeadFlags> {
       }
       ArgKind::ShortFlag('n') => match iterator.next() {
         Some(ArgKind::Arg(arg)) => {
-          lines = Some(arg.parse::<u64>().into_diagnostic()?);
+          count = Some(HeadCount::Lines(arg.parse::<u64>().into_diagnostic()?));
         }
         _ => bail!("expected a value following -n"),
       },
+      ArgKind::ShortFlag('c') => match iterator.next() {
+        Some(ArgKind::Arg(arg)) => {
+          count = Some(parse_byte_count(arg)?);
+        }
+        _ => bail!("expected a value following -c"),
+      },
       ArgKind::LongFlag(flag) => {
         if flag == "lines" || flag == "lines=" {
           bail!("expected a value for --lines");
         } else if let Some(arg) = flag.strip_prefix("lines=") {
-          lines = Some(arg.parse::<u64>().into_diagnostic()?);
+          count = Some(HeadCount::Lines(arg.parse::<u64>().into_diagnostic()?));
+        } else if flag == "bytes" || flag == "bytes=" {
+          bail!("expected a value for --bytes");
+        } else if let Some(arg) = flag.strip_prefix("bytes=") {
+          count = Some(parse_byte_count(arg)?);
         } else {
           arg.bail_unsupported()?
         }
@@ -151,7 +257,7 @@ fn parse_args(args: Vec<String>) -> Result<HeadFlags> {
 
   Ok(HeadFlags {
     path: path.unwrap_or("-".to_string()),
-    lines: lines.unwrap_or(10),
+    count: count.unwrap_or(HeadCount::Lines(10)),
   })
 }
 
@@ -215,34 +321,99 @@ mod test {
     copies_lines(512).await;
   }
 
+  async fn copy_data_with(
+    data: &'static [u8],
+    count: HeadCount,
+    buffer_size: usize,
+  ) -> String {
+    let (reader, mut writer) = pipe();
+    let reader_handle = reader.pipe_to_string_handle();
+    let mut offset = 0;
+    let result = copy_head(
+      &mut writer,
+      count,
+      &CancellationToken::new(),
+      |buffer| {
+        let read_length = min(buffer.len(), data.len() - offset);
+        buffer[..read_length]
+          .copy_from_slice(&data[offset..(offset + read_length)]);
+        offset += read_length;
+        Ok(read_length)
+      },
+      buffer_size,
+    );
+    drop(writer); // Drop the writer ahead of the reader to prevent a deadlock.
+    assert_eq!(result.unwrap().into_exit_code_and_handles().0, 0);
+    reader_handle.await.unwrap()
+  }
+
+  #[tokio::test]
+  async fn copies_bytes() {
+    for buffer_size in [1, 2, 4, 512] {
+      assert_eq!(
+        copy_data_with(b"abcdef", HeadCount::Bytes(3), buffer_size).await,
+        "abc"
+      );
+      assert_eq!(
+        copy_data_with(b"abcdef", HeadCount::Bytes(10), buffer_size).await,
+        "abcdef"
+      );
+      assert_eq!(
+        copy_data_with(b"abcdef", HeadCount::Bytes(0), buffer_size).await,
+        ""
+      );
+    }
+  }
+
+  #[tokio::test]
+  async fn copies_all_but_last_bytes() {
+    for buffer_size in [1, 2, 4, 512] {
+      assert_eq!(
+        copy_data_with(b"abcdef", HeadCount::AllButLastBytes(2), buffer_size)
+          .await,
+        "abcd"
+      );
+      assert_eq!(
+        copy_data_with(b"abcdef", HeadCount::AllButLastBytes(0), buffer_size)
+          .await,
+        "abcdef"
+      );
+      assert_eq!(
+        copy_data_with(b"abcdef", HeadCount::AllButLastBytes(10), buffer_size)
+          .await,
+        ""
+      );
+    }
+  }
+
   #[test]
   fn parses_args() {
     assert_eq!(
       parse_args(vec![]).unwrap(),
       HeadFlags {
         path: "-".to_string(),
-        lines: 10
+        count: HeadCount::Lines(10),
       }
     );
     assert_eq!(
       parse_args(vec!["-n".to_string(), "5".to_string()]).unwrap(),
       HeadFlags {
         path: "-".to_string(),
-        lines: 5
+        count: HeadCount::Lines(5),
       }
     );
     assert_eq!(
       parse_args(vec!["--lines=5".to_string()]).unwrap(),
       HeadFlags {
         path: "-".to_string(),
-        lines: 5
+        count: HeadCount::Lines(5),
       }
     );
     assert_eq!(
       parse_args(vec!["path".to_string()]).unwrap(),
       HeadFlags {
         path: "path".to_string(),
-        lines: 10
+        count: HeadCount::Lines(10),
       }
     );
     assert_eq!(
@@ -250,14 +421,14 @@ mod test {
         .unwrap(),
       HeadFlags {
         path: "path".to_string(),
-        lines: 5
+        count: HeadCount::Lines(5),
       }
     );
     assert_eq!(
       parse_args(vec!["--lines=5".to_string(), "path".to_string()]).unwrap(),
       HeadFlags {
         path: "path".to_string(),
-        lines: 5
+        count: HeadCount::Lines(5),
       }
     );
     assert_eq!(
@@ -265,16 +436,52 @@ mod test {
         .unwrap(),
       HeadFlags {
         path: "path".to_string(),
-        lines: 5
+        count: HeadCount::Lines(5),
       }
     );
     assert_eq!(
       parse_args(vec!["path".to_string(), "--lines=5".to_string()]).unwrap(),
       HeadFlags {
         path: "path".to_string(),
-        lines: 5
+        count: HeadCount::Lines(5),
+      }
+    );
+    assert_eq!(
+      parse_args(vec!["-c".to_string(), "3".to_string()]).unwrap(),
+      HeadFlags {
+        path: "-".to_string(),
+        count: HeadCount::Bytes(3),
+      }
+    );
+    assert_eq!(
+      parse_args(vec!["-c".to_string(), "-3".to_string(), "path".to_string()])
+        .unwrap(),
+      HeadFlags {
+        path: "path".to_string(),
+        count: HeadCount::AllButLastBytes(3),
       }
     );
+    assert_eq!(
+      parse_args(vec!["--bytes=3".to_string()]).unwrap(),
+      HeadFlags {
+        path: "-".to_string(),
+        count: HeadCount::Bytes(3),
+      }
+    );
+    assert_eq!(
+      parse_args(vec!["-c".to_string()])
+        .err()
+        .unwrap()
+        .to_string(),
+      "expected a value following -c"
+    );
+    assert_eq!(
+      parse_args(vec!["--bytes=".to_string()])
+        .err()
+        .unwrap()
+        .to_string(),
+      "expected a value for --bytes"
+    );
     assert_eq!(
       parse_args(vec!["-n".to_string()])
         .err()
@@ -0,0 +1,267 @@
+// Copyright 2018-2024 the Deno authors. MIT license.
+
+use futures::future::LocalBoxFuture;
+use miette::bail;
+use miette::Result;
+
+use crate::shell::types::ExecuteResult;
+use crate::shell::types::ShellState;
+
+use super::ShellCommand;
+use super::ShellCommandContext;
+
+/// The signals that have the same number on all unix platforms.
+const SIGNALS: [(&str, i32); 12] = [
+  ("HUP", 1),
+  ("INT", 2),
+  ("QUIT", 3),
+  ("ILL", 4),
+  ("TRAP", 5),
+  ("ABRT", 6),
+  ("FPE", 8),
+  ("KILL", 9),
+  ("SEGV", 11),
+  ("PIPE", 13),
+  ("ALRM", 14),
+  ("TERM", 15),
+];
+
+const DEFAULT_SIGNAL: i32 = 15;
+
+/// The signals that can be sent to a job. Jobs run inside the shell, so
+/// they can only be stopped, not sent a signal they could handle.
+const JOB_SIGNALS: [i32; 3] = [0, 9, 15];
+
+const USAGE: &str =
+  "usage: kill [-s sigspec | -n signum | -sigspec] pid | jobspec ... or kill -l [sigspec]";
+
+pub struct KillCommand;
+
+impl ShellCommand for KillCommand {
+  fn execute(
+    &self,
+    context: ShellCommandContext,
+  ) -> LocalBoxFuture<'static, ExecuteResult> {
+    let mut stderr = context.stderr.clone();
+    let result = match execute_kill(context) {
+      Ok(result) => result,
+      Err(err) => {
+        ExecuteResult::from_exit_code(stderr.write_command_error("kill", &err))
+      }
+    };
+    Box::pin(futures::future::ready(result))
+  }
+}
+
+fn execute_kill(mut context: ShellCommandContext) -> Result<ExecuteResult> {
+  let (signal, targets) = match parse_args(&context.args)? {
+    KillArgs::List(signals) => {
+      if signals.is_empty() {
+        let names = SIGNALS.map(|(name, _)| name);
+        context.stdout.write_line(&names.join(" "))?;
+      }
+      for signal in signals {
+        // like bash, a name lists its number and a number its name
+        let text = match signal.parse::<i32>() {
+          Ok(number) => signal_name(number)?.to_string(),
+          Err(_) => parse_signal(&signal)?.to_string(),
+        };
+        context.stdout.write_line(&text)?;
+      }
+      return Ok(ExecuteResult::from_exit_code(0));
+    }
+    KillArgs::Send { signal, targets } => (signal, targets),
+    KillArgs::Usage => {
+      context.stderr.write_line(&format!("kill: {USAGE}"))?;
+      return Ok(ExecuteResult::from_exit_code(2));
+    }
+  };
+
+  let mut exit_code = 0;
+  for target in targets {
+    if let Err(err) = kill_target(&context.state, &target, signal) {
+      context
+        .stderr
+        .write_line(&format!("kill: {target}: {err}"))?;
+      exit_code = 1;
+    }
+  }
+  Ok(ExecuteResult::from_exit_code(exit_code))
+}
+
+fn kill_target(state: &ShellState, target: &str, signal: i32) -> Result<()> {
+  if let Some(job_spec) = target.strip_prefix('%') {
+    let job_id = match job_spec {
+      "" | "%" | "+" => state.current_job_id(),
+      id => id.parse::<usize>().ok(),
+    };
+    // signal 0 only checks that the job exists
+    let Some(job_id) = job_id.filter(|id| state.kill_job(*id, 0)) else {
+      bail!("no such job");
+    };
+    if !JOB_SIGNALS.contains(&signal) {
+      bail!("{}: signal can't be sent to a job", signal_name(signal)?);
+    }
+    state.kill_job(job_id, signal);
+    Ok(())
+  } else {
+    match target.parse::<i32>() {
+      Ok(pid) => kill_process(pid, signal),
+      Err(_) => {
+        bail!("arguments must be process or job IDs")
+      }
+    }
+  }
+}
+
+#[cfg(unix)]
+fn kill_process(pid: i32, signal: i32) -> Result<()> {
+  // SAFETY: kill has no memory safety requirements
+  if unsafe { libc::kill(pid, signal) } == 0 {
+    Ok(())
+  } else {
+    bail!("{}", std::io::Error::last_os_error())
+  }
+}
+
+#[cfg(not(unix))]
+fn kill_process(_pid: i32, _signal: i32) -> Result<()> {
+  bail!("killing a process by id is not supported, use a job spec (ex. %1)")
+}
+
+fn signal_name(number: i32) -> Result<&'static str> {
+  // exit codes of killed commands are 128 + the signal
+  let number = if number > 128 { number - 128 } else { number };
+  match SIGNALS.iter().find(|(_, n)| *n == number) {
+    Some((name, _)) => Ok(name),
+    None => bail!("{}: invalid signal specification", number),
+  }
+}
+
+fn parse_signal(text: &str) -> Result<i32> {
+  if let Ok(number) = text.parse::<i32>() {
+    if number == 0 || signal_name(number).is_ok() {
+      return Ok(number);
+    }
+  } else {
+    let upper = text.to_uppercase();
+    let name = upper.strip_prefix("SIG").unwrap_or(&upper);
+    if let Some((_, number)) = SIGNALS.iter().find(|(n, _)| *n == name) {
+      return Ok(*number);
+    }
+  }
+  bail!("{}: invalid signal specification", text)
+}
+
+#[derive(Debug, PartialEq)]
+enum KillArgs {
+  /// `-l`, lists the signals or translates the given ones
+  List(Vec<String>),
+  Send {
+    signal: i32,
+    targets: Vec<String>,
+  },
+  /// No targets were given
+  Usage,
+}
+
+fn parse_args(args: &[String]) -> Result<KillArgs> {
+  let mut signal = DEFAULT_SIGNAL;
+  // the signal flags look like short flags (ex. `-TERM` and `-9`), so
+  // they're matched as is instead of with `parse_arg_kinds`
+  let mut iterator = args.iter();
+  while let Some(arg) = iterator.next() {
+    match arg.as_str() {
+      "-l" | "-L" => {
+        return Ok(KillArgs::List(iterator.cloned().collect()));
+      }
+      "-s" | "-n" => match iterator.next() {
+        Some(value) => signal = parse_signal(value)?,
+        None => bail!("{}: option requires an argument", arg),
+      },
+      "--" => {
+        let targets: Vec<_> = iterator.cloned().collect();
+        if targets.is_empty() {
+          break;
+        }
+        return Ok(KillArgs::Send { signal, targets });
+      }
+      _ if arg.starts_with('-') && arg.len() > 1 => {
+        signal = parse_signal(&arg[1..])?;
+      }
+      _ => {
+        let targets = std::iter::once(arg).chain(iterator).cloned().collect();
+        return Ok(KillArgs::Send { signal, targets });
+      }
+    }
+  }
+  Ok(KillArgs::Usage)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use pretty_assertions::assert_eq;
+
+  fn parse(args: &[&str]) -> Result<KillArgs> {
+    parse_args(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>())
+  }
+
+  #[test]
+  fn parses_args() {
+    assert_eq!(
+      parse(&["%1", "123"]).unwrap(),
+      KillArgs::Send {
+        signal: 15,
+        targets: vec!["%1".to_string(), "123".to_string()],
+      }
+    );
+    for args in [
+      &["-9", "%1"][..],
+      &["-KILL", "%1"],
+      &["-SIGKILL", "%1"],
+      &["-s", "kill", "%1"],
+      &["-n", "9", "%1"],
+    ] {
+      assert_eq!(
+        parse(args).unwrap(),
+        KillArgs::Send {
+          signal: 9,
+          targets: vec!["%1".to_string()],
+        }
+      );
+    }
+    assert_eq!(
+      parse(&["-l", "9"]).unwrap(),
+      KillArgs::List(vec!["9".to_string()])
+    );
+    assert_eq!(
+      parse(&["-FOO", "1"]).err().unwrap().to_string(),
+      "FOO: invalid signal specification"
+    );
+    assert_eq!(
+      parse(&["-s"]).err().unwrap().to_string(),
+      "-s: option requires an argument"
+    );
+    assert_eq!(parse(&[]).unwrap(), KillArgs::Usage);
+    assert_eq!(parse(&["--"]).unwrap(), KillArgs::Usage);
+    assert_eq!(parse(&["-9", "--"]).unwrap(), KillArgs::Usage);
+    assert_eq!(
+      parse(&["--", "%1"]).unwrap(),
+      KillArgs::Send {
+        signal: 15,
+        targets: vec!["%1".to_string()],
+      }
+    );
+  }
+
+  #[test]
+  fn translates_signals() {
+    assert_eq!(parse_signal("TERM").unwrap(), 15);
+    assert_eq!(parse_signal("sigint").unwrap(), 2);
+    assert_eq!(parse_signal("0").unwrap(), 0);
+    assert_eq!(signal_name(9).unwrap(), "KILL");
+    assert_eq!(signal_name(143).unwrap(), "TERM");
+    assert!(signal_name(99).is_err());
+  }
+}
@@ -4,15 +4,27 @@ mod args;
 mod cat;
 mod cd;
 mod cp_mv;
+mod declare;
 mod echo;
+mod exec;
 mod executable;
 mod exit;
 mod export;
+mod find;
+mod hash;
 mod head;
+mod kill;
 mod mkdir;
+mod nl;
+mod printenv;
 mod pwd;
+mod repeat;
 mod rm;
+mod rmdir;
 mod sleep;
+mod stat;
+mod time;
+mod tr;
 mod unset;
 mod xargs;
 
@@ -46,10 +58,18 @@ pub fn builtin_commands() -> HashMap<String, Rc<dyn ShellCommand>> {
       "cp".to_string(),
       Rc::new(cp_mv::CpCommand) as Rc<dyn ShellCommand>,
     ),
+    (
+      "declare".to_string(),
+      Rc::new(declare::DeclareCommand) as Rc<dyn ShellCommand>,
+    ),
     (
       "echo".to_string(),
       Rc::new(echo::EchoCommand) as Rc<dyn ShellCommand>,
     ),
+    (
+      "exec".to_string(),
+      Rc::new(exec::ExecCommand) as Rc<dyn ShellCommand>,
+    ),
     (
       "exit".to_string(),
       Rc::new(exit::ExitCommand) as Rc<dyn ShellCommand>,
@@ -58,10 +78,22 @@ pub fn builtin_commands() -> HashMap<String, Rc<dyn ShellCommand>> {
       "export".to_string(),
       Rc::new(export::ExportCommand) as Rc<dyn ShellCommand>,
     ),
+    (
+      "find".to_string(),
+      Rc::new(find::FindCommand) as Rc<dyn ShellCommand>,
+    ),
+    (
+      "hash".to_string(),
+      Rc::new(hash::HashCommand) as Rc<dyn ShellCommand>,
+    ),
     (
       "head".to_string(),
       Rc::new(head::HeadCommand) as Rc<dyn ShellCommand>,
     ),
+    (
+      "kill".to_string(),
+      Rc::new(kill::KillCommand) as Rc<dyn ShellCommand>,
+    ),
     (
       "mkdir".to_string(),
       Rc::new(mkdir::MkdirCommand) as Rc<dyn ShellCommand>,
@@ -70,18 +102,46 @@ pub fn builtin_commands() -> HashMap<String, Rc<dyn ShellCommand>> {
       "mv".to_string(),
       Rc::new(cp_mv::MvCommand) as Rc<dyn ShellCommand>,
     ),
+    (
+      "nl".to_string(),
+      Rc::new(nl::NlCommand) as Rc<dyn ShellCommand>,
+    ),
+    (
+      "printenv".to_string(),
+      Rc::new(printenv::PrintenvCommand) as Rc<dyn ShellCommand>,
+    ),
     (
       "pwd".to_string(),
       Rc::new(pwd::PwdCommand) as Rc<dyn ShellCommand>,
     ),
+    (
+      "repeat".to_string(),
+      Rc::new(repeat::RepeatCommand) as Rc<dyn ShellCommand>,
+    ),
     (
       "rm".to_string(),
       Rc::new(rm::RmCommand) as Rc<dyn ShellCommand>,
     ),
+    (
+      "rmdir".to_string(),
+      Rc::new(rmdir::RmdirCommand) as Rc<dyn ShellCommand>,
+    ),
     (
       "sleep".to_string(),
       Rc::new(sleep::SleepCommand) as Rc<dyn ShellCommand>,
     ),
+    (
+      "stat".to_string(),
+      Rc::new(stat::StatCommand) as Rc<dyn ShellCommand>,
+    ),
+    (
+      "time".to_string(),
+      Rc::new(time::TimeCommand) as Rc<dyn ShellCommand>,
+    ),
+    (
+      "tr".to_string(),
+      Rc::new(tr::TrCommand) as Rc<dyn ShellCommand>,
+    ),
     (
       "true".to_string(),
       Rc::new(ExitCodeCommand(0)) as Rc<dyn ShellCommand>,
@@ -116,7 +176,7 @@ pub struct ShellCommandContext {
   pub stdout: ShellPipeWriter,
   pub stderr: ShellPipeWriter,
   pub execute_command_args:
-    Box<dyn FnOnce(ExecuteCommandArgsContext) -> FutureExecuteResult>,
+    Box<dyn Fn(ExecuteCommandArgsContext) -> FutureExecuteResult>,
 }
 
 pub trait ShellCommand {
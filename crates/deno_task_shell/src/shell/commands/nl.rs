@@ -0,0 +1,194 @@
+// Copyright 2018-2024 the Deno authors. MIT license.
+
+use futures::future::LocalBoxFuture;
+use miette::bail;
+use miette::Result;
+
+use crate::shell::types::ExecuteResult;
+
+use super::args::parse_arg_kinds;
+use super::args::ArgKind;
+use super::ShellCommand;
+use super::ShellCommandContext;
+
+/// Width of the line numbers, like coreutils' default.
+const NUMBER_WIDTH: usize = 6;
+
+pub struct NlCommand;
+
+impl ShellCommand for NlCommand {
+  fn execute(
+    &self,
+    context: ShellCommandContext,
+  ) -> LocalBoxFuture<'static, ExecuteResult> {
+    let mut stderr = context.stderr.clone();
+    let result = match execute_nl(context) {
+      Ok(result) => result,
+      Err(err) => {
+        ExecuteResult::from_exit_code(stderr.write_command_error("nl", &err))
+      }
+    };
+    Box::pin(futures::future::ready(result))
+  }
+}
+
+fn execute_nl(mut context: ShellCommandContext) -> Result<ExecuteResult> {
+  let flags = parse_args(context.args)?;
+  let mut exit_code = 0;
+  let mut line_number = 1;
+  for path in &flags.paths {
+    if context.state.token().is_cancelled() {
+      return Ok(ExecuteResult::for_cancellation());
+    }
+    let text = if path == "-" {
+      let mut text = Vec::new();
+      context.stdin.clone().pipe_to(&mut text)?;
+      text
+    } else {
+      match std::fs::read(context.state.cwd().join(path)) {
+        Ok(text) => text,
+        Err(err) => {
+          context.stderr.write_line(&format!("nl: {path}: {err}"))?;
+          exit_code = 1;
+          continue;
+        }
+      }
+    };
+    let output = number_lines(&text, &flags, &mut line_number);
+    context.stdout.write_all(&output)?;
+  }
+
+  Ok(ExecuteResult::from_exit_code(exit_code))
+}
+
+fn number_lines(
+  text: &[u8],
+  flags: &NlFlags,
+  line_number: &mut usize,
+) -> Vec<u8> {
+  let mut output = Vec::with_capacity(text.len());
+  for line in text.split_inclusive(|c| *c == b'\n') {
+    let is_empty = line == b"\n";
+    let should_number = match flags.body_numbering {
+      BodyNumbering::All => true,
+      BodyNumbering::NonEmpty => !is_empty,
+      BodyNumbering::None => false,
+    };
+    if should_number {
+      output.extend(
+        format!("{:>NUMBER_WIDTH$}{}", line_number, flags.separator).bytes(),
+      );
+      *line_number += 1;
+    } else {
+      // like coreutils, unnumbered lines are padded to stay aligned
+      output.extend(" ".repeat(NUMBER_WIDTH + flags.separator.len()).bytes());
+    }
+    output.extend_from_slice(line);
+  }
+  output
+}
+
+#[derive(Debug, PartialEq)]
+enum BodyNumbering {
+  /// `-b a`
+  All,
+  /// `-b t`
+  NonEmpty,
+  /// `-b n`
+  None,
+}
+
+#[derive(Debug, PartialEq)]
+struct NlFlags {
+  body_numbering: BodyNumbering,
+  separator: String,
+  paths: Vec<String>,
+}
+
+fn parse_args(args: Vec<String>) -> Result<NlFlags> {
+  let mut body_numbering = BodyNumbering::NonEmpty;
+  let mut separator = "\t".to_string();
+  let mut paths = Vec::new();
+  let mut iterator = parse_arg_kinds(&args).into_iter();
+  while let Some(arg) = iterator.next() {
+    match arg {
+      ArgKind::ShortFlag('b') => {
+        body_numbering = match iterator.next() {
+          Some(ArgKind::Arg("a")) => BodyNumbering::All,
+          Some(ArgKind::Arg("t")) => BodyNumbering::NonEmpty,
+          Some(ArgKind::Arg("n")) => BodyNumbering::None,
+          Some(ArgKind::Arg(style)) => {
+            bail!("invalid body numbering style: '{}'", style)
+          }
+          _ => bail!("option requires an argument -- 'b'"),
+        }
+      }
+      ArgKind::ShortFlag('s') => match iterator.next() {
+        Some(ArgKind::Arg(value)) => separator = value.to_string(),
+        _ => bail!("option requires an argument -- 's'"),
+      },
+      ArgKind::Arg(path) => paths.push(path.to_string()),
+      _ => arg.bail_unsupported()?,
+    }
+  }
+
+  if paths.is_empty() {
+    paths.push("-".to_string());
+  }
+
+  Ok(NlFlags {
+    body_numbering,
+    separator,
+    paths,
+  })
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use pretty_assertions::assert_eq;
+
+  fn nl(args: &[&str], text: &str) -> String {
+    let flags =
+      parse_args(args.iter().map(|arg| arg.to_string()).collect()).unwrap();
+    let output = number_lines(text.as_bytes(), &flags, &mut 1);
+    String::from_utf8(output).unwrap()
+  }
+
+  #[test]
+  fn numbers_lines() {
+    assert_eq!(nl(&[], "a\n\nb\n"), "     1\ta\n       \n     2\tb\n");
+    assert_eq!(nl(&[], "a\nb"), "     1\ta\n     2\tb");
+    assert_eq!(
+      nl(&["-b", "a", "-s", ": "], "a\n\nb\n"),
+      "     1: a\n     2: \n     3: b\n"
+    );
+    assert_eq!(nl(&["-b", "n"], "a\n"), "       a\n");
+  }
+
+  #[test]
+  fn parses_args() {
+    assert_eq!(
+      parse_args(vec![]).unwrap(),
+      NlFlags {
+        body_numbering: BodyNumbering::NonEmpty,
+        separator: "\t".to_string(),
+        paths: vec!["-".to_string()],
+      }
+    );
+    assert_eq!(
+      parse_args(vec!["-b".to_string(), "x".to_string()])
+        .err()
+        .unwrap()
+        .to_string(),
+      "invalid body numbering style: 'x'"
+    );
+    assert_eq!(
+      parse_args(vec!["-s".to_string()])
+        .err()
+        .unwrap()
+        .to_string(),
+      "option requires an argument -- 's'"
+    );
+  }
+}
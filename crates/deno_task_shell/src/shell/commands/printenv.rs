@@ -0,0 +1,114 @@
+// Copyright 2018-2024 the Deno authors. MIT license.
+
+use std::collections::HashMap;
+
+use futures::future::LocalBoxFuture;
+use miette::Result;
+
+use crate::shell::types::ExecuteResult;
+
+use super::args::parse_arg_kinds;
+use super::args::ArgKind;
+use super::ShellCommand;
+use super::ShellCommandContext;
+
+pub struct PrintenvCommand;
+
+impl ShellCommand for PrintenvCommand {
+  fn execute(
+    &self,
+    mut context: ShellCommandContext,
+  ) -> LocalBoxFuture<'static, ExecuteResult> {
+    let result = match execute_printenv(context.state.env_vars(), &context.args)
+    {
+      Ok((lines, exit_code)) => {
+        for line in lines {
+          let _ = context.stdout.write_line(&line);
+        }
+        ExecuteResult::from_exit_code(exit_code)
+      }
+      Err(err) => ExecuteResult::from_exit_code(
+        context.stderr.write_command_error("printenv", &err),
+      ),
+    };
+    Box::pin(futures::future::ready(result))
+  }
+}
+
+/// Returns the lines to print and the exit code, which is 1 when one of the
+/// names isn't an environment variable. Shell variables aren't printed.
+fn execute_printenv(
+  env_vars: &HashMap<String, String>,
+  args: &[String],
+) -> Result<(Vec<String>, i32)> {
+  let names = parse_args(args)?;
+  if names.is_empty() {
+    let mut names = env_vars.keys().collect::<Vec<_>>();
+    names.sort();
+    let lines = names
+      .into_iter()
+      .map(|name| format!("{}={}", name, env_vars[name]))
+      .collect();
+    return Ok((lines, 0));
+  }
+
+  let mut lines = Vec::new();
+  let mut exit_code = 0;
+  for name in names {
+    // the names are normalized to uppercase on windows
+    let name = if cfg!(windows) {
+      name.to_uppercase()
+    } else {
+      name.to_string()
+    };
+    match env_vars.get(&name) {
+      Some(value) => lines.push(value.clone()),
+      None => exit_code = 1,
+    }
+  }
+  Ok((lines, exit_code))
+}
+
+fn parse_args(args: &[String]) -> Result<Vec<&str>> {
+  let mut names = Vec::new();
+  for arg in parse_arg_kinds(args) {
+    match arg {
+      ArgKind::Arg(name) => names.push(name),
+      _ => arg.bail_unsupported()?,
+    }
+  }
+  Ok(names)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use pretty_assertions::assert_eq;
+
+  #[test]
+  fn prints_env_vars() {
+    let env_vars = HashMap::from([
+      ("B".to_string(), "2".to_string()),
+      ("A".to_string(), "1".to_string()),
+    ]);
+    assert_eq!(
+      execute_printenv(&env_vars, &[]).unwrap(),
+      (vec!["A=1".to_string(), "B=2".to_string()], 0)
+    );
+    assert_eq!(
+      execute_printenv(&env_vars, &["B".to_string(), "A".to_string()]).unwrap(),
+      (vec!["2".to_string(), "1".to_string()], 0)
+    );
+    assert_eq!(
+      execute_printenv(&env_vars, &["A".to_string(), "C".to_string()]).unwrap(),
+      (vec!["1".to_string()], 1)
+    );
+    assert_eq!(
+      execute_printenv(&env_vars, &["-z".to_string()])
+        .err()
+        .unwrap()
+        .to_string(),
+      "unsupported flag: -z"
+    );
+  }
+}
@@ -4,6 +4,7 @@ use futures::future::LocalBoxFuture;
 use miette::Context;
 use miette::Result;
 use std::path::Path;
+use std::path::PathBuf;
 
 use crate::shell::fs_util;
 use crate::shell::types::ExecuteResult;
@@ -20,46 +21,61 @@ impl ShellCommand for PwdCommand {
     &self,
     mut context: ShellCommandContext,
   ) -> LocalBoxFuture<'static, ExecuteResult> {
-    let result = match execute_pwd(context.state.cwd(), context.args) {
-      Ok(output) => {
-        let _ = context.stdout.write_line(&output);
-        ExecuteResult::from_exit_code(0)
-      }
-      Err(err) => {
-        let _ = context.stderr.write_line(&format!("pwd: {err}"));
-        ExecuteResult::from_exit_code(1)
-      }
-    };
+    let pwd = context.state.get_var("PWD").map(PathBuf::from);
+    let result =
+      match execute_pwd(context.state.cwd(), pwd.as_deref(), context.args) {
+        Ok(output) => {
+          let _ = context.stdout.write_line(&output);
+          ExecuteResult::from_exit_code(0)
+        }
+        Err(err) => ExecuteResult::from_exit_code(
+          context.stderr.write_command_error("pwd", &err),
+        ),
+      };
     Box::pin(futures::future::ready(result))
   }
 }
 
-fn execute_pwd(cwd: &Path, args: Vec<String>) -> Result<String> {
-  let flags = parse_args(args)?;
-  let cwd = if flags.logical {
-    fs_util::canonicalize_path(cwd)
-      .with_context(|| format!("error canonicalizing: {}", cwd.display()))?
-  } else {
-    cwd.to_path_buf()
+fn execute_pwd(
+  cwd: &Path,
+  pwd: Option<&Path>,
+  args: Vec<String>,
+) -> Result<String> {
+  let cwd = match parse_args(args)? {
+    PwdMode::Logical => match pwd {
+      // like bash, only trust $PWD when it still points to the cwd
+      Some(pwd) if pwd.is_absolute() && is_same_dir(pwd, cwd) => {
+        pwd.to_path_buf()
+      }
+      _ => cwd.to_path_buf(),
+    },
+    PwdMode::Physical => fs_util::canonicalize_path(cwd)
+      .with_context(|| format!("error canonicalizing: {}", cwd.display()))?,
   };
   Ok(cwd.display().to_string())
 }
 
+fn is_same_dir(a: &Path, b: &Path) -> bool {
+  match (fs_util::canonicalize_path(a), fs_util::canonicalize_path(b)) {
+    (Ok(a), Ok(b)) => a == b,
+    _ => false,
+  }
+}
+
 #[derive(Debug, PartialEq)]
-struct PwdFlags {
-  logical: bool,
+enum PwdMode {
+  /// `-L`, keeps the symlinks of `$PWD`
+  Logical,
+  /// `-P`, resolves all symlinks
+  Physical,
 }
 
-fn parse_args(args: Vec<String>) -> Result<PwdFlags> {
-  let mut logical = false;
+fn parse_args(args: Vec<String>) -> Result<PwdMode> {
+  let mut mode = PwdMode::Logical;
   for arg in parse_arg_kinds(&args) {
     match arg {
-      ArgKind::ShortFlag('L') => {
-        logical = true;
-      }
-      ArgKind::ShortFlag('P') => {
-        // ignore, this is the default
-      }
+      ArgKind::ShortFlag('L') => mode = PwdMode::Logical,
+      ArgKind::ShortFlag('P') => mode = PwdMode::Physical,
       ArgKind::Arg(_) => {
         // args are ignored by pwd
       }
@@ -67,7 +83,7 @@ fn parse_args(args: Vec<String>) -> Result<PwdFlags> {
     }
   }
 
-  Ok(PwdFlags { logical })
+  Ok(mode)
 }
 
 #[cfg(test)]
@@ -77,14 +93,18 @@ mod test {
 
   #[test]
   fn parses_args() {
-    assert_eq!(parse_args(vec![]).unwrap(), PwdFlags { logical: false });
+    assert_eq!(parse_args(vec![]).unwrap(), PwdMode::Logical);
     assert_eq!(
       parse_args(vec!["-P".to_string()]).unwrap(),
-      PwdFlags { logical: false }
+      PwdMode::Physical
     );
     assert_eq!(
       parse_args(vec!["-L".to_string()]).unwrap(),
-      PwdFlags { logical: true }
+      PwdMode::Logical
+    );
+    assert_eq!(
+      parse_args(vec!["-L".to_string(), "-P".to_string()]).unwrap(),
+      PwdMode::Physical
     );
     assert!(parse_args(vec!["test".to_string()]).is_ok());
     assert_eq!(
@@ -102,4 +122,26 @@ mod test {
       "unsupported flag: -t"
     );
   }
+
+  #[cfg(unix)]
+  #[test]
+  fn resolves_symlinks_only_for_physical() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = fs_util::canonicalize_path(dir.path()).unwrap();
+    std::fs::create_dir(root.join("main")).unwrap();
+    std::os::unix::fs::symlink(root.join("main"), root.join("link")).unwrap();
+    let cwd = root.join("link");
+    let pwd = |pwd: Option<&Path>, args: &[&str]| {
+      execute_pwd(&cwd, pwd, args.iter().map(|arg| arg.to_string()).collect())
+        .unwrap()
+    };
+
+    let logical = cwd.display().to_string();
+    let physical = root.join("main").display().to_string();
+    assert_eq!(pwd(Some(&cwd), &[]), logical);
+    assert_eq!(pwd(Some(&cwd), &["-L"]), logical);
+    assert_eq!(pwd(Some(&cwd), &["-P"]), physical);
+    // a stale $PWD is not used
+    assert_eq!(pwd(Some(&root), &["-L"]), logical);
+  }
 }
@@ -0,0 +1,109 @@
+// Copyright 2018-2024 the Deno authors. MIT license.
+
+use futures::future::LocalBoxFuture;
+use futures::FutureExt;
+use miette::bail;
+use miette::Result;
+
+use crate::shell::types::ExecuteResult;
+use crate::shell::types::CANCELLATION_EXIT_CODE;
+use crate::ExecuteCommandArgsContext;
+
+use super::ShellCommand;
+use super::ShellCommandContext;
+
+pub struct RepeatCommand;
+
+impl ShellCommand for RepeatCommand {
+  fn execute(
+    &self,
+    mut context: ShellCommandContext,
+  ) -> LocalBoxFuture<'static, ExecuteResult> {
+    async move {
+      let (count, args) = match parse_args(std::mem::take(&mut context.args)) {
+        Ok(value) => value,
+        Err(err) => {
+          let _ = context.stderr.write_line(&format!("repeat: {err}"));
+          return ExecuteResult::from_exit_code(1);
+        }
+      };
+      let mut exit_code = 0;
+      let mut changes = Vec::new();
+      let mut handles = Vec::new();
+      for _ in 0..count {
+        if context.state.token().is_cancelled() {
+          return ExecuteResult::Continue(
+            CANCELLATION_EXIT_CODE,
+            changes,
+            handles,
+          );
+        }
+        let result =
+          (context.execute_command_args)(ExecuteCommandArgsContext {
+            args: args.clone(),
+            state: context.state.clone(),
+            stdin: context.stdin.clone(),
+            stdout: context.stdout.clone(),
+            stderr: context.stderr.clone(),
+          })
+          .await;
+        match result {
+          ExecuteResult::Exit(code, mut exit_handles) => {
+            handles.append(&mut exit_handles);
+            return ExecuteResult::Exit(code, handles);
+          }
+          ExecuteResult::Continue(code, env_changes, mut continue_handles) => {
+            handles.append(&mut continue_handles);
+            // the iterations run in the current shell, so each one sees
+            // the changes of the previous ones
+            context.state.apply_changes(&env_changes);
+            changes.extend(env_changes);
+            exit_code = code;
+            if code == CANCELLATION_EXIT_CODE {
+              break;
+            }
+          }
+        }
+      }
+      ExecuteResult::Continue(exit_code, changes, handles)
+    }
+    .boxed_local()
+  }
+}
+
+fn parse_args(mut args: Vec<String>) -> Result<(usize, Vec<String>)> {
+  if args.len() < 2 {
+    bail!("expected a count and a command");
+  }
+  let command = args.split_off(1);
+  match args[0].parse::<usize>() {
+    Ok(count) => Ok((count, command)),
+    Err(_) => bail!("{}: invalid count", args[0]),
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use pretty_assertions::assert_eq;
+
+  #[test]
+  fn parses_args() {
+    assert_eq!(
+      parse_args(vec!["3".to_string(), "echo".to_string(), "x".to_string()])
+        .unwrap(),
+      (3, vec!["echo".to_string(), "x".to_string()])
+    );
+    assert_eq!(
+      parse_args(vec!["a".to_string(), "echo".to_string()])
+        .err()
+        .unwrap()
+        .to_string(),
+      "a: invalid count"
+    );
+    assert_eq!(
+      parse_args(vec!["3".to_string()]).err().unwrap().to_string(),
+      "expected a count and a command"
+    );
+  }
+}
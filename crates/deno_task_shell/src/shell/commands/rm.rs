@@ -41,8 +41,7 @@ async fn rm_command(
   match execute_remove(cwd, args).await {
     Ok(()) => ExecuteResult::from_exit_code(0),
     Err(err) => {
-      let _ = stderr.write_line(&format!("rm: {err}"));
-      ExecuteResult::from_exit_code(1)
+      ExecuteResult::from_exit_code(stderr.write_command_error("rm", &err))
     }
   }
 }
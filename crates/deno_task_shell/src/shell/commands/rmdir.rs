@@ -0,0 +1,174 @@
+// Copyright 2018-2024 the Deno authors. MIT license.
+
+use futures::future::LocalBoxFuture;
+use futures::FutureExt;
+use miette::bail;
+use miette::Result;
+use std::path::Path;
+
+use crate::shell::types::ExecuteResult;
+use crate::shell::types::ShellPipeWriter;
+
+use super::args::parse_arg_kinds;
+use super::args::ArgKind;
+use super::execute_with_cancellation;
+use super::ShellCommand;
+use super::ShellCommandContext;
+
+pub struct RmdirCommand;
+
+impl ShellCommand for RmdirCommand {
+  fn execute(
+    &self,
+    context: ShellCommandContext,
+  ) -> LocalBoxFuture<'static, ExecuteResult> {
+    async move {
+      execute_with_cancellation!(
+        rmdir_command(context.state.cwd(), context.args, context.stderr),
+        context.state.token()
+      )
+    }
+    .boxed_local()
+  }
+}
+
+async fn rmdir_command(
+  cwd: &Path,
+  args: Vec<String>,
+  mut stderr: ShellPipeWriter,
+) -> ExecuteResult {
+  match execute_rmdir(cwd, args).await {
+    Ok(()) => ExecuteResult::Continue(0, Vec::new(), Vec::new()),
+    Err(err) => {
+      ExecuteResult::from_exit_code(stderr.write_command_error("rmdir", &err))
+    }
+  }
+}
+
+async fn execute_rmdir(cwd: &Path, args: Vec<String>) -> Result<()> {
+  let flags = parse_args(args)?;
+  for specified_path in &flags.paths {
+    remove_empty_dir(cwd, specified_path).await?;
+    if flags.parents {
+      // remove the now empty parents as specified (ex. `a/b` then `a`)
+      let mut parent = Path::new(specified_path).parent();
+      while let Some(path) = parent {
+        if path.as_os_str().is_empty() {
+          break;
+        }
+        remove_empty_dir(cwd, &path.to_string_lossy()).await?;
+        parent = path.parent();
+      }
+    }
+  }
+  Ok(())
+}
+
+async fn remove_empty_dir(cwd: &Path, specified_path: &str) -> Result<()> {
+  let path = cwd.join(specified_path);
+  if path.is_file() {
+    bail!("failed to remove '{}': Not a directory", specified_path);
+  }
+  if let Ok(mut entries) = tokio::fs::read_dir(&path).await {
+    if let Ok(Some(_)) = entries.next_entry().await {
+      bail!("failed to remove '{}': Directory not empty", specified_path);
+    }
+  }
+  if let Err(err) = tokio::fs::remove_dir(&path).await {
+    bail!("failed to remove '{}': {}", specified_path, err);
+  }
+  Ok(())
+}
+
+#[derive(Default, Debug, PartialEq)]
+struct RmdirFlags {
+  parents: bool,
+  paths: Vec<String>,
+}
+
+fn parse_args(args: Vec<String>) -> Result<RmdirFlags> {
+  let mut result = RmdirFlags::default();
+
+  for arg in parse_arg_kinds(&args) {
+    match arg {
+      ArgKind::LongFlag("parents") | ArgKind::ShortFlag('p') => {
+        result.parents = true;
+      }
+      ArgKind::Arg(path) => {
+        result.paths.push(path.to_string());
+      }
+      ArgKind::LongFlag(_) | ArgKind::ShortFlag(_) | ArgKind::PlusFlag(_) => {
+        arg.bail_unsupported()?
+      }
+    }
+  }
+
+  if result.paths.is_empty() {
+    bail!("missing operand");
+  }
+
+  Ok(result)
+}
+
+#[cfg(test)]
+mod test {
+  use tempfile::tempdir;
+
+  use super::*;
+  use std::fs;
+
+  #[test]
+  fn parses_args() {
+    assert_eq!(
+      parse_args(vec!["-p".to_string(), "a".to_string(), "b".to_string()])
+        .unwrap(),
+      RmdirFlags {
+        parents: true,
+        paths: vec!["a".to_string(), "b".to_string()],
+      }
+    );
+    assert_eq!(
+      parse_args(vec!["--parents".to_string()])
+        .err()
+        .unwrap()
+        .to_string(),
+      "missing operand",
+    );
+    assert_eq!(
+      parse_args(vec!["-f".to_string(), "a".to_string()])
+        .err()
+        .unwrap()
+        .to_string(),
+      "unsupported flag: -f",
+    );
+  }
+
+  #[tokio::test]
+  async fn test_removes() {
+    let dir = tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("a/b/c")).unwrap();
+    fs::write(dir.path().join("file.txt"), "").unwrap();
+
+    assert_eq!(
+      execute_rmdir(dir.path(), vec!["a".to_string()])
+        .await
+        .err()
+        .unwrap()
+        .to_string(),
+      "failed to remove 'a': Directory not empty"
+    );
+    assert_eq!(
+      execute_rmdir(dir.path(), vec!["file.txt".to_string()])
+        .await
+        .err()
+        .unwrap()
+        .to_string(),
+      "failed to remove 'file.txt': Not a directory"
+    );
+
+    execute_rmdir(dir.path(), vec!["-p".to_string(), "a/b/c".to_string()])
+      .await
+      .unwrap();
+    assert!(!dir.path().join("a").exists());
+  }
+}
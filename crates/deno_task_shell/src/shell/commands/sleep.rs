@@ -41,8 +41,7 @@ async fn sleep_command(
   match execute_sleep(args).await {
     Ok(()) => ExecuteResult::from_exit_code(0),
     Err(err) => {
-      let _ = stderr.write_line(&format!("sleep: {err}"));
-      ExecuteResult::from_exit_code(1)
+      ExecuteResult::from_exit_code(stderr.write_command_error("sleep", &err))
     }
   }
 }
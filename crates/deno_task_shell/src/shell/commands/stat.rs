@@ -0,0 +1,241 @@
+// Copyright 2018-2024 the Deno authors. MIT license.
+
+use std::fs::Metadata;
+use std::time::UNIX_EPOCH;
+
+use futures::future::LocalBoxFuture;
+use miette::bail;
+use miette::Result;
+
+use crate::shell::types::ExecuteResult;
+
+use super::args::parse_arg_kinds;
+use super::args::ArgKind;
+use super::ShellCommand;
+use super::ShellCommandContext;
+
+const DEFAULT_FORMAT: &str = "  File: %n\n  Size: %s\t%F";
+
+pub struct StatCommand;
+
+impl ShellCommand for StatCommand {
+  fn execute(
+    &self,
+    mut context: ShellCommandContext,
+  ) -> LocalBoxFuture<'static, ExecuteResult> {
+    let result = match parse_args(&context.args) {
+      Ok(flags) => {
+        let format = flags.format.unwrap_or(DEFAULT_FORMAT);
+        let mut exit_code = 0;
+        for path in &flags.paths {
+          let path_buf = context.state.cwd().join(path);
+          let metadata = if flags.dereference {
+            path_buf.metadata()
+          } else {
+            path_buf.symlink_metadata()
+          };
+          match metadata {
+            Ok(metadata) => {
+              let _ = context
+                .stdout
+                .write_line(&format_stat(format, path, &metadata));
+            }
+            Err(err) => {
+              let _ = context
+                .stderr
+                .write_line(&format!("stat: cannot stat '{path}': {err}"));
+              exit_code = 1;
+            }
+          }
+        }
+        ExecuteResult::from_exit_code(exit_code)
+      }
+      Err(err) => ExecuteResult::from_exit_code(
+        context.stderr.write_command_error("stat", &err),
+      ),
+    };
+    Box::pin(futures::future::ready(result))
+  }
+}
+
+/// Replaces the directives of the format with the file's metadata, the
+/// unknown ones become `?`.
+fn format_stat(format: &str, name: &str, metadata: &Metadata) -> String {
+  let mut output = String::new();
+  let mut chars = format.chars();
+  while let Some(c) = chars.next() {
+    if c != '%' {
+      output.push(c);
+      continue;
+    }
+    match chars.next() {
+      Some('%') => output.push('%'),
+      Some('n') => output.push_str(name),
+      Some('s') => output.push_str(&metadata.len().to_string()),
+      Some('Y') => {
+        let seconds = metadata
+          .modified()
+          .ok()
+          .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+          .map(|duration| duration.as_secs().to_string());
+        output.push_str(seconds.as_deref().unwrap_or("?"));
+      }
+      Some('a') => output.push_str(&permissions(metadata)),
+      Some('F') => output.push_str(file_type(metadata)),
+      Some(_) => output.push('?'),
+      None => output.push('%'),
+    }
+  }
+  output
+}
+
+#[cfg(unix)]
+fn permissions(metadata: &Metadata) -> String {
+  use std::os::unix::fs::PermissionsExt;
+  format!("{:o}", metadata.permissions().mode() & 0o7777)
+}
+
+#[cfg(not(unix))]
+fn permissions(_metadata: &Metadata) -> String {
+  "?".to_string()
+}
+
+fn file_type(metadata: &Metadata) -> &'static str {
+  let file_type = metadata.file_type();
+  if file_type.is_dir() {
+    return "directory";
+  } else if file_type.is_symlink() {
+    return "symbolic link";
+  } else if file_type.is_file() {
+    return if metadata.len() == 0 {
+      "regular empty file"
+    } else {
+      "regular file"
+    };
+  }
+  #[cfg(unix)]
+  {
+    use std::os::unix::fs::FileTypeExt;
+    if file_type.is_fifo() {
+      return "fifo";
+    } else if file_type.is_socket() {
+      return "socket";
+    } else if file_type.is_block_device() {
+      return "block special file";
+    } else if file_type.is_char_device() {
+      return "character special file";
+    }
+  }
+  "unknown"
+}
+
+#[derive(Debug, PartialEq)]
+struct StatFlags<'a> {
+  format: Option<&'a str>,
+  dereference: bool,
+  paths: Vec<&'a str>,
+}
+
+fn parse_args(args: &[String]) -> Result<StatFlags<'_>> {
+  let mut format = None;
+  let mut dereference = false;
+  let mut paths = Vec::new();
+  let mut iterator = parse_arg_kinds(args).into_iter();
+  while let Some(arg) = iterator.next() {
+    match arg {
+      ArgKind::Arg(path) => paths.push(path),
+      ArgKind::ShortFlag('c') => match iterator.next() {
+        Some(ArgKind::Arg(value)) => format = Some(value),
+        _ => bail!("expected a value following -c"),
+      },
+      ArgKind::ShortFlag('L') | ArgKind::LongFlag("dereference") => {
+        dereference = true;
+      }
+      ArgKind::LongFlag(flag) => match flag.strip_prefix("format=") {
+        Some(value) => format = Some(value),
+        None => arg.bail_unsupported()?,
+      },
+      _ => arg.bail_unsupported()?,
+    }
+  }
+  if paths.is_empty() {
+    bail!("missing operand");
+  }
+
+  Ok(StatFlags {
+    format,
+    dereference,
+    paths,
+  })
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use pretty_assertions::assert_eq;
+
+  #[test]
+  fn parses_args() {
+    let args = |args: &[&str]| {
+      args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>()
+    };
+    assert_eq!(
+      parse_args(&args(&["-c", "%s", "file"])).unwrap(),
+      StatFlags {
+        format: Some("%s"),
+        dereference: false,
+        paths: vec!["file"],
+      }
+    );
+    assert_eq!(
+      parse_args(&args(&["-L", "--format=%n %F", "a", "b"])).unwrap(),
+      StatFlags {
+        format: Some("%n %F"),
+        dereference: true,
+        paths: vec!["a", "b"],
+      }
+    );
+    assert_eq!(
+      parse_args(&args(&["-c"])).err().unwrap().to_string(),
+      "expected a value following -c"
+    );
+    assert_eq!(
+      parse_args(&args(&[])).err().unwrap().to_string(),
+      "missing operand"
+    );
+    assert_eq!(
+      parse_args(&args(&["-x", "file"]))
+        .err()
+        .unwrap()
+        .to_string(),
+      "unsupported flag: -x"
+    );
+  }
+
+  #[test]
+  fn formats_metadata() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let path = temp_dir.path().join("file.txt");
+    std::fs::write(&path, "hello").unwrap();
+    let metadata = path.metadata().unwrap();
+
+    assert_eq!(
+      format_stat("%n: %s bytes, %F 100%% %q", "file.txt", &metadata),
+      "file.txt: 5 bytes, regular file 100% ?"
+    );
+    assert_eq!(
+      format_stat("%F", "dir", &temp_dir.path().metadata().unwrap()),
+      "directory"
+    );
+    let modified = metadata
+      .modified()
+      .unwrap()
+      .duration_since(UNIX_EPOCH)
+      .unwrap()
+      .as_secs();
+    assert_eq!(
+      format_stat("%Y", "file.txt", &metadata),
+      modified.to_string()
+    );
+  }
+}
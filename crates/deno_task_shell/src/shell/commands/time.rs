@@ -0,0 +1,204 @@
+// Copyright 2018-2024 the Deno authors. MIT license.
+
+use std::time::Duration;
+use std::time::Instant;
+
+use futures::future::LocalBoxFuture;
+use futures::FutureExt;
+
+use crate::shell::types::ExecuteResult;
+use crate::ExecuteCommandArgsContext;
+
+use super::ShellCommand;
+use super::ShellCommandContext;
+
+pub struct TimeCommand;
+
+impl ShellCommand for TimeCommand {
+  fn execute(
+    &self,
+    mut context: ShellCommandContext,
+  ) -> LocalBoxFuture<'static, ExecuteResult> {
+    async move {
+      let start = Instant::now();
+      let start_cpu = cpu_times();
+      let result = if context.args.is_empty() {
+        ExecuteResult::from_exit_code(0)
+      } else {
+        (context.execute_command_args)(ExecuteCommandArgsContext {
+          args: std::mem::take(&mut context.args),
+          state: context.state.clone(),
+          stdin: context.stdin.clone(),
+          stdout: context.stdout.clone(),
+          stderr: context.stderr.clone(),
+        })
+        .await
+      };
+      let (user, sys) = cpu_times();
+      let times = Times {
+        real: start.elapsed(),
+        user: user.saturating_sub(start_cpu.0),
+        sys: sys.saturating_sub(start_cpu.1),
+      };
+      // like bash, an empty `TIMEFORMAT` prints nothing
+      let format = context
+        .state
+        .get_var("TIMEFORMAT")
+        .map(String::as_str)
+        .unwrap_or(DEFAULT_TIME_FORMAT);
+      if !format.is_empty() {
+        let _ = context.stderr.write_line(&format_times(format, &times));
+      }
+      result
+    }
+    .boxed_local()
+  }
+}
+
+/// The output of bash when `TIMEFORMAT` isn't set.
+const DEFAULT_TIME_FORMAT: &str = "\nreal\t%3lR\nuser\t%3lU\nsys\t%3lS";
+
+struct Times {
+  real: Duration,
+  user: Duration,
+  sys: Duration,
+}
+
+/// The user and system CPU time used by the shell and its waited for
+/// children so far.
+#[cfg(unix)]
+fn cpu_times() -> (Duration, Duration) {
+  fn usage(who: libc::c_int) -> (Duration, Duration) {
+    let to_duration = |time: libc::timeval| {
+      Duration::from_secs(time.tv_sec as u64)
+        + Duration::from_micros(time.tv_usec as u64)
+    };
+    // SAFETY: getrusage only writes to the provided struct
+    let mut usage = unsafe { std::mem::zeroed::<libc::rusage>() };
+    if unsafe { libc::getrusage(who, &mut usage) } != 0 {
+      return (Duration::ZERO, Duration::ZERO);
+    }
+    (to_duration(usage.ru_utime), to_duration(usage.ru_stime))
+  }
+
+  let (self_user, self_sys) = usage(libc::RUSAGE_SELF);
+  let (children_user, children_sys) = usage(libc::RUSAGE_CHILDREN);
+  (self_user + children_user, self_sys + children_sys)
+}
+
+#[cfg(not(unix))]
+fn cpu_times() -> (Duration, Duration) {
+  (Duration::ZERO, Duration::ZERO)
+}
+
+/// Expands the `%[p][l]R`, `%[p][l]U`, `%[p][l]S`, `%P` and `%%` escapes
+/// of a `TIMEFORMAT` value. `p` is the number of decimals (0 to 3) and `l`
+/// selects the `MmSS.FFFs` format. Unknown escapes are kept as they are.
+fn format_times(format: &str, times: &Times) -> String {
+  let mut result = String::new();
+  let mut chars = format.chars().peekable();
+  while let Some(c) = chars.next() {
+    if c != '%' {
+      result.push(c);
+      continue;
+    }
+    let mut escape = String::from('%');
+    let precision = match chars.peek().and_then(|c| c.to_digit(10)) {
+      Some(digit) => {
+        escape.push(chars.next().unwrap());
+        digit.min(3) as usize
+      }
+      None => 3,
+    };
+    let long = chars.peek() == Some(&'l');
+    if long {
+      escape.push(chars.next().unwrap());
+    }
+    let duration = match chars.next() {
+      Some('R') => times.real,
+      Some('U') => times.user,
+      Some('S') => times.sys,
+      Some('P') if escape == "%" => {
+        let cpu = (times.user + times.sys).as_secs_f64();
+        let real = times.real.as_secs_f64();
+        let percent = if real > 0.0 { cpu * 100.0 / real } else { 0.0 };
+        result.push_str(&format!("{percent:.2}"));
+        continue;
+      }
+      Some('%') if escape == "%" => {
+        result.push('%');
+        continue;
+      }
+      Some(c) => {
+        result.push_str(&escape);
+        result.push(c);
+        continue;
+      }
+      None => {
+        result.push_str(&escape);
+        break;
+      }
+    };
+    result.push_str(&format_duration(duration, precision, long));
+  }
+  result
+}
+
+/// Formats a duration like bash, either as seconds (ex. `61.250`) or in
+/// the long format (ex. `1m1.250s`).
+fn format_duration(duration: Duration, precision: usize, long: bool) -> String {
+  let millis = duration.as_millis();
+  let fraction = if precision == 0 {
+    String::new()
+  } else {
+    format!(".{:03}", millis % 1000)[..precision + 1].to_string()
+  };
+  if long {
+    format!("{}m{}{}s", millis / 60_000, millis / 1000 % 60, fraction)
+  } else {
+    format!("{}{}", millis / 1000, fraction)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn formats_duration() {
+    let format =
+      |millis| format_duration(Duration::from_millis(millis), 3, true);
+    assert_eq!(format(0), "0m0.000s");
+    assert_eq!(format(123), "0m0.123s");
+    assert_eq!(format(61_250), "1m1.250s");
+    assert_eq!(
+      format_duration(Duration::from_millis(61_250), 3, false),
+      "61.250"
+    );
+    assert_eq!(
+      format_duration(Duration::from_millis(61_250), 1, false),
+      "61.2"
+    );
+    assert_eq!(
+      format_duration(Duration::from_millis(61_250), 0, true),
+      "1m1s"
+    );
+  }
+
+  #[test]
+  fn formats_times() {
+    let times = Times {
+      real: Duration::from_millis(2_500),
+      user: Duration::from_millis(1_000),
+      sys: Duration::from_millis(250),
+    };
+    assert_eq!(
+      format_times(DEFAULT_TIME_FORMAT, &times),
+      "\nreal\t0m2.500s\nuser\t0m1.000s\nsys\t0m0.250s"
+    );
+    assert_eq!(format_times("%R", &times), "2.500");
+    assert_eq!(format_times("%2U %0lS", &times), "1.00 0m0s");
+    assert_eq!(format_times("%P%% cpu", &times), "50.00% cpu");
+    assert_eq!(format_times("%x %5R %", &times), "%x 2.500 %");
+  }
+}
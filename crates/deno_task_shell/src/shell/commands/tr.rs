@@ -0,0 +1,308 @@
+// Copyright 2018-2024 the Deno authors. MIT license.
+
+use futures::future::LocalBoxFuture;
+use miette::bail;
+use miette::Result;
+
+use crate::shell::types::ExecuteResult;
+
+use super::args::parse_arg_kinds;
+use super::args::ArgKind;
+use super::ShellCommand;
+use super::ShellCommandContext;
+
+pub struct TrCommand;
+
+impl ShellCommand for TrCommand {
+  fn execute(
+    &self,
+    context: ShellCommandContext,
+  ) -> LocalBoxFuture<'static, ExecuteResult> {
+    let mut stderr = context.stderr.clone();
+    let result = match execute_tr(context) {
+      Ok(result) => result,
+      Err(err) => {
+        ExecuteResult::from_exit_code(stderr.write_command_error("tr", &err))
+      }
+    };
+    Box::pin(futures::future::ready(result))
+  }
+}
+
+fn execute_tr(mut context: ShellCommandContext) -> Result<ExecuteResult> {
+  let flags = parse_args(context.args)?;
+  let mut translator = Translator::new(&flags)?;
+  let mut buf = vec![0; 1024];
+  loop {
+    if context.state.token().is_cancelled() {
+      return Ok(ExecuteResult::for_cancellation());
+    }
+    let size = context.stdin.read(&mut buf)?;
+    if size == 0 {
+      break;
+    }
+    let output = translator.process(&buf[..size]);
+    context.stdout.write_all(&output)?;
+  }
+  Ok(ExecuteResult::from_exit_code(0))
+}
+
+/// Applies the translation, deletion and squeezing to chunks of input,
+/// remembering the last written byte so squeezing works across chunks.
+struct Translator {
+  map: [u8; 256],
+  delete: [bool; 256],
+  squeeze: [bool; 256],
+  last: Option<u8>,
+}
+
+impl Translator {
+  fn new(flags: &TrFlags) -> Result<Self> {
+    let mut map = [0; 256];
+    for (i, value) in map.iter_mut().enumerate() {
+      *value = i as u8;
+    }
+    let mut delete = [false; 256];
+    let mut squeeze = [false; 256];
+
+    let set1 = expand_set(&flags.set1)?;
+    let set2 = match &flags.set2 {
+      Some(set2) => Some(expand_set(set2)?),
+      None => None,
+    };
+
+    if flags.delete {
+      for c in &set1 {
+        delete[*c as usize] = true;
+      }
+      if flags.squeeze {
+        for c in set2.iter().flatten() {
+          squeeze[*c as usize] = true;
+        }
+      }
+    } else if let Some(set2) = &set2 {
+      let Some(last) = set2.last() else {
+        bail!("when not truncating set1, string2 must be non-empty");
+      };
+      // like GNU tr, the second set is padded with its last character
+      let padded = set2.iter().chain(std::iter::repeat(last));
+      for (from, to) in set1.iter().zip(padded) {
+        map[*from as usize] = *to;
+      }
+      if flags.squeeze {
+        for c in set2 {
+          squeeze[*c as usize] = true;
+        }
+      }
+    } else {
+      for c in &set1 {
+        squeeze[*c as usize] = true;
+      }
+    }
+
+    Ok(Self {
+      map,
+      delete,
+      squeeze,
+      last: None,
+    })
+  }
+
+  fn process(&mut self, input: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(input.len());
+    for byte in input {
+      if self.delete[*byte as usize] {
+        continue;
+      }
+      let byte = self.map[*byte as usize];
+      if self.squeeze[byte as usize] && self.last == Some(byte) {
+        continue;
+      }
+      output.push(byte);
+      self.last = Some(byte);
+    }
+    output
+  }
+}
+
+/// Expands a set like `a-z`, `[:upper:]` or `\n` to its bytes.
+fn expand_set(set: &str) -> Result<Vec<u8>> {
+  let bytes = set.as_bytes();
+  let mut result = Vec::new();
+  let mut i = 0;
+  while i < bytes.len() {
+    if bytes[i..].starts_with(b"[:") {
+      if let Some(end) = set[i..].find(":]") {
+        let name = &set[i + 2..i + end];
+        result.extend(expand_class(name)?);
+        i += end + 2;
+        continue;
+      }
+    }
+    let (start, len) = parse_char(&bytes[i..]);
+    i += len;
+    if bytes.get(i) == Some(&b'-') && i + 1 < bytes.len() {
+      let (end, len) = parse_char(&bytes[i + 1..]);
+      if end < start {
+        bail!(
+          "range-endpoints of '{}-{}' are in reverse collating sequence order",
+          start as char,
+          end as char
+        );
+      }
+      result.extend(start..=end);
+      i += 1 + len;
+    } else {
+      result.push(start);
+    }
+  }
+  Ok(result)
+}
+
+/// Parses a possibly backslash escaped character, returning it along
+/// with the number of bytes consumed.
+fn parse_char(bytes: &[u8]) -> (u8, usize) {
+  if bytes[0] != b'\\' || bytes.len() == 1 {
+    return (bytes[0], 1);
+  }
+  let c = match bytes[1] {
+    b'n' => b'\n',
+    b't' => b'\t',
+    b'r' => b'\r',
+    b'a' => 0x07,
+    b'b' => 0x08,
+    b'f' => 0x0c,
+    b'v' => 0x0b,
+    c => c,
+  };
+  (c, 2)
+}
+
+fn expand_class(name: &str) -> Result<Vec<u8>> {
+  let matches: fn(&u8) -> bool = match name {
+    "alnum" => u8::is_ascii_alphanumeric,
+    "alpha" => u8::is_ascii_alphabetic,
+    "blank" => |c| *c == b' ' || *c == b'\t',
+    "cntrl" => u8::is_ascii_control,
+    "digit" => u8::is_ascii_digit,
+    "graph" => u8::is_ascii_graphic,
+    "lower" => u8::is_ascii_lowercase,
+    "print" => |c| c.is_ascii_graphic() || *c == b' ',
+    "punct" => u8::is_ascii_punctuation,
+    "space" => |c| c.is_ascii_whitespace() || *c == 0x0b,
+    "upper" => u8::is_ascii_uppercase,
+    "xdigit" => u8::is_ascii_hexdigit,
+    _ => bail!("invalid character class '{}'", name),
+  };
+  Ok((0..=127).filter(matches).collect())
+}
+
+#[derive(Debug, PartialEq)]
+struct TrFlags {
+  delete: bool,
+  squeeze: bool,
+  set1: String,
+  set2: Option<String>,
+}
+
+fn parse_args(args: Vec<String>) -> Result<TrFlags> {
+  let mut delete = false;
+  let mut squeeze = false;
+  let mut sets = Vec::new();
+  for arg in parse_arg_kinds(&args) {
+    match arg {
+      ArgKind::ShortFlag('d') => delete = true,
+      ArgKind::ShortFlag('s') => squeeze = true,
+      ArgKind::Arg(set) => sets.push(set.to_string()),
+      _ => arg.bail_unsupported()?,
+    }
+  }
+
+  let max_sets = if delete && !squeeze { 1 } else { 2 };
+  let min_sets = if delete == squeeze { 2 } else { 1 };
+  if sets.len() > max_sets {
+    bail!("extra operand '{}'", sets[max_sets]);
+  }
+  if sets.len() < min_sets {
+    match sets.first() {
+      Some(set) => bail!("missing operand after '{}'", set),
+      None => bail!("missing operand"),
+    }
+  }
+
+  let mut sets = sets.into_iter();
+  Ok(TrFlags {
+    delete,
+    squeeze,
+    set1: sets.next().unwrap(),
+    set2: sets.next(),
+  })
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use pretty_assertions::assert_eq;
+
+  fn tr(args: &[&str], input: &str) -> String {
+    let flags =
+      parse_args(args.iter().map(|arg| arg.to_string()).collect()).unwrap();
+    let output = Translator::new(&flags).unwrap().process(input.as_bytes());
+    String::from_utf8(output).unwrap()
+  }
+
+  #[test]
+  fn translates() {
+    assert_eq!(tr(&["A-Z", "a-z"], "HELLO World"), "hello world");
+    assert_eq!(tr(&["[:lower:]", "[:upper:]"], "abc1"), "ABC1");
+    assert_eq!(tr(&["abc", "x"], "aabbccd"), "xxxxxxd");
+    assert_eq!(tr(&["\\n", " "], "a\nb\n"), "a b ");
+    assert_eq!(tr(&["-d", "[:digit:]"], "a1b22c"), "abc");
+    assert_eq!(tr(&["-s", "a-c"], "aabbcc aa"), "abc a");
+    assert_eq!(tr(&["-s", "a-z", "x"], "ab cd"), "x x");
+    assert_eq!(tr(&["-ds", "0-9", "a"], "a1a2aab"), "ab");
+  }
+
+  #[test]
+  fn squeezes_across_chunks() {
+    let flags = parse_args(vec!["-s".to_string(), "a".to_string()]).unwrap();
+    let mut translator = Translator::new(&flags).unwrap();
+    assert_eq!(translator.process(b"baa"), b"ba");
+    assert_eq!(translator.process(b"aac"), b"c");
+  }
+
+  #[test]
+  fn parses_args() {
+    assert_eq!(
+      parse_args(vec!["-d".to_string(), "a".to_string()]).unwrap(),
+      TrFlags {
+        delete: true,
+        squeeze: false,
+        set1: "a".to_string(),
+        set2: None,
+      }
+    );
+    assert_eq!(
+      parse_args(vec!["a".to_string()]).err().unwrap().to_string(),
+      "missing operand after 'a'"
+    );
+    assert_eq!(
+      parse_args(vec!["-d".to_string(), "a".to_string(), "b".to_string()])
+        .err()
+        .unwrap()
+        .to_string(),
+      "extra operand 'b'"
+    );
+    assert_eq!(
+      parse_args(vec!["-c".to_string()])
+        .err()
+        .unwrap()
+        .to_string(),
+      "unsupported flag: -c"
+    );
+    assert_eq!(
+      expand_set("z-a").err().unwrap().to_string(),
+      "range-endpoints of 'z-a' are in reverse collating sequence order"
+    );
+  }
+}
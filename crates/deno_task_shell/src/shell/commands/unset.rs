@@ -7,6 +7,8 @@ use miette::Result;
 use crate::shell::types::ExecuteResult;
 use crate::EnvChange;
 
+use super::args::parse_arg_kinds;
+use super::args::ArgKind;
 use super::ShellCommand;
 use super::ShellCommandContext;
 
@@ -17,36 +19,69 @@ impl ShellCommand for UnsetCommand {
     &self,
     mut context: ShellCommandContext,
   ) -> LocalBoxFuture<'static, ExecuteResult> {
-    let result = match parse_names(context.args) {
-      Ok(names) => ExecuteResult::Continue(
-        0,
-        names.into_iter().map(EnvChange::UnsetVar).collect(),
-        Vec::new(),
+    let result = match parse_args(context.args) {
+      Ok(flags) => ExecuteResult::Continue(0, flags.into_changes(), Vec::new()),
+      Err(err) => ExecuteResult::from_exit_code(
+        context.stderr.write_command_error("unset", &err),
       ),
-      Err(err) => {
-        let _ = context.stderr.write_line(&format!("unset: {err}"));
-        ExecuteResult::Continue(1, Vec::new(), Vec::new())
-      }
     };
     Box::pin(futures::future::ready(result))
   }
 }
 
-fn parse_names(mut args: Vec<String>) -> Result<Vec<String>> {
-  match args.first() {
-    None => {
-      // Running the actual `unset` with no argument completes with success.
-      Ok(args)
+#[derive(Debug, Default, PartialEq, Eq)]
+enum UnsetKind {
+  /// `unset -v NAME` or `unset NAME`
+  #[default]
+  Variables,
+  /// `unset -f NAME`
+  Functions,
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct UnsetFlags {
+  kind: UnsetKind,
+  names: Vec<String>,
+}
+
+impl UnsetFlags {
+  fn into_changes(self) -> Vec<EnvChange> {
+    match self.kind {
+      UnsetKind::Variables => {
+        self.names.into_iter().map(EnvChange::UnsetVar).collect()
+      }
+      // the shell does not support function definitions yet, so there
+      // is never a function to remove
+      UnsetKind::Functions => Vec::new(),
     }
-    Some(flag) if flag == "-f" => bail!("unsupported flag: -f"),
-    Some(flag) if flag == "-v" => {
-      // It's fine to use `swap_remove` (instead of `remove`) because the order
-      // of args doesn't matter for `unset` command.
-      args.swap_remove(0);
-      Ok(args)
+  }
+}
+
+fn parse_args(args: Vec<String>) -> Result<UnsetFlags> {
+  let mut result = UnsetFlags::default();
+  let mut had_kind_flag = false;
+
+  for arg in parse_arg_kinds(&args) {
+    let kind = match arg {
+      ArgKind::ShortFlag('v') => UnsetKind::Variables,
+      ArgKind::ShortFlag('f') => UnsetKind::Functions,
+      ArgKind::Arg(name) => {
+        result.names.push(name.to_string());
+        continue;
+      }
+      _ => {
+        arg.bail_unsupported()?;
+        continue;
+      }
+    };
+    if had_kind_flag && kind != result.kind {
+      bail!("cannot simultaneously unset a function and a variable");
     }
-    Some(_) => Ok(args),
+    had_kind_flag = true;
+    result.kind = kind;
   }
+
+  Ok(result)
 }
 
 #[cfg(test)]
@@ -56,33 +91,57 @@ mod test {
   #[test]
   fn parse_args() {
     assert_eq!(
-      parse_names(vec!["VAR1".to_string()]).unwrap(),
-      vec!["VAR1".to_string()]
+      super::parse_args(vec!["VAR1".to_string()]).unwrap(),
+      UnsetFlags {
+        kind: UnsetKind::Variables,
+        names: vec!["VAR1".to_string()],
+      }
     );
     assert_eq!(
-      parse_names(vec!["VAR1".to_string(), "VAR2".to_string()]).unwrap(),
-      vec!["VAR1".to_string(), "VAR2".to_string()]
+      super::parse_args(vec!["VAR1".to_string(), "VAR2".to_string()]).unwrap(),
+      UnsetFlags {
+        kind: UnsetKind::Variables,
+        names: vec!["VAR1".to_string(), "VAR2".to_string()],
+      }
     );
-    assert!(parse_names(vec![]).unwrap().is_empty());
+    assert_eq!(super::parse_args(vec![]).unwrap(), UnsetFlags::default());
     assert_eq!(
-      parse_names(vec![
+      super::parse_args(vec![
         "-f".to_string(),
         "VAR1".to_string(),
         "VAR2".to_string()
       ])
-      .err()
-      .unwrap()
-      .to_string(),
-      "unsupported flag: -f".to_string()
+      .unwrap(),
+      UnsetFlags {
+        kind: UnsetKind::Functions,
+        names: vec!["VAR1".to_string(), "VAR2".to_string()],
+      }
     );
     assert_eq!(
-      parse_names(vec![
+      super::parse_args(vec![
         "-v".to_string(),
         "VAR1".to_string(),
         "VAR2".to_string()
       ])
       .unwrap(),
-      vec!["VAR2".to_string(), "VAR1".to_string()]
+      UnsetFlags {
+        kind: UnsetKind::Variables,
+        names: vec!["VAR1".to_string(), "VAR2".to_string()],
+      }
+    );
+    assert_eq!(
+      super::parse_args(vec!["-fv".to_string(), "VAR1".to_string()])
+        .err()
+        .unwrap()
+        .to_string(),
+      "cannot simultaneously unset a function and a variable"
+    );
+    assert_eq!(
+      super::parse_args(vec!["-x".to_string()])
+        .err()
+        .unwrap()
+        .to_string(),
+      "unsupported flag: -x"
     );
   }
 }
@@ -8,6 +8,7 @@ use miette::Result;
 
 use crate::shell::types::ExecuteResult;
 use crate::shell::types::ShellPipeReader;
+use crate::shell::types::CANCELLATION_EXIT_CODE;
 use crate::ExecuteCommandArgsContext;
 
 use super::args::parse_arg_kinds;
@@ -23,44 +24,84 @@ impl ShellCommand for XargsCommand {
     mut context: ShellCommandContext,
   ) -> LocalBoxFuture<'static, ExecuteResult> {
     async move {
-      match xargs_collect_args(context.args, context.stdin.clone()) {
-        Ok(args) => {
-          // don't select on cancellation here as that will occur at a lower level
+      let invocations =
+        match xargs_collect_args(context.args, context.stdin.clone()) {
+          Ok(invocations) => invocations,
+          Err(err) => {
+            let _ = context.stderr.write_line(&format!("xargs: {err}"));
+            return ExecuteResult::from_exit_code(1);
+          }
+        };
+      let mut exit_code = 0;
+      let mut handles = Vec::new();
+      for args in invocations {
+        // don't select on cancellation here as that will occur at a lower level
+        let result =
           (context.execute_command_args)(ExecuteCommandArgsContext {
             args,
-            state: context.state,
-            stdin: context.stdin,
-            stdout: context.stdout,
-            stderr: context.stderr,
+            state: context.state.clone(),
+            stdin: context.stdin.clone(),
+            stdout: context.stdout.clone(),
+            stderr: context.stderr.clone(),
           })
-          .await
-        }
-        Err(err) => {
-          let _ = context.stderr.write_line(&format!("xargs: {err}"));
-          ExecuteResult::from_exit_code(1)
+          .await;
+        match result {
+          ExecuteResult::Exit(code, mut exit_handles) => {
+            handles.append(&mut exit_handles);
+            return ExecuteResult::Exit(code, handles);
+          }
+          ExecuteResult::Continue(code, _, mut continue_handles) => {
+            handles.append(&mut continue_handles);
+            if code == CANCELLATION_EXIT_CODE {
+              return ExecuteResult::Continue(code, Vec::new(), handles);
+            } else if code != 0 {
+              exit_code = code;
+            }
+          }
         }
       }
+      ExecuteResult::Continue(exit_code, Vec::new(), handles)
     }
     .boxed_local()
   }
 }
 
+/// Reads the arguments from stdin and returns the arguments of each
+/// command invocation.
 fn xargs_collect_args(
   cli_args: Vec<String>,
   stdin: ShellPipeReader,
-) -> Result<Vec<String>> {
+) -> Result<Vec<Vec<String>>> {
   let flags = parse_args(cli_args)?;
   let mut buf = Vec::new();
   stdin.pipe_to(&mut buf)?;
   let text = String::from_utf8(buf).into_diagnostic()?;
-  let mut args = flags.initial_args;
+  let mut initial_args = flags.initial_args;
 
-  if args.is_empty() {
+  if initial_args.is_empty() {
     // defaults to echo
-    args.push("echo".to_string());
+    initial_args.push("echo".to_string());
+  }
+
+  if let Some(replace) = &flags.replace {
+    // each non-blank line is an invocation with the replace string
+    // substituted in the initial arguments
+    return Ok(
+      text
+        .lines()
+        .map(|line| line.trim_start())
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+          initial_args
+            .iter()
+            .map(|arg| arg.replace(replace.as_str(), line))
+            .collect()
+        })
+        .collect(),
+    );
   }
 
-  if let Some(delim) = &flags.delimiter {
+  let args = if let Some(delim) = &flags.delimiter {
     // strip a single trailing newline (xargs seems to do this)
     let text = if *delim == '\n' {
       if let Some(text) = text.strip_suffix(&delim.to_string()) {
@@ -72,14 +113,29 @@ fn xargs_collect_args(
       &text
     };
 
-    args.extend(text.split(*delim).map(|t| t.to_string()));
+    text.split(*delim).map(|t| t.to_string()).collect()
   } else if flags.is_null_delimited {
-    args.extend(text.split('\0').map(|t| t.to_string()));
+    text.split('\0').map(|t| t.to_string()).collect()
   } else {
-    args.extend(delimit_blanks(&text)?);
-  }
+    delimit_blanks(&text)?
+  };
 
-  Ok(args)
+  match flags.max_args {
+    Some(max_args) if !args.is_empty() => Ok(
+      args
+        .chunks(max_args)
+        .map(|chunk| {
+          let mut args = initial_args.clone();
+          args.extend(chunk.iter().cloned());
+          args
+        })
+        .collect(),
+    ),
+    _ => {
+      initial_args.extend(args);
+      Ok(vec![initial_args])
+    }
+  }
 }
 
 fn delimit_blanks(text: &str) -> Result<Vec<String>> {
@@ -129,6 +185,10 @@ struct XargsFlags {
   initial_args: Vec<String>,
   delimiter: Option<char>,
   is_null_delimited: bool,
+  /// `-n N`
+  max_args: Option<usize>,
+  /// `-I REPLACE`
+  replace: Option<String>,
 }
 
 fn parse_args(args: Vec<String>) -> Result<XargsFlags> {
@@ -163,6 +223,8 @@ fn parse_args(args: Vec<String>) -> Result<XargsFlags> {
   let mut delimiter = None;
   let mut iterator = parse_arg_kinds(&args).into_iter();
   let mut is_null_delimited = false;
+  let mut max_args = None;
+  let mut replace = None;
   while let Some(arg) = iterator.next() {
     match arg {
       ArgKind::Arg(arg) => {
@@ -192,6 +254,17 @@ fn parse_args(args: Vec<String>) -> Result<XargsFlags> {
         }
         _ => bail!("expected delimiter argument following -d"),
       },
+      ArgKind::ShortFlag('n') => match iterator.next() {
+        Some(ArgKind::Arg(arg)) => match arg.parse::<usize>() {
+          Ok(value) if value > 0 => max_args = Some(value),
+          _ => bail!("value for -n option should be >= 1: {}", arg),
+        },
+        _ => bail!("expected number argument following -n"),
+      },
+      ArgKind::ShortFlag('I') => match iterator.next() {
+        Some(ArgKind::Arg(arg)) => replace = Some(arg.to_string()),
+        _ => bail!("expected replace string argument following -I"),
+      },
       ArgKind::LongFlag(flag) => {
         if let Some(arg) = flag.strip_prefix("delimiter=") {
           delimiter = Some(parse_delimiter(arg)?);
@@ -211,6 +284,8 @@ fn parse_args(args: Vec<String>) -> Result<XargsFlags> {
     initial_args,
     delimiter,
     is_null_delimited,
+    max_args,
+    replace,
   })
 }
 
@@ -227,6 +302,8 @@ mod test {
         initial_args: Vec::new(),
         delimiter: None,
         is_null_delimited: false,
+        max_args: None,
+        replace: None,
       }
     );
     assert_eq!(
@@ -247,6 +324,8 @@ mod test {
         ],
         delimiter: None,
         is_null_delimited: true,
+        max_args: None,
+        replace: None,
       }
     );
     assert_eq!(
@@ -260,6 +339,8 @@ mod test {
         initial_args: vec!["echo".to_string()],
         delimiter: Some('\n'),
         is_null_delimited: false,
+        max_args: None,
+        replace: None,
       }
     );
     assert_eq!(
@@ -273,8 +354,34 @@ mod test {
         initial_args: vec!["echo".to_string(), "-d".to_string()],
         delimiter: Some('5'),
         is_null_delimited: false,
+        max_args: None,
+        replace: None,
       }
     );
+    assert_eq!(
+      parse_args(vec![
+        "-n".to_string(),
+        "2".to_string(),
+        "-I".to_string(),
+        "{}".to_string(),
+        "echo".to_string()
+      ])
+      .unwrap(),
+      XargsFlags {
+        initial_args: vec!["echo".to_string()],
+        delimiter: None,
+        is_null_delimited: false,
+        max_args: Some(2),
+        replace: Some("{}".to_string()),
+      }
+    );
+    assert_eq!(
+      parse_args(vec!["-n".to_string(), "0".to_string()])
+        .err()
+        .unwrap()
+        .to_string(),
+      "value for -n option should be >= 1: 0",
+    );
     assert_eq!(
       parse_args(vec!["-d".to_string(), "5".to_string(), "-t".to_string()])
         .err()
@@ -1,8 +1,12 @@
 // Copyright 2018-2024 the Deno authors. MIT license.
 
+use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
 use std::path::Path;
 use std::rc::Rc;
+use std::time::Duration;
 
 use futures::future;
 use futures::future::LocalBoxFuture;
@@ -16,6 +20,7 @@ use tokio_util::sync::CancellationToken;
 
 use crate::parser::AssignmentOp;
 use crate::parser::BinaryOp;
+use crate::parser::BooleanList;
 use crate::parser::Condition;
 use crate::parser::ConditionInner;
 use crate::parser::ElsePart;
@@ -26,15 +31,20 @@ use crate::parser::UnaryOp;
 use crate::parser::VariableModifier;
 use crate::shell::commands::ShellCommand;
 use crate::shell::commands::ShellCommandContext;
+use crate::shell::extglob;
+use crate::shell::extglob::ExtGlobPattern;
 use crate::shell::types::pipe;
 use crate::shell::types::ArithmeticResult;
 use crate::shell::types::ArithmeticValue;
 use crate::shell::types::EnvChange;
+use crate::shell::types::ExecRedirect;
 use crate::shell::types::ExecuteResult;
 use crate::shell::types::FutureExecuteResult;
+use crate::shell::types::ShellOptions;
 use crate::shell::types::ShellPipeReader;
 use crate::shell::types::ShellPipeWriter;
 use crate::shell::types::ShellState;
+use crate::shell::types::StdioPipe;
 
 use crate::parser::Arithmetic;
 use crate::parser::ArithmeticPart;
@@ -46,6 +56,7 @@ use crate::parser::PipeSequence;
 use crate::parser::PipeSequenceOperator;
 use crate::parser::Pipeline;
 use crate::parser::PipelineInner;
+use crate::parser::PostArithmeticOp;
 use crate::parser::Redirect;
 use crate::parser::RedirectFd;
 use crate::parser::RedirectOp;
@@ -137,6 +148,55 @@ pub async fn execute_with_pipes(
   }
 }
 
+/// The output of a command executed with `execute_capture`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapturedOutput {
+  pub stdout: String,
+  pub stderr: String,
+  pub exit_code: i32,
+}
+
+/// Executes a `SequentialList` of commands with an empty stdin, capturing
+/// its stdout and stderr in memory.
+///
+/// This must be run within a tokio runtime and `LocalSet`.
+///
+/// ```
+/// use deno_task_shell::execute_capture;
+/// use deno_task_shell::parser::parse;
+/// use deno_task_shell::ShellState;
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// # tokio::task::LocalSet::new().run_until(async {
+/// let list = parse("echo hi").unwrap();
+/// let cwd = std::env::current_dir().unwrap();
+/// let state = ShellState::new(Default::default(), &cwd, Default::default());
+/// let output = execute_capture(list, state).await;
+/// assert_eq!(output.stdout, "hi\n");
+/// assert_eq!(output.stderr, "");
+/// assert_eq!(output.exit_code, 0);
+/// # }).await;
+/// # }
+/// ```
+pub async fn execute_capture(
+  list: SequentialList,
+  state: ShellState,
+) -> CapturedOutput {
+  // drop the writer so the commands see an empty stdin
+  let (stdin, _) = pipe();
+  let (stdout_reader, stdout) = pipe();
+  let (stderr_reader, stderr) = pipe();
+  let stdout_handle = stdout_reader.pipe_to_string_handle();
+  let stderr_handle = stderr_reader.pipe_to_string_handle();
+  let exit_code = execute_with_pipes(list, state, stdin, stdout, stderr).await;
+  CapturedOutput {
+    stdout: stdout_handle.await.unwrap(),
+    stderr: stderr_handle.await.unwrap(),
+    exit_code,
+  }
+}
+
 #[derive(Debug, PartialEq)]
 pub enum AsyncCommandBehavior {
   Wait,
@@ -145,32 +205,121 @@ pub enum AsyncCommandBehavior {
 
 /// Execute a `SequentialList` of commands in a deno_task_shell environment.
 pub fn execute_sequential_list(
+  list: SequentialList,
+  state: ShellState,
+  stdin: ShellPipeReader,
+  stdout: ShellPipeWriter,
+  stderr: ShellPipeWriter,
+  async_command_behavior: AsyncCommandBehavior,
+) -> FutureExecuteResult {
+  execute_list(
+    list,
+    state,
+    stdin,
+    stdout,
+    stderr,
+    async_command_behavior,
+    ListOrigin::Input(None),
+  )
+}
+
+/// Executes a `SequentialList` like [`execute_sequential_list`], with the
+/// text each item was parsed from (see
+/// [`parse_with_source`](crate::parser::parse_with_source)), which
+/// `set -v` prints instead of the canonical form of the items.
+pub fn execute_sequential_list_with_source(
+  list: SequentialList,
+  item_texts: Vec<String>,
+  state: ShellState,
+  stdin: ShellPipeReader,
+  stdout: ShellPipeWriter,
+  stderr: ShellPipeWriter,
+  async_command_behavior: AsyncCommandBehavior,
+) -> FutureExecuteResult {
+  // the texts don't belong to the list if it was changed after parsing
+  let item_texts = (item_texts.len() == list.items.len()).then_some(item_texts);
+  execute_list(
+    list,
+    state,
+    stdin,
+    stdout,
+    stderr,
+    async_command_behavior,
+    ListOrigin::Input(item_texts),
+  )
+}
+
+/// Where a list comes from, which decides what `set -v` echoes.
+enum ListOrigin {
+  /// The shell's input, with the text of each item when it's known
+  Input(Option<Vec<String>>),
+  /// A list within a command (ex. the body of an `if`), which isn't
+  /// echoed again
+  Nested,
+}
+
+fn execute_list(
   list: SequentialList,
   mut state: ShellState,
   stdin: ShellPipeReader,
   stdout: ShellPipeWriter,
   stderr: ShellPipeWriter,
   async_command_behavior: AsyncCommandBehavior,
+  origin: ListOrigin,
 ) -> FutureExecuteResult {
   async move {
+    let is_input = matches!(origin, ListOrigin::Input(_));
+    // nested lists were already given the streams replaced with `exec`
+    let (mut stdin, mut stdout, mut stderr) = if is_input {
+      state.exec_stdio(stdin, stdout, stderr)
+    } else {
+      (stdin, stdout, stderr)
+    };
     let mut final_exit_code = 0;
     let mut final_changes = Vec::new();
     let mut async_handles = Vec::new();
     let mut was_exit = false;
-    for item in list.items {
+    for (index, item) in list.items.into_iter().enumerate() {
+      if is_input && state.verbose() {
+        let text = match &origin {
+          ListOrigin::Input(Some(texts)) => texts[index].clone(),
+          _ => item.to_shell_string(),
+        };
+        let _ = stderr.clone().write_line(&text);
+      }
       if item.is_async {
-        let state = state.clone();
+        let (job_id, job_state) = state.start_job();
+        let main_token = state.token().clone();
         let stdin = stdin.clone();
         let stdout = stdout.clone();
         let stderr = stderr.clone();
         async_handles.push(tokio::task::spawn_local(async move {
-          let main_token = state.token().clone();
-          let result =
-            execute_sequence(item.sequence, state, stdin, stdout, stderr).await;
+          let result = execute_sequence(
+            item.sequence,
+            job_state.clone(),
+            stdin,
+            stdout,
+            stderr,
+          )
+          .await;
           let (exit_code, handles) = result.into_exit_code_and_handles();
-          wait_handles(exit_code, handles, main_token).await
+          let exit_code = wait_handles(exit_code, handles, main_token).await;
+          match job_state.finish_job(job_id) {
+            // like sh, a killed job exits with 128 + the signal
+            Some(signal) => 128 + signal,
+            None => exit_code,
+          }
         }));
+        // starting a job always succeeds, its exit code is only reported
+        // once the job is waited on
+        state.apply_env_var("?", "0");
+        state.set_last_command_exit_code(0);
       } else {
+        let triggers_err_trap = triggers_err_trap(&item.sequence);
+        // the failure is reported once, for the statement of the input
+        // that contains the failing command
+        let command_text = (is_input && state.exit_on_error_diagnostics())
+          .then(|| item.to_shell_string());
         let result = execute_sequence(
           item.sequence,
           state.clone(),
@@ -189,11 +338,37 @@ pub fn execute_sequential_list(
           ExecuteResult::Continue(exit_code, changes, handles) => {
             state.apply_changes(&changes);
             state.apply_env_var("?", &exit_code.to_string());
+            state.set_last_command_exit_code(exit_code);
+            for change in &changes {
+              if let EnvChange::ExecRedirect(redirect) = change {
+                redirect.apply(&mut stdin, &mut stdout, &mut stderr);
+              }
+            }
             final_changes.extend(changes);
             async_handles.extend(handles);
+            if triggers_err_trap && exit_code != 0 {
+              // the ERR trap runs before errexit aborts the list
+              let changes = execute_err_trap(
+                &mut state,
+                exit_code,
+                stdin.clone(),
+                stdout.clone(),
+                stderr.clone(),
+              )
+              .await;
+              final_changes.extend(changes);
+            }
             // use the final sequential item's exit code
             final_exit_code = exit_code;
+            if state.source_exited() {
+              break;
+            }
             if state.exit_on_error() && exit_code != 0 {
+              if let Some(command_text) = &command_text {
+                let _ = stderr.write_line(&format!(
+                  "shell: exiting because `{command_text}` failed with exit code {exit_code}"
+                ));
+              }
               break;
             }
           }
@@ -220,6 +395,82 @@ pub fn execute_sequential_list(
   .boxed_local()
 }
 
+/// Whether a failure of this sequence should run the `ERR` trap.
+///
+/// Like bash, negated pipelines and the commands of a boolean list other
+/// than the last one never trigger it. The last command of a boolean list
+/// is handled in `execute_sequence`. If clauses are skipped because the
+/// failing command in their body already ran the trap.
+fn triggers_err_trap(sequence: &Sequence) -> bool {
+  match sequence {
+    Sequence::Pipeline(pipeline) if !pipeline.negated => !matches!(
+      &pipeline.inner,
+      PipelineInner::Command(Command {
+        inner: CommandInner::If(_),
+        ..
+      })
+    ),
+    _ => false,
+  }
+}
+
+/// Whether the command still runs with `set -n`, so that the option can be
+/// turned off again (`set +n`) and the shell can `exit`.
+fn runs_in_no_exec(command: &Command) -> bool {
+  match &command.inner {
+    CommandInner::Simple(simple) => matches!(
+      simple.args.first().map(|word| word.parts().as_slice()),
+      Some([WordPart::Text(name)]) if name == "set" || name == "exit"
+    ),
+    _ => false,
+  }
+}
+
+/// Runs the command registered with `trap ... ERR`, if any, returning
+/// the changes it made to the shell state.
+async fn execute_err_trap(
+  state: &mut ShellState,
+  exit_code: i32,
+  stdin: ShellPipeReader,
+  stdout: ShellPipeWriter,
+  mut stderr: ShellPipeWriter,
+) -> Vec<EnvChange> {
+  let command = match state.trap("ERR") {
+    // an empty command ignores the condition
+    Some(command) if !command.trim().is_empty() => command.clone(),
+    _ => return Vec::new(),
+  };
+  let list = match crate::parser::parse(&command) {
+    Ok(list) => list,
+    Err(err) => {
+      let _ = stderr.write_line(&format!("trap: {err}"));
+      return Vec::new();
+    }
+  };
+  // failures inside the trap itself must not run the trap again
+  let mut trap_state = state.clone();
+  trap_state.apply_change(&EnvChange::RemoveTrap("ERR".to_string()));
+  trap_state.set_last_command_exit_code(exit_code);
+  let result = execute_list(
+    list,
+    trap_state,
+    stdin,
+    stdout,
+    stderr,
+    AsyncCommandBehavior::Wait,
+    ListOrigin::Nested,
+  )
+  .await;
+  let changes = match result {
+    ExecuteResult::Exit(..) => Vec::new(),
+    ExecuteResult::Continue(_, changes, _) => changes,
+  };
+  state.apply_changes(&changes);
+  // the trap does not change the exit status of the failed command
+  state.apply_env_var("?", &exit_code.to_string());
+  changes
+}
+
 async fn wait_handles(
   mut exit_code: i32,
   mut handles: Vec<JoinHandle<i32>>,
@@ -246,90 +497,158 @@ fn execute_sequence(
   sequence: Sequence,
   mut state: ShellState,
   stdin: ShellPipeReader,
-  mut stdout: ShellPipeWriter,
+  stdout: ShellPipeWriter,
   mut stderr: ShellPipeWriter,
 ) -> FutureExecuteResult {
   // requires boxed async because of recursive async
   async move {
     match sequence {
       Sequence::ShellVar(var) => {
-        let value =
-          match evaluate_word(var.value, &mut state, stdin, stderr.clone())
-            .await
-          {
+        if state.no_exec() {
+          if state.print_trace() {
+            let _ = stderr.write_line(&format!(
+              "{}{}={}",
+              state.trace_prefix(),
+              var.name,
+              var.value.to_shell_string()
+            ));
+          }
+          return ExecuteResult::from_exit_code(0);
+        }
+        let value = match evaluate_assignment_word(
+          var.value,
+          &mut state,
+          stdin,
+          stderr.clone(),
+        )
+        .await
+        {
+          Ok(value) => value,
+          Err(err) => {
+            return err.into_exit_code(&mut stderr);
+          }
+        };
+
+        // like bash, an assignment's exit code is the one of its last
+        // command substitution (ex. `x=$(false)` fails)
+        let exit_code = value.exit_code.unwrap_or(0);
+        let value: String = if state.is_integer_var(&var.name) {
+          match evaluate_integer_value(&value.value, &mut state).await {
             Ok(value) => value,
             Err(err) => {
-              return err.into_exit_code(&mut stderr);
+              let _ = stderr.write_line(&format!("{}: {:#}", var.name, err));
+              return ExecuteResult::from_exit_code(1);
             }
-          };
+          }
+        } else {
+          value.into()
+        };
 
         if state.print_trace() {
-          let _ = stdout.write_line(&format!("+ {}={}", var.name, value));
+          let _ = stderr.write_line(&format!(
+            "{}{}={}",
+            state.trace_prefix(),
+            var.name,
+            value
+          ));
         }
 
         ExecuteResult::Continue(
-          0,
-          vec![EnvChange::SetShellVar(var.name, value.into())],
+          exit_code,
+          vec![EnvChange::SetShellVar(var.name, value)],
           Vec::new(),
         )
       }
       Sequence::BooleanList(list) => {
-        let mut changes = vec![];
-        let first_result = execute_sequence(
-          list.current,
+        execute_boolean_list(*list, state, stdin, stdout, stderr, true).await
+      }
+      Sequence::Pipeline(pipeline) => {
+        execute_pipeline(pipeline, state, stdin, stdout, stderr).await
+      }
+    }
+  }
+  .boxed_local()
+}
+
+/// Executes a boolean list, which groups from the left (ex. `a || b && c`
+/// is `(a || b) && c`).
+///
+/// Only the last command of the whole list may run the `ERR` trap, so it
+/// is disabled for the lists nested on the left.
+fn execute_boolean_list(
+  list: BooleanList,
+  mut state: ShellState,
+  stdin: ShellPipeReader,
+  stdout: ShellPipeWriter,
+  stderr: ShellPipeWriter,
+  runs_err_trap: bool,
+) -> FutureExecuteResult {
+  // requires boxed async because of recursive async
+  async move {
+    let first_result = match list.current {
+      Sequence::BooleanList(current) => {
+        execute_boolean_list(
+          *current,
           state.clone(),
           stdin.clone(),
           stdout.clone(),
           stderr.clone(),
+          false,
         )
-        .await;
-        let (exit_code, mut async_handles) = match first_result {
-          ExecuteResult::Exit(_, _) => return first_result,
-          ExecuteResult::Continue(exit_code, sub_changes, async_handles) => {
-            changes.extend(sub_changes);
-            (exit_code, async_handles)
-          }
-        };
+        .await
+      }
+      current => {
+        execute_sequence(
+          current,
+          state.clone(),
+          stdin.clone(),
+          stdout.clone(),
+          stderr.clone(),
+        )
+        .await
+      }
+    };
+    let (exit_code, mut changes, mut async_handles) = match first_result {
+      ExecuteResult::Exit(_, _) => return first_result,
+      ExecuteResult::Continue(exit_code, changes, async_handles) => {
+        (exit_code, changes, async_handles)
+      }
+    };
 
-        state.apply_changes(&changes);
+    if !list.op.moves_next_for_exit_code(exit_code) || state.source_exited() {
+      return ExecuteResult::Continue(exit_code, changes, async_handles);
+    }
 
-        let next = if list.op.moves_next_for_exit_code(exit_code) {
-          Some(list.next)
-        } else {
-          let mut next = list.next;
-          loop {
-            // boolean lists always move right on the tree
-            match next {
-              Sequence::BooleanList(list) => {
-                if list.op.moves_next_for_exit_code(exit_code) {
-                  break Some(list.next);
-                }
-                next = list.next;
-              }
-              _ => break None,
-            }
-          }
-        };
-        if let Some(next) = next {
-          let next_result =
-            execute_sequence(next, state, stdin, stdout, stderr).await;
-          match next_result {
-            ExecuteResult::Exit(code, sub_handles) => {
-              async_handles.extend(sub_handles);
-              ExecuteResult::Exit(code, async_handles)
-            }
-            ExecuteResult::Continue(exit_code, sub_changes, sub_handles) => {
-              changes.extend(sub_changes);
-              async_handles.extend(sub_handles);
-              ExecuteResult::Continue(exit_code, changes, async_handles)
-            }
-          }
+    state.apply_changes(&changes);
+    state.set_last_command_exit_code(exit_code);
+
+    let triggers_err_trap = runs_err_trap && triggers_err_trap(&list.next);
+    let next_result = execute_sequence(
+      list.next,
+      state.clone(),
+      stdin.clone(),
+      stdout.clone(),
+      stderr.clone(),
+    )
+    .await;
+    match next_result {
+      ExecuteResult::Exit(code, sub_handles) => {
+        async_handles.extend(sub_handles);
+        ExecuteResult::Exit(code, async_handles)
+      }
+      ExecuteResult::Continue(exit_code, sub_changes, sub_handles) => {
+        async_handles.extend(sub_handles);
+        if triggers_err_trap && exit_code != 0 {
+          state.apply_changes(&sub_changes);
+          changes.extend(sub_changes);
+          changes.extend(
+            execute_err_trap(&mut state, exit_code, stdin, stdout, stderr)
+              .await,
+          );
         } else {
-          ExecuteResult::Continue(exit_code, changes, async_handles)
+          changes.extend(sub_changes);
         }
-      }
-      Sequence::Pipeline(pipeline) => {
-        execute_pipeline(pipeline, state, stdin, stdout, stderr).await
+        ExecuteResult::Continue(exit_code, changes, async_handles)
       }
     }
   }
@@ -381,16 +700,20 @@ enum RedirectPipe {
   Output(ShellPipeWriter, Option<Vec<EnvChange>>),
 }
 
+/// Resolves the pipe of the redirect along with its expanded target (ex. the
+/// path of `> $FILE`), which is shown by `set -x`.
 async fn resolve_redirect_pipe(
   redirect: &Redirect,
   state: &ShellState,
   stdin: &ShellPipeReader,
   stdout: &ShellPipeWriter,
   stderr: &mut ShellPipeWriter,
-) -> Result<RedirectPipe, ExecuteResult> {
+) -> Result<(RedirectPipe, IoFile), ExecuteResult> {
   match redirect.io_file.clone() {
     IoFile::Word(word) => {
-      resolve_redirect_word_pipe(word, &redirect.op, state, stdin, stderr).await
+      resolve_redirect_word_pipe(word, &redirect.op, state, stdin, stderr)
+        .await
+        .map(|(pipe, path)| (pipe, IoFile::Word(Word::new_word(&path))))
     }
     IoFile::Fd(fd) => match &redirect.op {
       RedirectOp::Input(RedirectOpInput::Redirect) => {
@@ -400,8 +723,8 @@ async fn resolve_redirect_pipe(
         Err(ExecuteResult::from_exit_code(1))
       }
       RedirectOp::Output(_op) => match fd {
-        1 => Ok(RedirectPipe::Output(stdout.clone(), None)),
-        2 => Ok(RedirectPipe::Output(stderr.clone(), None)),
+        1 => Ok((RedirectPipe::Output(stdout.clone(), None), IoFile::Fd(fd))),
+        2 => Ok((RedirectPipe::Output(stderr.clone(), None), IoFile::Fd(fd))),
         _ => {
           let _ = stderr.write_line(
             &format!("{:?}", miette!("shell: output redirecting file descriptors beyond stdout and stderr is not implemented")),
@@ -419,7 +742,7 @@ async fn resolve_redirect_word_pipe(
   state: &ShellState,
   stdin: &ShellPipeReader,
   stderr: &mut ShellPipeWriter,
-) -> Result<RedirectPipe, ExecuteResult> {
+) -> Result<(RedirectPipe, String), ExecuteResult> {
   fn handle_std_result(
     output_path: &Path,
     std_file_result: std::io::Result<std::fs::File>,
@@ -440,6 +763,7 @@ async fn resolve_redirect_word_pipe(
 
   let words = evaluate_word_parts(
     word.into_parts(),
+    ExpansionMode::Expand,
     &mut state.clone(),
     stdin.clone(),
     stderr.clone(),
@@ -468,27 +792,39 @@ async fn resolve_redirect_word_pipe(
   }
   let output_path = &words.value[0];
 
-  match &redirect_op {
+  let pipe = match &redirect_op {
     RedirectOp::Input(RedirectOpInput::Redirect) => {
       let output_path = state.cwd().join(output_path);
       let std_file_result =
         std::fs::OpenOptions::new().read(true).open(&output_path);
-      handle_std_result(&output_path, std_file_result, stderr).map(|std_file| {
-        RedirectPipe::Input(
-          ShellPipeReader::from_std(std_file),
-          Some(words.changes),
-        )
-      })
+      handle_std_result(&output_path, std_file_result, stderr).map(
+        |std_file| {
+          RedirectPipe::Input(
+            ShellPipeReader::from_std(std_file),
+            Some(words.changes),
+          )
+        },
+      )?
     }
     RedirectOp::Output(op) => {
       // cross platform suppress output
       if output_path == "/dev/null" {
-        return Ok(RedirectPipe::Output(
-          ShellPipeWriter::null(),
-          Some(words.changes),
+        return Ok((
+          RedirectPipe::Output(ShellPipeWriter::null(), Some(words.changes)),
+          output_path.clone(),
         ));
       }
       let output_path = state.cwd().join(output_path);
+      if *op == RedirectOpOutput::Overwrite
+        && state.no_clobber()
+        && output_path.is_file()
+      {
+        let _ = stderr.write_line(&format!(
+          "{}: cannot overwrite existing file",
+          words.value[0]
+        ));
+        return Err(ExecuteResult::from_exit_code(1));
+      }
       let is_append = *op == RedirectOpOutput::Append;
       let std_file_result = std::fs::OpenOptions::new()
         .write(true)
@@ -496,14 +832,17 @@ async fn resolve_redirect_word_pipe(
         .append(is_append)
         .truncate(!is_append)
         .open(&output_path);
-      handle_std_result(&output_path, std_file_result, stderr).map(|std_file| {
-        RedirectPipe::Output(
-          ShellPipeWriter::from_std(std_file),
-          Some(words.changes),
-        )
-      })
+      handle_std_result(&output_path, std_file_result, stderr).map(
+        |std_file| {
+          RedirectPipe::Output(
+            ShellPipeWriter::from_std(std_file),
+            Some(words.changes),
+          )
+        },
+      )?
     }
-  }
+  };
+  Ok((pipe, words.value[0].clone()))
 }
 
 async fn execute_command(
@@ -513,46 +852,77 @@ async fn execute_command(
   stdout: ShellPipeWriter,
   mut stderr: ShellPipeWriter,
 ) -> ExecuteResult {
-  let (stdin, stdout, mut stderr, changes) = if let Some(redirect) =
-    &command.redirect
-  {
-    let pipe = match resolve_redirect_pipe(
-      redirect,
-      &state,
-      &stdin,
-      &stdout,
-      &mut stderr,
-    )
-    .await
-    {
-      Ok(value) => value,
-      Err(value) => return value,
-    };
-    match pipe {
-      RedirectPipe::Input(pipe, changes) => match redirect.maybe_fd {
-        Some(_) => {
-          let _ = stderr.write_line(
-            "input redirects with file descriptors are not supported",
-          );
-          return ExecuteResult::from_exit_code(1);
-        }
-        None => (pipe, stdout, stderr, changes),
-      },
-      RedirectPipe::Output(pipe, changes) => match redirect.maybe_fd {
-        Some(RedirectFd::Fd(2)) => (stdin, stdout, pipe, changes),
-        Some(RedirectFd::Fd(1)) | None => (stdin, pipe, stderr, changes),
-        Some(RedirectFd::Fd(_)) => {
-          let _ = stderr.write_line(
-            "only redirecting to stdout (1) and stderr (2) is supported",
-          );
-          return ExecuteResult::from_exit_code(1);
-        }
-        Some(RedirectFd::StdoutStderr) => (stdin, pipe.clone(), pipe, changes),
-      },
+  if state.no_exec() && !runs_in_no_exec(&command) {
+    // like `sh -n`, commands are only traced and never run
+    if state.print_trace() {
+      let _ = stderr.write_line(&format!(
+        "{}{}",
+        state.trace_prefix(),
+        command.to_shell_string()
+      ));
     }
-  } else {
-    (stdin, stdout, stderr, None)
-  };
+    return ExecuteResult::from_exit_code(0);
+  }
+  // the trace goes to the original stderr rather than the redirect
+  let trace_stderr = stderr.clone();
+  let mut redirect_trace = String::new();
+  let mut redirected_fds: &[u32] = &[];
+  let (stdin, stdout, mut stderr, changes) =
+    if let Some(redirect) = &command.redirect {
+      let (pipe, io_file) = match resolve_redirect_pipe(
+        redirect,
+        &state,
+        &stdin,
+        &stdout,
+        &mut stderr,
+      )
+      .await
+      {
+        Ok(value) => value,
+        Err(value) => return value,
+      };
+      let expanded_redirect = Redirect {
+        io_file,
+        ..redirect.clone()
+      };
+      redirect_trace = format!(" {}", expanded_redirect.to_shell_string());
+      match pipe {
+        RedirectPipe::Input(pipe, changes) => match redirect.maybe_fd {
+          Some(_) => {
+            let _ = stderr.write_line(
+              "input redirects with file descriptors are not supported",
+            );
+            return ExecuteResult::from_exit_code(1);
+          }
+          None => {
+            redirected_fds = &[0];
+            (pipe, stdout, stderr, changes)
+          }
+        },
+        RedirectPipe::Output(pipe, changes) => match redirect.maybe_fd {
+          Some(RedirectFd::Fd(2)) => {
+            redirected_fds = &[2];
+            (stdin, stdout, pipe, changes)
+          }
+          Some(RedirectFd::Fd(1)) | None => {
+            redirected_fds = &[1];
+            (stdin, pipe, stderr, changes)
+          }
+          Some(RedirectFd::Fd(_)) => {
+            let _ = stderr.write_line(
+              "only redirecting to stdout (1) and stderr (2) is supported",
+            );
+            return ExecuteResult::from_exit_code(1);
+          }
+          Some(RedirectFd::StdoutStderr) => {
+            redirected_fds = &[1, 2];
+            (stdin, pipe.clone(), pipe, changes)
+          }
+        },
+      }
+    } else {
+      (stdin, stdout, stderr, None)
+    };
   let mut changes = if let Some(changes) = changes {
     state.apply_changes(&changes);
     changes
@@ -562,7 +932,17 @@ async fn execute_command(
   match command.inner {
     CommandInner::Simple(command) => {
       // This can change the state, so we need to pass it by mutable reference
-      execute_simple_command(command, &mut state, stdin, stdout, stderr).await
+      let trace = (trace_stderr, redirect_trace);
+      execute_simple_command(
+        command,
+        &mut state,
+        stdin,
+        stdout,
+        stderr,
+        trace,
+        redirected_fds,
+      )
+      .await
     }
     CommandInner::Subshell(list) => {
       // Here the state can be changed but we can not pass by reference
@@ -575,6 +955,28 @@ async fn execute_command(
         }
       }
     }
+    CommandInner::BraceGroup(list) => {
+      // unlike a subshell, the group runs in the current shell
+      match execute_list(
+        *list,
+        state,
+        stdin,
+        stdout,
+        stderr,
+        AsyncCommandBehavior::Yield,
+        ListOrigin::Nested,
+      )
+      .await
+      {
+        ExecuteResult::Exit(code, handles) => {
+          ExecuteResult::Exit(code, handles)
+        }
+        ExecuteResult::Continue(code, env_changes, handles) => {
+          changes.extend(env_changes);
+          ExecuteResult::Continue(code, changes, handles)
+        }
+      }
+    }
     CommandInner::If(if_clause) => {
       // The state can be changed
       execute_if_clause(if_clause, &mut state, stdin, stdout, stderr).await
@@ -595,11 +997,30 @@ async fn execute_command(
   }
 }
 
+/// Evaluates the value assigned to a variable with the integer attribute
+/// (`declare -i`) as an arithmetic expression.
+pub(crate) async fn evaluate_integer_value(
+  value: &str,
+  state: &mut ShellState,
+) -> Result<String, Error> {
+  if value.trim().is_empty() {
+    return Ok("0".to_string());
+  }
+  let arithmetic = crate::parser::parse_arithmetic(value)?;
+  let result = execute_arithmetic_expression(arithmetic, state).await?;
+  Ok(result.to_string())
+}
+
 async fn execute_arithmetic_expression(
   arithmetic: Arithmetic,
   state: &mut ShellState,
 ) -> Result<ArithmeticResult, Error> {
-  evaluate_arithmetic(&arithmetic, state).await
+  let result = evaluate_arithmetic(&arithmetic, state).await?;
+  if state.float_arithmetic() {
+    Ok(result)
+  } else {
+    Ok(result.truncate())
+  }
 }
 
 async fn evaluate_arithmetic(
@@ -662,11 +1083,17 @@ async fn evaluate_arithmetic_part(
       false_expr,
     } => {
       let cond = Box::pin(evaluate_arithmetic_part(condition, state)).await?;
-      if cond.is_zero() {
-        Box::pin(evaluate_arithmetic_part(true_expr, state)).await
+      // like in C, a nonzero condition is true
+      let branch = if cond.is_zero() {
+        false_expr
       } else {
-        Box::pin(evaluate_arithmetic_part(false_expr, state)).await
-      }
+        true_expr
+      };
+      let value = Box::pin(evaluate_arithmetic_part(branch, state)).await?;
+      let mut result = ArithmeticResult::new(value.value);
+      result.with_changes(cond.changes);
+      result.with_changes(value.changes);
+      Ok(result)
     }
     ArithmeticPart::BinaryArithmeticExpr {
       left,
@@ -690,16 +1117,23 @@ async fn evaluate_arithmetic_part(
       let val = Box::pin(evaluate_arithmetic_part(operand, state)).await?;
       apply_unary_op(*operator, val)
     }
-    ArithmeticPart::PostArithmeticExpr { operand, .. } => {
-      let val = Box::pin(evaluate_arithmetic_part(operand, state)).await?;
-      Ok(val)
+    ArithmeticPart::PreArithmeticExpr { operator, operand } => {
+      let (_, new_value) = apply_increment(operand, operator, state)?;
+      Ok(new_value)
     }
-    ArithmeticPart::Variable(name) => state
-      .get_var(name)
-      .and_then(|s| s.parse::<ArithmeticResult>().ok())
-      .ok_or_else(|| {
-        miette::miette!("Undefined or non-integer variable: {}", name)
-      }),
+    ArithmeticPart::PostArithmeticExpr { operand, operator } => {
+      let (old_value, new_value) = apply_increment(operand, operator, state)?;
+      let mut result = ArithmeticResult::new(old_value.value);
+      result.with_changes(new_value.changes);
+      Ok(result)
+    }
+    ArithmeticPart::Variable(name) => match state.get_var(name) {
+      Some(value) => value
+        .parse::<ArithmeticResult>()
+        .map_err(|e| miette::miette!("{}: {}", name, e)),
+      // like bash, unset variables evaluate to zero
+      None => Ok(ArithmeticResult::new(ArithmeticValue::Integer(0))),
+    },
     ArithmeticPart::Number(num_str) => num_str
       .parse::<ArithmeticResult>()
       .map_err(|e| miette::miette!(e.to_string())),
@@ -772,9 +1206,42 @@ fn apply_conditional_binary_op(
     } else {
       ArithmeticResult::new(ArithmeticValue::Integer(0))
     }),
+    BinaryOp::NewerThan | BinaryOp::OlderThan | BinaryOp::SameFile => {
+      Err(miette!("File comparisons are not supported in arithmetic"))
+    }
   }
 }
 
+/// Applies `++` or `--` to a variable, returning its old and new value.
+fn apply_increment(
+  operand: &ArithmeticPart,
+  operator: &PostArithmeticOp,
+  state: &mut ShellState,
+) -> Result<(ArithmeticResult, ArithmeticResult), Error> {
+  let ArithmeticPart::Variable(name) = operand else {
+    return Err(miette::miette!(
+      "increment and decrement require a variable"
+    ));
+  };
+  let old_value = match state.get_var(name) {
+    Some(value) => value
+      .parse::<ArithmeticResult>()
+      .map_err(|e| miette::miette!("{}: {}", name, e))?,
+    None => ArithmeticResult::new(ArithmeticValue::Integer(0)),
+  };
+  let one = ArithmeticResult::new(ArithmeticValue::Integer(1));
+  let mut new_value = match operator {
+    PostArithmeticOp::Increment => old_value.checked_add(&one)?,
+    PostArithmeticOp::Decrement => old_value.checked_sub(&one)?,
+  };
+  state.apply_env_var(name, &new_value.to_string());
+  new_value.with_changes(vec![EnvChange::SetShellVar(
+    name.clone(),
+    new_value.to_string(),
+  )]);
+  Ok((old_value, new_value))
+}
+
 fn apply_unary_op(
   op: UnaryArithmeticOp,
   val: ArithmeticResult,
@@ -816,9 +1283,11 @@ async fn execute_pipe_sequence(
       }
       PipelineInner::Command(command) => (stderr.clone(), command),
     };
+    let mut stage_state = state.clone();
+    stage_state.enter_pipeline_or_job();
     wait_tasks.push(execute_command(
       command,
-      state.clone(),
+      stage_state,
       last_output.take().unwrap(),
       output_writer.clone(),
       stderr.clone(),
@@ -834,7 +1303,7 @@ async fn execute_pipe_sequence(
 
   let (all_handles, changes): (Vec<_>, Vec<_>) = results
     .into_iter()
-    .map(|r| (r.into_handles_and_changes()))
+    .map(|r| r.into_handles_and_changes())
     .unzip();
   let all_handles: Vec<JoinHandle<i32>> =
     all_handles.into_iter().flatten().collect();
@@ -855,12 +1324,15 @@ async fn execute_pipe_sequence(
 
 async fn execute_subshell(
   list: Box<SequentialList>,
-  state: ShellState,
+  mut state: ShellState,
   stdin: ShellPipeReader,
   stdout: ShellPipeWriter,
   stderr: ShellPipeWriter,
 ) -> ExecuteResult {
-  let result = execute_sequential_list(
+  // like bash, the ERR trap is not inherited by subshells
+  state.apply_change(&EnvChange::RemoveTrap("ERR".to_string()));
+  state.enter_subshell();
+  let result = execute_list(
     *list,
     state,
     stdin,
@@ -868,6 +1340,7 @@ async fn execute_subshell(
     stderr,
     // yield async commands to the parent
     AsyncCommandBehavior::Yield,
+    ListOrigin::Nested,
   )
   .await;
 
@@ -909,13 +1382,14 @@ async fn execute_if_clause(
         changes: env_changes,
       }) => {
         changes.extend(env_changes);
-        let exec_result = execute_sequential_list(
+        let exec_result = execute_list(
           current_body,
           state.clone(),
           stdin,
           stdout,
           stderr,
           AsyncCommandBehavior::Yield,
+          ListOrigin::Nested,
         )
         .await;
         match exec_result {
@@ -940,13 +1414,14 @@ async fn execute_if_clause(
             current_else = elif_clause.else_part;
           }
           Some(ElsePart::Else(else_body)) => {
-            let exec_result = execute_sequential_list(
+            let exec_result = execute_list(
               else_body,
               state.clone(),
               stdin,
               stdout,
               stderr,
               AsyncCommandBehavior::Yield,
+              ListOrigin::Nested,
             )
             .await;
             match exec_result {
@@ -977,93 +1452,229 @@ async fn evaluate_condition(
   stdin: ShellPipeReader,
   stderr: ShellPipeWriter,
 ) -> Result<ConditionalResult, EvaluateWordTextError> {
-  let mut changes = Vec::new();
-  match condition.condition_inner {
-    ConditionInner::Binary { left, op, right } => {
-      let left =
-        evaluate_word(left, state, stdin.clone(), stderr.clone()).await?;
-      state.apply_changes(&left.changes);
-      changes.extend(left.clone().changes);
-
-      let right =
-        evaluate_word(right, state, stdin.clone(), stderr.clone()).await?;
-      state.apply_changes(&right.changes);
-      changes.extend(right.clone().changes);
-
-      // transform the string comparison to a numeric comparison if possible
-      if let Ok(left) = Into::<String>::into(left.clone()).parse::<i64>() {
-        if let Ok(right) = Into::<String>::into(right.clone()).parse::<i64>() {
-          return Ok(
-            match op {
-              BinaryOp::Equal => left == right,
-              BinaryOp::NotEqual => left != right,
-              BinaryOp::LessThan => left < right,
-              BinaryOp::LessThanOrEqual => left <= right,
-              BinaryOp::GreaterThan => left > right,
-              BinaryOp::GreaterThanOrEqual => left >= right,
+  evaluate_condition_inner(condition.condition_inner, state, stdin, stderr)
+    .await
+}
+
+fn evaluate_condition_inner(
+  condition_inner: ConditionInner,
+  state: &mut ShellState,
+  stdin: ShellPipeReader,
+  stderr: ShellPipeWriter,
+) -> LocalBoxFuture<'_, Result<ConditionalResult, EvaluateWordTextError>> {
+  async move {
+    let mut changes = Vec::new();
+    match condition_inner {
+      ConditionInner::Binary { left, op, right } => {
+        let left =
+          evaluate_word(left, state, stdin.clone(), stderr.clone()).await?;
+        state.apply_changes(&left.changes);
+        changes.extend(left.clone().changes);
+
+        // the right side of `==` and `!=` is a pattern
+        let is_pattern = matches!(op, BinaryOp::Equal | BinaryOp::NotEqual);
+        let right = if is_pattern {
+          evaluate_word_as_pattern(right, state, stdin.clone(), stderr.clone())
+            .await?
+        } else {
+          evaluate_word(right, state, stdin.clone(), stderr.clone()).await?
+        };
+        state.apply_changes(&right.changes);
+        changes.extend(right.clone().changes);
+
+        // integers are compared as numbers and other values as strings
+        let numbers = left
+          .value
+          .parse::<i64>()
+          .ok()
+          .zip(right.value.parse::<i64>().ok());
+        let compare = |is_match: fn(Ordering) -> bool| match numbers {
+          Some((left, right)) => is_match(left.cmp(&right)),
+          None => is_match(left.value.cmp(&right.value)),
+        };
+        let cwd = state.cwd();
+        let value = match op {
+          BinaryOp::Equal => match numbers {
+            Some((left, right)) => left == right,
+            None => {
+              matches_pattern(&left.value, &right.value, state.ext_glob())
             }
-            .into(),
-          );
+          },
+          BinaryOp::NotEqual => match numbers {
+            Some((left, right)) => left != right,
+            None => {
+              !matches_pattern(&left.value, &right.value, state.ext_glob())
+            }
+          },
+          BinaryOp::LessThan => compare(Ordering::is_lt),
+          BinaryOp::LessThanOrEqual => compare(Ordering::is_le),
+          BinaryOp::GreaterThan => compare(Ordering::is_gt),
+          BinaryOp::GreaterThanOrEqual => compare(Ordering::is_ge),
+          BinaryOp::NewerThan => {
+            is_newer_file(&cwd.join(&left.value), &cwd.join(&right.value))
+          }
+          BinaryOp::OlderThan => {
+            is_newer_file(&cwd.join(&right.value), &cwd.join(&left.value))
+          }
+          BinaryOp::SameFile => {
+            is_same_file(&cwd.join(&left.value), &cwd.join(&right.value))
+          }
+        };
+        Ok(ConditionalResult::new(value, changes))
+      }
+      ConditionInner::Unary { op, right } => {
+        let right =
+          evaluate_word(right, state, stdin.clone(), stderr.clone()).await?;
+        state.apply_changes(&right.changes);
+        changes.extend(right.clone().changes);
+        let right: String = right.into();
+        let path = state.cwd().join(&right);
+        let value = match op {
+          Some(UnaryOp::FileExists) => path.exists(),
+          Some(UnaryOp::BlockSpecial) => todo!(),
+          Some(UnaryOp::CharSpecial) => todo!(),
+          Some(UnaryOp::Directory) => path.is_dir(),
+          Some(UnaryOp::RegularFile) => path.is_file(),
+          Some(UnaryOp::SetGroupId) => todo!(),
+          Some(UnaryOp::SymbolicLink) => path
+            .symlink_metadata()
+            .map(|metadata| metadata.file_type().is_symlink())
+            .unwrap_or(false),
+          Some(UnaryOp::StickyBit) => todo!(),
+          Some(UnaryOp::NamedPipe) => todo!(),
+          Some(UnaryOp::Readable) => todo!(),
+          Some(UnaryOp::SizeNonZero) => path
+            .metadata()
+            .map(|metadata| metadata.len() > 0)
+            .unwrap_or(false),
+          Some(UnaryOp::TerminalFd) => todo!(),
+          Some(UnaryOp::SetUserId) => todo!(),
+          Some(UnaryOp::Writable) => todo!(),
+          Some(UnaryOp::Executable) => todo!(),
+          Some(UnaryOp::OwnedByEffectiveGroupId) => todo!(),
+          Some(UnaryOp::ModifiedSinceLastRead) => todo!(),
+          Some(UnaryOp::OwnedByEffectiveUserId) => todo!(),
+          Some(UnaryOp::Socket) => todo!(),
+          Some(UnaryOp::NonEmptyString) => !right.is_empty(),
+          Some(UnaryOp::EmptyString) => right.is_empty(),
+          Some(UnaryOp::VariableSet) => state.get_var(&right).is_some(),
+          // name references (`declare -n`) are not supported
+          Some(UnaryOp::VariableNameReference) => false,
+          // a bare word is true when it is not empty
+          None => !right.is_empty(),
+        };
+        Ok(ConditionalResult::new(value, changes))
+      }
+      ConditionInner::Not(inner) => {
+        let result =
+          evaluate_condition_inner(*inner, state, stdin, stderr).await?;
+        Ok(ConditionalResult::new(!result.value, result.changes))
+      }
+      ConditionInner::Logical { left, op, right } => {
+        let left_result =
+          evaluate_condition_inner(*left, state, stdin.clone(), stderr.clone())
+            .await?;
+        changes.extend(left_result.changes);
+        let exit_code = if left_result.value { 0 } else { 1 };
+        // short-circuit when the left side already decides the result
+        if !op.moves_next_for_exit_code(exit_code) {
+          return Ok(ConditionalResult::new(left_result.value, changes));
         }
+        let right_result =
+          evaluate_condition_inner(*right, state, stdin, stderr).await?;
+        changes.extend(right_result.changes);
+        Ok(ConditionalResult::new(right_result.value, changes))
       }
+    }
+  }
+  .boxed_local()
+}
 
-      Ok(
-        match op {
-          BinaryOp::Equal => left == right,
-          BinaryOp::NotEqual => left != right,
-          BinaryOp::LessThan => left < right,
-          BinaryOp::LessThanOrEqual => left <= right,
-          BinaryOp::GreaterThan => left > right,
-          BinaryOp::GreaterThanOrEqual => left >= right,
-        }
-        .into(),
-      )
+/// Whether the first file was modified after the second one (`-nt`).
+/// Like bash, a missing file is older than an existing one.
+fn is_newer_file(path: &Path, other: &Path) -> bool {
+  let modified = |path: &Path| {
+    path
+      .metadata()
+      .and_then(|metadata| metadata.modified())
+      .ok()
+  };
+  match (modified(path), modified(other)) {
+    (Some(modified), Some(other_modified)) => modified > other_modified,
+    (modified, other_modified) => {
+      modified.is_some() && other_modified.is_none()
     }
-    ConditionInner::Unary { op, right } => {
-      let _right =
-        evaluate_word(right, state, stdin.clone(), stderr.clone()).await?;
-      match op {
-        Some(UnaryOp::FileExists) => todo!(),
-        Some(UnaryOp::BlockSpecial) => todo!(),
-        Some(UnaryOp::CharSpecial) => todo!(),
-        Some(UnaryOp::Directory) => todo!(),
-        Some(UnaryOp::RegularFile) => todo!(),
-        Some(UnaryOp::SetGroupId) => todo!(),
-        Some(UnaryOp::SymbolicLink) => todo!(),
-        Some(UnaryOp::StickyBit) => todo!(),
-        Some(UnaryOp::NamedPipe) => todo!(),
-        Some(UnaryOp::Readable) => todo!(),
-        Some(UnaryOp::SizeNonZero) => todo!(),
-        Some(UnaryOp::TerminalFd) => todo!(),
-        Some(UnaryOp::SetUserId) => todo!(),
-        Some(UnaryOp::Writable) => todo!(),
-        Some(UnaryOp::Executable) => todo!(),
-        Some(UnaryOp::OwnedByEffectiveGroupId) => todo!(),
-        Some(UnaryOp::ModifiedSinceLastRead) => todo!(),
-        Some(UnaryOp::OwnedByEffectiveUserId) => todo!(),
-        Some(UnaryOp::Socket) => todo!(),
-        Some(UnaryOp::NonEmptyString) => todo!(),
-        Some(UnaryOp::EmptyString) => todo!(),
-        Some(UnaryOp::VariableSet) => todo!(),
-        Some(UnaryOp::VariableNameReference) => todo!(),
-        None => todo!(),
-      }
+  }
+}
+
+#[cfg(unix)]
+fn is_same_file(left: &Path, right: &Path) -> bool {
+  use std::os::unix::fs::MetadataExt;
+  match (left.metadata(), right.metadata()) {
+    (Ok(left), Ok(right)) => {
+      left.dev() == right.dev() && left.ino() == right.ino()
+    }
+    _ => false,
+  }
+}
+
+#[cfg(not(unix))]
+fn is_same_file(left: &Path, right: &Path) -> bool {
+  match (left.canonicalize(), right.canonicalize()) {
+    (Ok(left), Ok(right)) => left == right,
+    _ => false,
+  }
+}
+
+/// Collapses the `**` of a glob so that, like bash without `globstar`, it
+/// only matches within a single directory.
+fn without_globstar(pattern: &str) -> String {
+  let mut result = String::with_capacity(pattern.len());
+  for c in pattern.chars() {
+    if c != '*' || !result.ends_with('*') {
+      result.push(c);
     }
   }
+  result
+}
+
+/// Matches text against a pattern from the right side of `[[ a == b ]]`.
+fn matches_pattern(text: &str, pattern: &str, ext_glob: bool) -> bool {
+  if ext_glob && extglob::has_extglob(pattern) {
+    return ExtGlobPattern::new(pattern, true).matches(text);
+  }
+  match glob::Pattern::new(pattern) {
+    Ok(pattern) => pattern.matches_with(
+      text,
+      glob::MatchOptions {
+        case_sensitive: true,
+        // unlike for file names, `*` matches `/` and leading dots
+        require_literal_separator: false,
+        require_literal_leading_dot: false,
+      },
+    ),
+    // an invalid pattern only matches itself
+    Err(_) => text == pattern,
+  }
 }
 
+/// Executes the simple command, `trace` is where `set -x` writes to along
+/// with the text of the command's redirect.
 async fn execute_simple_command(
   command: SimpleCommand,
   state: &mut ShellState,
   stdin: ShellPipeReader,
-  mut stdout: ShellPipeWriter,
+  stdout: ShellPipeWriter,
   mut stderr: ShellPipeWriter,
+  trace: (ShellPipeWriter, String),
+  redirected_fds: &[u32],
 ) -> ExecuteResult {
+  let (mut trace_stderr, redirect_trace) = trace;
   let args =
     evaluate_args(command.args, state, stdin.clone(), stderr.clone()).await;
 
-  let (args, mut changes) = match args {
-    Ok(args) => (args.value, args.changes),
+  let (args, mut changes, substitution_exit_code) = match args {
+    Ok(args) => (args.value, args.changes, args.exit_code),
     Err(err) => {
       return err.into_exit_code(&mut stderr);
     }
@@ -1071,9 +1682,13 @@ async fn execute_simple_command(
 
   let mut state = state.clone();
   for env_var in command.env_vars {
-    let word_result =
-      evaluate_word(env_var.value, &mut state, stdin.clone(), stderr.clone())
-        .await;
+    let word_result = evaluate_assignment_word(
+      env_var.value,
+      &mut state,
+      stdin.clone(),
+      stderr.clone(),
+    )
+    .await;
     let word_result = match word_result {
       Ok(word_result) => word_result,
       Err(err) => {
@@ -1084,16 +1699,51 @@ async fn execute_simple_command(
     changes.extend(word_result.changes);
 
     if state.print_trace() {
-      let _ = stdout
-        .write_line(&format!("+ {:}={:}", env_var.name, word_result.value));
+      let _ = trace_stderr.write_line(&format!(
+        "{}{}={}",
+        state.trace_prefix(),
+        env_var.name,
+        word_result.value
+      ));
     }
   }
 
   if state.print_trace() {
-    let _ = stdout.write_line(&format!("+ {:}", args.join(" ")));
+    let _ = trace_stderr.write_line(&format!(
+      "{}{}{}",
+      state.trace_prefix(),
+      args.join(" "),
+      redirect_trace
+    ));
+  }
+
+  if let (true, Some(exit_code)) = (args.is_empty(), substitution_exit_code) {
+    // a command that expands to nothing, ex. `$(false)`, exits with the
+    // code of its last command substitution
+    return ExecuteResult::Continue(exit_code, changes, Vec::new());
+  }
+
+  if args == ["exec"] && !redirected_fds.is_empty() {
+    // `exec` without a command keeps its redirect for the rest of the shell
+    changes.extend(redirected_fds.iter().map(|fd| {
+      EnvChange::ExecRedirect(ExecRedirect::new(match fd {
+        0 => StdioPipe::Stdin(stdin.clone()),
+        1 => StdioPipe::Stdout(stdout.clone()),
+        _ => StdioPipe::Stderr(stderr.clone()),
+      }))
+    }));
+    return ExecuteResult::Continue(0, changes, Vec::new());
   }
 
-  let result = execute_command_args(args, state, stdin, stdout, stderr).await;
+  let result = match state.command_timeout() {
+    Some(timeout) => {
+      let state = state.with_child_token();
+      let token = state.token().clone();
+      let command = execute_command_args(args, state, stdin, stdout, stderr);
+      execute_with_timeout(command, token, timeout).await
+    }
+    None => execute_command_args(args, state, stdin, stdout, stderr).await,
+  };
   match result {
     ExecuteResult::Exit(code, handles) => ExecuteResult::Exit(code, handles),
     ExecuteResult::Continue(code, env_changes, handles) => {
@@ -1103,26 +1753,116 @@ async fn execute_simple_command(
   }
 }
 
+/// Cancels the command's token once the timeout elapses, then waits for
+/// the command to wind down (e.g. for a child process to be killed).
+async fn execute_with_timeout(
+  mut command: FutureExecuteResult,
+  token: CancellationToken,
+  timeout: Duration,
+) -> ExecuteResult {
+  tokio::select! {
+    result = &mut command => result,
+    _ = tokio::time::sleep(timeout) => {
+      token.cancel();
+      command.await
+    }
+  }
+}
+
+/// Expands the aliases of the command name. Like bash, the words of an
+/// alias are checked for aliases again, and the word after an alias whose
+/// value ends with a space is expanded too (ex. `alias sudo='sudo '`).
+/// Aliases that were already expanded are skipped so `alias ls=ls`
+/// doesn't loop forever.
+fn expand_aliases(args: Vec<String>, state: &ShellState) -> Vec<String> {
+  // each word is paired with whether the word after it should be expanded
+  let mut remaining = args
+    .into_iter()
+    .map(|arg| (arg, false))
+    .collect::<VecDeque<_>>();
+  let mut result = Vec::with_capacity(remaining.len());
+  let mut expanded = HashSet::new();
+  let mut check_alias = true;
+  while let Some((word, expand_next)) = remaining.pop_front() {
+    if !check_alias {
+      result.push(word);
+      result.extend(remaining.into_iter().map(|(word, _)| word));
+      break;
+    }
+    match state.alias_map().get(&word) {
+      Some(value) if expanded.insert(word.clone()) => {
+        let expand_next = expand_next || value.ends_with(char::is_whitespace);
+        let mut words = split_alias_value(value)
+          .into_iter()
+          .map(|word| (word, false))
+          .collect::<Vec<_>>();
+        // the first word of the alias is checked again, or for an empty
+        // alias the word after it when it takes the alias' position
+        check_alias = match words.last_mut() {
+          Some(last) => {
+            last.1 = expand_next;
+            true
+          }
+          None => expand_next || result.is_empty(),
+        };
+        for word in words.into_iter().rev() {
+          remaining.push_front(word);
+        }
+      }
+      _ => {
+        result.push(word);
+        check_alias = expand_next;
+      }
+    }
+  }
+  result
+}
+
+/// Splits the value of an alias into words, keeping quoted segments
+/// together (ex. `echo "a b"` is `echo` and `a b`).
+fn split_alias_value(value: &str) -> Vec<String> {
+  let mut words = Vec::new();
+  let mut current: Option<String> = None;
+  let mut chars = value.chars();
+  while let Some(c) = chars.next() {
+    match c {
+      '\'' | '"' => {
+        let word = current.get_or_insert_with(String::new);
+        for inner in chars.by_ref() {
+          if inner == c {
+            break;
+          }
+          word.push(inner);
+        }
+      }
+      '\\' => {
+        if let Some(escaped) = chars.next() {
+          current.get_or_insert_with(String::new).push(escaped);
+        }
+      }
+      c if c.is_whitespace() => {
+        if let Some(word) = current.take() {
+          words.push(word);
+        }
+      }
+      c => current.get_or_insert_with(String::new).push(c),
+    }
+  }
+  words.extend(current);
+  words
+}
+
 fn execute_command_args(
-  mut args: Vec<String>,
+  args: Vec<String>,
   state: ShellState,
   stdin: ShellPipeReader,
   stdout: ShellPipeWriter,
   mut stderr: ShellPipeWriter,
 ) -> FutureExecuteResult {
+  let mut args = expand_aliases(args, &state);
   let command_name = if args.is_empty() {
     String::new()
   } else {
-    // check if the command name is in the alias hashmap
-    if let Some(value) = state.alias_map().get(&args[0]) {
-      args.remove(0);
-      args = value
-        .iter()
-        .chain(args.iter())
-        .cloned()
-        .collect::<Vec<String>>();
-    }
-
     args.remove(0)
   };
 
@@ -1179,6 +1919,7 @@ pub async fn evaluate_args(
   for arg in args {
     let parts = evaluate_word_parts(
       arg.into_parts(),
+      ExpansionMode::Expand,
       state,
       stdin.clone(),
       stderr.clone(),
@@ -1196,12 +1937,73 @@ async fn evaluate_word(
   stderr: ShellPipeWriter,
 ) -> Result<WordResult, EvaluateWordTextError> {
   Ok(
-    evaluate_word_parts(word.into_parts(), state, stdin, stderr)
-      .await?
-      .into(),
+    evaluate_word_parts(
+      word.into_parts(),
+      ExpansionMode::Expand,
+      state,
+      stdin,
+      stderr,
+    )
+    .await?
+    .into(),
   )
 }
 
+/// Evaluates the value of a variable assignment, which isn't glob
+/// expanded and keeps the inner newlines of command substitutions.
+async fn evaluate_assignment_word(
+  word: Word,
+  state: &mut ShellState,
+  stdin: ShellPipeReader,
+  stderr: ShellPipeWriter,
+) -> Result<WordResult, EvaluateWordTextError> {
+  Ok(
+    evaluate_word_parts(
+      word.into_parts(),
+      ExpansionMode::Assignment,
+      state,
+      stdin,
+      stderr,
+    )
+    .await?
+    .into(),
+  )
+}
+
+/// Evaluates a word into a glob pattern without expanding it to file
+/// paths. Glob characters that were quoted are escaped so they only
+/// match literally.
+async fn evaluate_word_as_pattern(
+  word: Word,
+  state: &mut ShellState,
+  stdin: ShellPipeReader,
+  stderr: ShellPipeWriter,
+) -> Result<WordResult, EvaluateWordTextError> {
+  Ok(
+    evaluate_word_parts(
+      word.into_parts(),
+      ExpansionMode::Pattern,
+      state,
+      stdin,
+      stderr,
+    )
+    .await?
+    .into(),
+  )
+}
+
+/// How the expansions and unquoted glob characters in a word are handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ExpansionMode {
+  /// Expand globs to the matching file paths.
+  Expand,
+  /// Keep globs as a pattern (e.g. the right side of `[[ a == b ]]`).
+  Pattern,
+  /// Keep globs and the newlines of command substitutions as is, like
+  /// the value of `FOO=$(cmd)`.
+  Assignment,
+}
+
 #[derive(Debug, Error)]
 pub enum EvaluateWordTextError {
   #[error("glob: no matches found '{}'. {}", pattern, err)]
@@ -1213,12 +2015,19 @@ pub enum EvaluateWordTextError {
   NoFilesMatched { pattern: String },
   #[error("Failed to get home directory")]
   FailedToGetHomeDirectory(miette::Error),
+  #[error("{0}")]
+  Arithmetic(miette::Error),
 }
 
 impl EvaluateWordTextError {
   pub fn into_exit_code(self, stderr: &mut ShellPipeWriter) -> ExecuteResult {
     let _ = stderr.write_line(&self.to_string());
-    ExecuteResult::from_exit_code(1)
+    // like bash, a failed arithmetic expansion is a usage error
+    let exit_code = match self {
+      Self::Arithmetic(_) => 2,
+      _ => 1,
+    };
+    ExecuteResult::from_exit_code(exit_code)
   }
 }
 
@@ -1235,31 +2044,31 @@ impl VariableModifier {
     state: &mut ShellState,
     stdin: ShellPipeReader,
     stderr: ShellPipeWriter,
-  ) -> Result<(Text, Option<Vec<EnvChange>>), miette::Report> {
+  ) -> Result<(String, Option<Vec<EnvChange>>), miette::Report> {
     match self {
-      VariableModifier::DefaultValue(default_value) => {
-        match state.get_var(name) {
-          Some(v) => Ok((v.clone().into(), None)),
+      VariableModifier::DefaultValue { value, colon } => {
+        match get_set_var(state, name, *colon) {
+          Some(v) => Ok((v, None)),
           None => {
-            let v = evaluate_word(default_value.clone(), state, stdin, stderr)
+            let v = evaluate_word(value.clone(), state, stdin, stderr)
               .await
               .into_diagnostic()?;
-            Ok((v.value.into(), Some(v.changes)))
+            Ok((v.value, Some(v.changes)))
           }
         }
       }
-      VariableModifier::AssignDefault(default_value) => {
-        match state.get_var(name) {
-          Some(v) => Ok((v.clone().into(), None)),
+      VariableModifier::AssignDefault { value, colon } => {
+        match get_set_var(state, name, *colon) {
+          Some(v) => Ok((v, None)),
           None => {
-            let v = evaluate_word(default_value.clone(), state, stdin, stderr)
+            let v = evaluate_word(value.clone(), state, stdin, stderr)
               .await
               .into_diagnostic()?;
             state.apply_env_var(name, &v.value);
             let mut changes = v.changes;
             changes
               .push(EnvChange::SetShellVar(name.to_string(), v.value.clone()));
-            Ok((v.value.into(), Some(changes)))
+            Ok((v.value, Some(changes)))
           }
         }
       }
@@ -1324,27 +2133,40 @@ impl VariableModifier {
           Err(miette::miette!("Undefined variable: {}", name))
         }
       }
-      VariableModifier::AlternateValue(default_value) => {
-        let val = state.get_var(name);
-        if val.is_none() || val.unwrap().is_empty() {
-          Ok(("".to_string().into(), None))
+      VariableModifier::Length => {
+        let length = state.get_var(name).map(|v| v.chars().count());
+        Ok((length.unwrap_or(0).to_string(), None))
+      }
+      VariableModifier::AlternateValue { value, colon } => {
+        if get_set_var(state, name, *colon).is_none() {
+          Ok((String::new(), None))
         } else {
-          let v = evaluate_word(default_value.clone(), state, stdin, stderr)
+          let v = evaluate_word(value.clone(), state, stdin, stderr)
             .await
             .into_diagnostic()?;
-          Ok((v.value.into(), Some(v.changes)))
+          Ok((v.value, Some(v.changes)))
         }
       }
     }
   }
 }
 
+/// Gets the value of the variable when it's set. With `colon` (ex. `:-`
+/// instead of `-`), an empty value counts as unset.
+fn get_set_var(state: &ShellState, name: &str, colon: bool) -> Option<String> {
+  state
+    .get_var(name)
+    .filter(|value| !(colon && value.is_empty()))
+    .cloned()
+}
+
 fn evaluate_word_parts(
   parts: Vec<WordPart>,
+  expansion_mode: ExpansionMode,
   state: &mut ShellState,
   stdin: ShellPipeReader,
   stderr: ShellPipeWriter,
-) -> LocalBoxFuture<Result<WordPartsResult, EvaluateWordTextError>> {
+) -> LocalBoxFuture<'_, Result<WordPartsResult, EvaluateWordTextError>> {
   fn text_parts_to_string(parts: Vec<TextPart>) -> String {
     let mut result =
       String::with_capacity(parts.iter().map(|p| p.as_str().len()).sum());
@@ -1354,40 +2176,68 @@ fn evaluate_word_parts(
     result
   }
 
+  /// With `escape_extglob`, the parentheses that aren't part of an
+  /// extended glob are escaped so they only match literally.
+  fn text_parts_to_pattern(
+    parts: Vec<TextPart>,
+    escape_extglob: bool,
+  ) -> String {
+    let mut current_text = String::new();
+    for text_part in parts {
+      let (text, is_quoted) = match text_part {
+        TextPart::Quoted(text) => (text, true),
+        TextPart::Text(text) => (text, false),
+        TextPart::ExtGlob(text) => {
+          current_text.push_str(&text);
+          continue;
+        }
+      };
+      for c in text.chars() {
+        let escape = match c {
+          // escape because it was quoted
+          '?' | '*' | '[' | ']' => is_quoted,
+          '(' | ')' | '|' => escape_extglob,
+          _ => false,
+        };
+        if escape {
+          current_text.push('[');
+          current_text.push(c);
+          current_text.push(']');
+        } else {
+          current_text.push(c);
+        }
+      }
+    }
+    current_text
+  }
+
   fn evaluate_word_text(
     state: &ShellState,
     text_parts: Vec<TextPart>,
     is_quoted: bool,
+    expansion_mode: ExpansionMode,
   ) -> Result<WordPartsResult, EvaluateWordTextError> {
-    if !is_quoted
-      && text_parts
-        .iter()
-        .filter_map(|p| match p {
-          TextPart::Quoted(_) => None,
-          TextPart::Text(text) => Some(text.as_str()),
-        })
-        .any(|text| text.chars().any(|c| matches!(c, '?' | '*' | '[')))
-    {
-      let mut current_text = String::new();
-      for text_part in text_parts {
-        match text_part {
-          TextPart::Quoted(text) => {
-            for c in text.chars() {
-              match c {
-                '?' | '*' | '[' | ']' => {
-                  // escape because it was quoted
-                  current_text.push('[');
-                  current_text.push(c);
-                  current_text.push(']');
-                }
-                _ => current_text.push(c),
-              }
-            }
-          }
-          TextPart::Text(text) => {
-            current_text.push_str(&text);
-          }
+    if expansion_mode == ExpansionMode::Pattern && !is_quoted {
+      Ok(WordPartsResult::new(
+        vec![text_parts_to_pattern(text_parts, state.ext_glob())],
+        Vec::new(),
+      ))
+    } else if expansion_mode == ExpansionMode::Expand
+      && !is_quoted
+      && text_parts.iter().any(|p| match p {
+        TextPart::Quoted(_) => false,
+        TextPart::Text(text) => {
+          text.chars().any(|c| matches!(c, '?' | '*' | '['))
         }
+        TextPart::ExtGlob(_) => true,
+      })
+    {
+      let has_extglob =
+        text_parts.iter().any(|p| matches!(p, TextPart::ExtGlob(_)));
+      let mut current_text =
+        text_parts_to_pattern(text_parts, state.ext_glob());
+      if !state.glob_star() {
+        current_text = without_globstar(&current_text);
       }
       let is_absolute = std::path::PathBuf::from(&current_text).is_absolute();
       let cwd = state.cwd();
@@ -1396,64 +2246,71 @@ fn evaluate_word_parts(
       } else {
         format!("{}/{}", cwd.display(), current_text)
       };
-      let result = glob::glob_with(
-        &pattern,
-        glob::MatchOptions {
-          // false because it should work the same way on case insensitive file systems
-          case_sensitive: false,
-          // true because it copies what sh does
-          require_literal_separator: true,
-          // true because it copies with sh does—these files are considered "hidden"
-          require_literal_leading_dot: true,
-        },
-      );
-      match result {
-        Ok(paths) => {
-          let paths =
-            paths.into_iter().filter_map(|p| p.ok()).collect::<Vec<_>>();
-          if paths.is_empty() {
-            Err(EvaluateWordTextError::NoFilesMatched { pattern })
-          } else {
-            let paths = if is_absolute {
-              paths
-                .into_iter()
-                .map(|p| p.display().to_string())
-                .collect::<Vec<_>>()
-            } else {
-              paths
-                .into_iter()
-                .map(|p| {
-                  let path = p.strip_prefix(cwd).unwrap();
-                  path.display().to_string()
-                })
-                .collect::<Vec<_>>()
-            };
-            Ok(WordPartsResult::new(paths, Vec::new()))
+      let paths = if has_extglob {
+        extglob::glob_paths(&pattern, state.dot_glob(), !state.no_case_glob())
+      } else {
+        let result = glob::glob_with(
+          &pattern,
+          glob::MatchOptions {
+            // insensitive by default because it should work the same way on
+            // case insensitive file systems
+            case_sensitive: !state.no_case_glob(),
+            // true because it copies what sh does
+            require_literal_separator: true,
+            // true because it copies with sh does—these files are considered "hidden"
+            require_literal_leading_dot: !state.dot_glob(),
+          },
+        );
+        match result {
+          Ok(paths) => paths.into_iter().filter_map(|p| p.ok()).collect(),
+          Err(err) => {
+            return Err(EvaluateWordTextError::InvalidPattern { pattern, err })
           }
         }
-        Err(err) => Err(EvaluateWordTextError::InvalidPattern { pattern, err }),
+      };
+      if paths.is_empty() && state.null_glob() {
+        Ok(WordPartsResult::new(Vec::new(), Vec::new()))
+      } else if paths.is_empty() {
+        Err(EvaluateWordTextError::NoFilesMatched { pattern })
+      } else {
+        let paths = if is_absolute {
+          paths
+            .into_iter()
+            .map(|p| p.display().to_string())
+            .collect::<Vec<_>>()
+        } else {
+          paths
+            .into_iter()
+            .map(|p| {
+              let path = p.strip_prefix(cwd).unwrap();
+              path.display().to_string()
+            })
+            .collect::<Vec<_>>()
+        };
+        Ok(WordPartsResult::new(paths, Vec::new()))
       }
     } else {
-      Ok(WordPartsResult {
-        value: vec![text_parts_to_string(text_parts)],
-        changes: Vec::new(),
-      })
+      Ok(WordPartsResult::new(
+        vec![text_parts_to_string(text_parts)],
+        Vec::new(),
+      ))
     }
   }
 
   fn evaluate_word_parts_inner(
     parts: Vec<WordPart>,
     is_quoted: bool,
+    expansion_mode: ExpansionMode,
     state: &mut ShellState,
     stdin: ShellPipeReader,
     stderr: ShellPipeWriter,
-  ) -> LocalBoxFuture<Result<WordPartsResult, EvaluateWordTextError>> {
+  ) -> LocalBoxFuture<'_, Result<WordPartsResult, EvaluateWordTextError>> {
     // recursive async, so requires boxing
     async move {
       let mut result = WordPartsResult::new(Vec::new(), Vec::new());
       let mut current_text = Vec::new();
       for part in parts {
-        let evaluation_result_text: Result<Option<Text>, Error> = match part {
+        let evaluation_result_text: Result<Option<String>, Error> = match part {
           WordPart::Text(text) => {
             current_text.push(TextPart::Text(text));
             continue;
@@ -1467,16 +2324,17 @@ fn evaluate_word_parts(
                 result.with_changes(env_changes);
               }
               Ok(Some(text))
-            } else if let Some(val) =
-              state.get_var(&name).map(|v| v.to_string())
+            } else if let Some(val) = state
+              .get_dynamic_var(&name)
+              .or_else(|| state.get_var(&name).map(|v| v.to_string()))
             {
-              Ok(Some(val.into()))
+              Ok(Some(val))
             } else {
               Err(miette::miette!("Undefined variable: {}", name))
             }
           }
           WordPart::Command(list) => {
-            let cmd = evaluate_command_substitution(
+            let (cmd, exit_code) = evaluate_command_substitution(
               list,
               // contain cancellation to the command substitution
               &state.with_child_token(),
@@ -1484,12 +2342,14 @@ fn evaluate_word_parts(
               stderr.clone(),
             )
             .await;
-            Ok(Some(cmd.into()))
+            result.exit_code = Some(exit_code);
+            Ok(Some(cmd))
           }
           WordPart::Quoted(parts) => {
             let res = evaluate_word_parts_inner(
               parts,
               true,
+              expansion_mode,
               state,
               stdin.clone(),
               stderr.clone(),
@@ -1499,17 +2359,25 @@ fn evaluate_word_parts(
             let WordPartsResult {
               value,
               changes: env_changes,
+              exit_code,
             } = res;
             result.with_changes(env_changes);
+            result.exit_code = exit_code.or(result.exit_code);
             current_text.push(TextPart::Quoted(value.join(" ")));
             continue;
           }
           WordPart::Tilde(tilde_prefix) => {
             if tilde_prefix.only_tilde() {
-              let home_str = dirs::home_dir()
-                .ok_or_else(|| miette::miette!("Failed to get home directory"))?
-                .display()
-                .to_string();
+              // like sh, `$HOME` is preferred over the user's home directory
+              let home_str = match state.get_var("HOME") {
+                Some(home) => home.clone(),
+                None => dirs::home_dir()
+                  .ok_or_else(|| {
+                    miette::miette!("Failed to get home directory")
+                  })?
+                  .display()
+                  .to_string(),
+              };
               current_text.push(TextPart::Text(home_str));
               continue;
             } else {
@@ -1520,7 +2388,9 @@ fn evaluate_word_parts(
           }
           WordPart::Arithmetic(arithmetic) => {
             let arithmetic_result =
-              execute_arithmetic_expression(arithmetic, state).await?;
+              execute_arithmetic_expression(arithmetic, state)
+                .await
+                .map_err(EvaluateWordTextError::Arithmetic)?;
             current_text.push(TextPart::Text(arithmetic_result.to_string()));
             result.with_changes(arithmetic_result.changes);
             continue;
@@ -1530,10 +2400,72 @@ fn evaluate_word_parts(
             current_text.push(TextPart::Text(exit_code.to_string()));
             continue;
           }
+          WordPart::LineNumber(line) => {
+            // an assigned `LINENO` takes precedence over the parsed line
+            let line = match state.get_var("LINENO") {
+              Some(value) => value.clone(),
+              None => line.to_string(),
+            };
+            current_text.push(TextPart::Text(line));
+            continue;
+          }
+          WordPart::ExtGlob(parts) => {
+            // the variables within the pattern are not split
+            let WordPartsResult {
+              value,
+              changes: env_changes,
+              ..
+            } = evaluate_word_parts_inner(
+              parts,
+              true,
+              expansion_mode,
+              state,
+              stdin.clone(),
+              stderr.clone(),
+            )
+            .await?;
+            result.with_changes(env_changes);
+            let pattern = value.join(" ");
+            // without `extglob`, the pattern is only text
+            current_text.push(if state.ext_glob() {
+              TextPart::ExtGlob(pattern)
+            } else {
+              TextPart::Quoted(pattern)
+            });
+            continue;
+          }
         };
 
         if let Ok(Some(text)) = evaluation_result_text {
-          let mut parts = text.into_parts();
+          let mut ends_with_separator = false;
+          let mut parts =
+            if is_quoted || expansion_mode == ExpansionMode::Assignment {
+              // quoted expansions and assignments are not split
+              vec![TextPart::Text(text)]
+            } else {
+              let text = text.replace("\r\n", "\n");
+              let ifs = state.ifs().to_string();
+              let mut parts = Text::split_fields(&text, &ifs).into_parts();
+              // a separator at the start or end of the expansion ends the
+              // word before or after it, ex. `pre$x` with `x=" a"` is
+              // `pre a` instead of `prea`
+              let starts_with_whitespace = text
+                .starts_with(|c: char| c.is_whitespace() && ifs.contains(c));
+              if starts_with_whitespace && !current_text.is_empty() {
+                result.extend(evaluate_word_text(
+                  state,
+                  std::mem::take(&mut current_text),
+                  is_quoted,
+                  expansion_mode,
+                )?);
+                // the whitespace and a following separator split once
+                if parts.first().is_some_and(|part| part.as_str().is_empty()) {
+                  parts.remove(0);
+                }
+              }
+              ends_with_separator = text.ends_with(|c| ifs.contains(c));
+              parts
+            };
 
           if !parts.is_empty() {
             // append the first part to the current text
@@ -1546,6 +2478,7 @@ fn evaluate_word_parts(
                 state,
                 current_text,
                 is_quoted,
+                expansion_mode,
               )?);
 
               // store all the parts except the last one
@@ -1554,6 +2487,7 @@ fn evaluate_word_parts(
                   state,
                   vec![part],
                   is_quoted,
+                  expansion_mode,
                 )?);
               }
 
@@ -1562,53 +2496,122 @@ fn evaluate_word_parts(
               current_text = parts;
             }
           }
+
+          if ends_with_separator && !current_text.is_empty() {
+            result.extend(evaluate_word_text(
+              state,
+              std::mem::take(&mut current_text),
+              is_quoted,
+              expansion_mode,
+            )?);
+          }
         }
       }
       if !current_text.is_empty() {
-        result.extend(evaluate_word_text(state, current_text, is_quoted)?);
+        result.extend(evaluate_word_text(
+          state,
+          current_text,
+          is_quoted,
+          expansion_mode,
+        )?);
       }
       Ok(result)
     }
     .boxed_local()
   }
 
-  evaluate_word_parts_inner(parts, false, state, stdin, stderr)
+  evaluate_word_parts_inner(parts, false, expansion_mode, state, stdin, stderr)
 }
 
 async fn evaluate_command_substitution(
   list: SequentialList,
   state: &ShellState,
   stdin: ShellPipeReader,
-  stderr: ShellPipeWriter,
-) -> String {
-  let text = execute_with_stdout_as_text(|shell_stdout_writer| {
-    execute_sequential_list(
-      list,
-      state.clone(),
-      stdin,
-      shell_stdout_writer,
-      stderr,
-      AsyncCommandBehavior::Wait,
+  mut stderr: ShellPipeWriter,
+) -> (String, i32) {
+  let (text, exit_code) = if let Some(redirect) = get_file_read_redirect(&list)
+  {
+    // `$(< file)` reads the file without running a command
+    match resolve_redirect_pipe(
+      redirect,
+      state,
+      &stdin,
+      &ShellPipeWriter::null(),
+      &mut stderr,
     )
-  })
-  .await;
+    .await
+    {
+      Ok((RedirectPipe::Input(reader, _), _)) => {
+        (reader.pipe_to_string_handle().await.unwrap(), 0)
+      }
+      _ => (String::new(), 1),
+    }
+  } else {
+    // like subshells, command substitutions do not inherit the ERR trap,
+    // and like bash without `inherit_errexit` they ignore `set -e`
+    let mut state = state.clone();
+    state.apply_change(&EnvChange::RemoveTrap("ERR".to_string()));
+    state.apply_change(&EnvChange::SetShellOptions(
+      ShellOptions::ExitOnError,
+      false,
+    ));
+    state.enter_subshell();
+    execute_with_stdout_as_text(|shell_stdout_writer| {
+      execute_list(
+        list,
+        state,
+        stdin,
+        shell_stdout_writer,
+        stderr,
+        AsyncCommandBehavior::Wait,
+        ListOrigin::Nested,
+      )
+    })
+    .await
+  };
 
-  // Remove the trailing newline and then replace inner newlines with a space
-  // This seems to be what sh does, but I'm not entirely sure:
+  // Remove the trailing newline. The inner newlines are split on by the
+  // caller when the output is used as arguments:
   //
   // > echo $(echo 1 && echo -e "\n2\n")
   // 1 2
-  text
+  let text = text
     .strip_suffix("\r\n")
     .or_else(|| text.strip_suffix('\n'))
     .unwrap_or(&text)
-    .replace("\r\n", " ")
-    .replace('\n', " ")
+    .to_string();
+  (text, exit_code)
+}
+
+/// Gets the input redirect of a command substitution like `$(< file)`.
+fn get_file_read_redirect(list: &SequentialList) -> Option<&Redirect> {
+  let [item] = list.items.as_slice() else {
+    return None;
+  };
+  let Sequence::Pipeline(Pipeline {
+    negated: false,
+    inner: PipelineInner::Command(command),
+  }) = &item.sequence
+  else {
+    return None;
+  };
+  match (&command.inner, &command.redirect) {
+    (CommandInner::Simple(simple_command), Some(redirect))
+      if !item.is_async
+        && simple_command.args.is_empty()
+        && simple_command.env_vars.is_empty()
+        && redirect.maybe_fd.is_none()
+        && matches!(redirect.op, RedirectOp::Input(_)) =>
+    {
+      Some(redirect)
+    }
+    _ => None,
+  }
 }
 
 async fn execute_with_stdout_as_text(
   execute: impl FnOnce(ShellPipeWriter) -> FutureExecuteResult,
-) -> String {
+) -> (String, i32) {
   let (shell_stdout_reader, shell_stdout_writer) = pipe();
   let spawned_output = execute(shell_stdout_writer);
   let output_handle = tokio::task::spawn_blocking(move || {
@@ -1616,7 +2619,7 @@ async fn execute_with_stdout_as_text(
     shell_stdout_reader.pipe_to(&mut final_data).unwrap();
     final_data
   });
-  let _ = spawned_output.await;
+  let (exit_code, _) = spawned_output.await.into_exit_code_and_handles();
   let data = output_handle.await.unwrap();
-  String::from_utf8_lossy(&data).to_string()
+  (String::from_utf8_lossy(&data).to_string(), exit_code)
 }
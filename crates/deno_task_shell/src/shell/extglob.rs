@@ -0,0 +1,426 @@
+// Copyright 2018-2024 the Deno authors. MIT license.
+
+//! Matching of the extended globs enabled by `shopt -s extglob`, like
+//! `@(a|b)` or `!(*.txt)`, which the `glob` crate doesn't support.
+
+use std::path::Path;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GroupKind {
+  /// `?(...)`
+  ZeroOrOne,
+  /// `*(...)`
+  ZeroOrMore,
+  /// `+(...)`
+  OneOrMore,
+  /// `@(...)`
+  ExactlyOne,
+  /// `!(...)`
+  Not,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+  Char(char),
+  /// `?`
+  AnyChar,
+  /// `*`
+  AnyString,
+  /// `[abc]`, `[a-z]` or `[!abc]`
+  Class {
+    negated: bool,
+    ranges: Vec<(char, char)>,
+  },
+  /// A group of `|` separated patterns, ex. `@(a|b)`
+  Group(GroupKind, Vec<Vec<Token>>),
+}
+
+/// A glob pattern that may contain extended globs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtGlobPattern {
+  tokens: Vec<Token>,
+  case_sensitive: bool,
+}
+
+impl ExtGlobPattern {
+  /// Parses the pattern. Like in sh, an unclosed `[` or group is matched
+  /// literally instead of being an error.
+  pub fn new(pattern: &str, case_sensitive: bool) -> Self {
+    let chars = pattern.chars().collect::<Vec<_>>();
+    let mut index = 0;
+    let mut tokens = Vec::new();
+    while index < chars.len() {
+      // a `|` or `)` outside of a group is a literal
+      tokens.extend(parse_sequence(&chars, &mut index));
+      if let Some(&c) = chars.get(index) {
+        tokens.push(Token::Char(c));
+        index += 1;
+      }
+    }
+    Self {
+      tokens,
+      case_sensitive,
+    }
+  }
+
+  pub fn matches(&self, text: &str) -> bool {
+    let text = text.chars().collect::<Vec<_>>();
+    self.matches_tokens(&self.tokens, &text)
+  }
+
+  fn matches_tokens(&self, tokens: &[Token], text: &[char]) -> bool {
+    let Some((token, rest)) = tokens.split_first() else {
+      return text.is_empty();
+    };
+    match token {
+      Token::Char(c) => {
+        text.first().is_some_and(|t| self.chars_eq(*t, *c))
+          && self.matches_tokens(rest, &text[1..])
+      }
+      Token::AnyChar => {
+        !text.is_empty() && self.matches_tokens(rest, &text[1..])
+      }
+      Token::Class { negated, ranges } => {
+        text
+          .first()
+          .is_some_and(|t| self.class_matches(ranges, *t) != *negated)
+          && self.matches_tokens(rest, &text[1..])
+      }
+      Token::AnyString => {
+        (0..=text.len()).any(|len| self.matches_tokens(rest, &text[len..]))
+      }
+      Token::Group(kind, alternatives) => (0..=text.len()).any(|len| {
+        self.matches_group(*kind, alternatives, &text[..len])
+          && self.matches_tokens(rest, &text[len..])
+      }),
+    }
+  }
+
+  fn matches_group(
+    &self,
+    kind: GroupKind,
+    alternatives: &[Vec<Token>],
+    text: &[char],
+  ) -> bool {
+    match kind {
+      GroupKind::ExactlyOne => self.matches_any(alternatives, text),
+      GroupKind::ZeroOrOne => {
+        text.is_empty() || self.matches_any(alternatives, text)
+      }
+      GroupKind::ZeroOrMore => self.matches_repeated(alternatives, text),
+      GroupKind::OneOrMore => {
+        self.matches_any(alternatives, text)
+          || (!text.is_empty() && self.matches_repeated(alternatives, text))
+      }
+      GroupKind::Not => !self.matches_any(alternatives, text),
+    }
+  }
+
+  fn matches_any(&self, alternatives: &[Vec<Token>], text: &[char]) -> bool {
+    alternatives
+      .iter()
+      .any(|alternative| self.matches_tokens(alternative, text))
+  }
+
+  /// Whether the text is made of zero or more matches of the alternatives.
+  fn matches_repeated(
+    &self,
+    alternatives: &[Vec<Token>],
+    text: &[char],
+  ) -> bool {
+    text.is_empty()
+      || (1..=text.len()).any(|len| {
+        self.matches_any(alternatives, &text[..len])
+          && self.matches_repeated(alternatives, &text[len..])
+      })
+  }
+
+  fn chars_eq(&self, a: char, b: char) -> bool {
+    a == b || (!self.case_sensitive && a.to_lowercase().eq(b.to_lowercase()))
+  }
+
+  fn class_matches(&self, ranges: &[(char, char)], c: char) -> bool {
+    let in_ranges = |c: char| {
+      ranges
+        .iter()
+        .any(|(start, end)| (*start..=*end).contains(&c))
+    };
+    in_ranges(c)
+      || (!self.case_sensitive
+        && (c.to_lowercase().any(in_ranges) || c.to_uppercase().any(in_ranges)))
+  }
+}
+
+/// Parses tokens until the end of the pattern or a `|` or `)` that ends
+/// the alternative of a group, which is left unconsumed.
+fn parse_sequence(chars: &[char], index: &mut usize) -> Vec<Token> {
+  let mut tokens = Vec::new();
+  while let Some(&c) = chars.get(*index) {
+    match c {
+      '|' | ')' => break,
+      '@' | '!' | '*' | '+' | '?'
+        if chars.get(*index + 1) == Some(&'(')
+          && find_group_end(chars, *index + 1).is_some() =>
+      {
+        let kind = match c {
+          '@' => GroupKind::ExactlyOne,
+          '!' => GroupKind::Not,
+          '*' => GroupKind::ZeroOrMore,
+          '+' => GroupKind::OneOrMore,
+          _ => GroupKind::ZeroOrOne,
+        };
+        *index += 2;
+        let mut alternatives = vec![parse_sequence(chars, index)];
+        while chars.get(*index) == Some(&'|') {
+          *index += 1;
+          alternatives.push(parse_sequence(chars, index));
+        }
+        // skip the closing `)`
+        *index += 1;
+        tokens.push(Token::Group(kind, alternatives));
+      }
+      '*' => {
+        if tokens.last() != Some(&Token::AnyString) {
+          tokens.push(Token::AnyString);
+        }
+        *index += 1;
+      }
+      '?' => {
+        tokens.push(Token::AnyChar);
+        *index += 1;
+      }
+      '[' => match parse_class(chars, *index) {
+        Some((token, end)) => {
+          tokens.push(token);
+          *index = end;
+        }
+        None => {
+          tokens.push(Token::Char(c));
+          *index += 1;
+        }
+      },
+      _ => {
+        tokens.push(Token::Char(c));
+        *index += 1;
+      }
+    }
+  }
+  tokens
+}
+
+/// Finds the `)` that closes the group opened at `start`.
+fn find_group_end(chars: &[char], start: usize) -> Option<usize> {
+  let mut depth = 0;
+  for (index, c) in chars.iter().enumerate().skip(start) {
+    match c {
+      '(' => depth += 1,
+      ')' => {
+        depth -= 1;
+        if depth == 0 {
+          return Some(index);
+        }
+      }
+      _ => {}
+    }
+  }
+  None
+}
+
+/// Parses the bracket expression at `start`, returning the token and the
+/// index after the closing `]`.
+fn parse_class(chars: &[char], start: usize) -> Option<(Token, usize)> {
+  let mut index = start + 1;
+  let negated = matches!(chars.get(index), Some('!' | '^'));
+  if negated {
+    index += 1;
+  }
+  let mut ranges = Vec::new();
+  let mut is_first = true;
+  loop {
+    let c = *chars.get(index)?;
+    if c == ']' && !is_first {
+      return Some((Token::Class { negated, ranges }, index + 1));
+    }
+    is_first = false;
+    match (chars.get(index + 1), chars.get(index + 2)) {
+      (Some('-'), Some(&end)) if end != ']' => {
+        ranges.push((c, end));
+        index += 3;
+      }
+      _ => {
+        ranges.push((c, c));
+        index += 1;
+      }
+    }
+  }
+}
+
+/// Whether the pattern contains an extended glob group.
+pub fn has_extglob(pattern: &str) -> bool {
+  let chars = pattern.chars().collect::<Vec<_>>();
+  chars.windows(2).enumerate().any(|(index, window)| {
+    matches!(window, ['@' | '!' | '*' | '+' | '?', '('])
+      && find_group_end(&chars, index + 1).is_some()
+  })
+}
+
+/// Expands the path pattern to the existing paths, sorted within each
+/// directory. Like `*`, the patterns don't match a leading `.` of a file
+/// name unless `dot_glob` is set or the pattern starts with a `.`.
+pub fn glob_paths(
+  pattern: &str,
+  dot_glob: bool,
+  case_sensitive: bool,
+) -> Vec<PathBuf> {
+  let components = split_components(pattern);
+  let last_index = components.len() - 1;
+  let mut paths = vec![PathBuf::new()];
+  for (index, component) in components.into_iter().enumerate() {
+    if component.is_empty() {
+      if index == 0 {
+        paths = vec![PathBuf::from("/")];
+      }
+      continue;
+    }
+    if !component.contains(['*', '?', '[']) && !has_extglob(component) {
+      paths = paths.into_iter().map(|path| path.join(component)).collect();
+      continue;
+    }
+
+    let pattern = ExtGlobPattern::new(component, case_sensitive);
+    let mut matches = Vec::new();
+    for path in paths {
+      let dir = if path.as_os_str().is_empty() {
+        Path::new(".")
+      } else {
+        path.as_path()
+      };
+      let Ok(entries) = std::fs::read_dir(dir) else {
+        continue;
+      };
+      let mut names = entries
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter(|name| {
+          (dot_glob || !name.starts_with('.') || component.starts_with('.'))
+            && pattern.matches(name)
+        })
+        .collect::<Vec<_>>();
+      names.sort();
+      matches.extend(
+        names
+          .into_iter()
+          .map(|name| path.join(name))
+          .filter(|path| index == last_index || path.is_dir()),
+      );
+    }
+    paths = matches;
+  }
+  paths.retain(|path| path.exists());
+  paths
+}
+
+/// Splits the pattern on the `/` that are not inside a group.
+fn split_components(pattern: &str) -> Vec<&str> {
+  let mut components = Vec::new();
+  let mut depth = 0;
+  let mut start = 0;
+  for (index, c) in pattern.char_indices() {
+    match c {
+      '(' => depth += 1,
+      ')' => depth -= 1,
+      '/' if depth <= 0 => {
+        components.push(&pattern[start..index]);
+        start = index + 1;
+      }
+      _ => {}
+    }
+  }
+  components.push(&pattern[start..]);
+  components
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use pretty_assertions::assert_eq;
+
+  fn matches(pattern: &str, text: &str) -> bool {
+    ExtGlobPattern::new(pattern, true).matches(text)
+  }
+
+  #[test]
+  fn matches_extended_globs() {
+    assert!(matches("@(a|b).txt", "a.txt"));
+    assert!(matches("@(a|b).txt", "b.txt"));
+    assert!(!matches("@(a|b).txt", "c.txt"));
+    assert!(!matches("@(a|b).txt", "ab.txt"));
+
+    assert!(matches("!(*.txt)", "file.rs"));
+    assert!(!matches("!(*.txt)", "file.txt"));
+    assert!(matches("!(a)", ""));
+    assert!(matches("file!(.txt)", "file.rs"));
+
+    assert!(matches("?(a)b", "b"));
+    assert!(matches("?(a)b", "ab"));
+    assert!(!matches("?(a)b", "aab"));
+
+    assert!(matches("*(ab)c", "c"));
+    assert!(matches("*(ab)c", "ababc"));
+    assert!(!matches("*(ab)c", "abac"));
+
+    assert!(!matches("+(ab)c", "c"));
+    assert!(matches("+(ab)c", "abc"));
+    assert!(matches("+(a|b)", "abba"));
+
+    assert!(matches("@(x|+([0-9]))", "123"));
+    assert!(!matches("@(x|+([0-9]))", "12a"));
+  }
+
+  #[test]
+  fn matches_globs() {
+    assert!(matches("*.txt", "a.txt"));
+    assert!(matches("?.txt", "a.txt"));
+    assert!(!matches("?.txt", "ab.txt"));
+    assert!(matches("[a-c]", "b"));
+    assert!(!matches("[!a-c]", "b"));
+    assert!(matches("[]]", "]"));
+    assert!(matches("[(]a[)]", "(a)"));
+    assert!(matches("a|b)", "a|b)"));
+    assert!(matches("[a", "[a"));
+    assert!(!matches("A", "a"));
+    assert!(ExtGlobPattern::new("@(A)", false).matches("a"));
+  }
+
+  #[test]
+  fn finds_extended_globs() {
+    assert!(has_extglob("@(a|b)"));
+    assert!(has_extglob("x!(y)z"));
+    assert!(!has_extglob("@[(]a[)]"));
+    assert!(!has_extglob("@(a"));
+    assert!(!has_extglob("*.txt"));
+  }
+
+  #[test]
+  fn globs_paths() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let dir = temp_dir.path();
+    for file in ["a.txt", "b.txt", "c.rs", ".hidden", "sub/d.txt"] {
+      let path = dir.join(file);
+      std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+      std::fs::write(path, "").unwrap();
+    }
+    let glob = |pattern: &str, dot_glob: bool| {
+      glob_paths(&format!("{}/{}", dir.display(), pattern), dot_glob, false)
+        .into_iter()
+        .map(|path| path.strip_prefix(dir).unwrap().display().to_string())
+        .collect::<Vec<_>>()
+    };
+
+    assert_eq!(glob("!(*.txt)", false), vec!["c.rs", "sub"]);
+    assert_eq!(glob("!(*.txt)", true), vec![".hidden", "c.rs", "sub"]);
+    assert_eq!(glob("@(a|b).txt", false), vec!["a.txt", "b.txt"]);
+    assert_eq!(glob("@(sub)/*.txt", false), vec!["sub/d.txt"]);
+    assert_eq!(glob("@(x|y)", false), Vec::<String>::new());
+  }
+}
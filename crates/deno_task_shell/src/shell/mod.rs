@@ -6,17 +6,27 @@ pub use commands::ExecuteCommandArgsContext;
 pub use commands::ShellCommand;
 pub use commands::ShellCommandContext;
 pub use execute::execute;
+pub use execute::execute_capture;
+pub use execute::CapturedOutput;
 pub use execute::{
-  execute_sequential_list, execute_with_pipes, AsyncCommandBehavior,
+  execute_sequential_list, execute_sequential_list_with_source,
+  execute_with_pipes, AsyncCommandBehavior,
 };
+pub use session::Shell;
 pub use types::pipe;
+pub use types::BrokenPipeError;
+pub use types::CommandNotFoundHook;
+pub use types::CommandWrapper;
 pub use types::EnvChange;
+pub use types::ExecRedirect;
 pub use types::ExecuteResult;
 pub use types::FutureExecuteResult;
+pub use types::HashedCommand;
 pub use types::ShellOptions;
 pub use types::ShellPipeReader;
 pub use types::ShellPipeWriter;
 pub use types::ShellState;
+pub use types::StdioPipe;
 
 pub use commands::parse_arg_kinds;
 pub use commands::ArgKind;
@@ -26,4 +36,6 @@ pub mod fs_util;
 mod command;
 mod commands;
 mod execute;
+mod extglob;
+mod session;
 mod types;
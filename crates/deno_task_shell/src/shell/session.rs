@@ -0,0 +1,132 @@
+// Copyright 2018-2024 the Deno authors. MIT license.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::rc::Rc;
+
+use crate::parser::parse_with_source;
+
+use super::commands::ShellCommand;
+use super::execute::execute_sequential_list_with_source;
+use super::execute::AsyncCommandBehavior;
+use super::types::EnvChange;
+use super::types::ExecuteResult;
+use super::types::ShellPipeReader;
+use super::types::ShellPipeWriter;
+use super::types::ShellState;
+
+/// A shell that keeps its state (variables, aliases, current directory,
+/// options) between the lines it runs, like an interactive session.
+///
+/// This is the simplest way to embed the shell in another application:
+///
+/// ```no_run
+/// # async fn run() {
+/// use deno_task_shell::Shell;
+///
+/// let cwd = std::env::current_dir().unwrap();
+/// let mut shell = Shell::new(std::env::vars().collect(), &cwd, Default::default());
+/// shell.run_line("FOO=1").await;
+/// shell.run_line("echo $FOO").await;
+/// # }
+/// ```
+pub struct Shell {
+  state: ShellState,
+  last_exit_code: i32,
+}
+
+impl Shell {
+  pub fn new(
+    env_vars: HashMap<String, String>,
+    cwd: &Path,
+    custom_commands: HashMap<String, Rc<dyn ShellCommand>>,
+  ) -> Self {
+    Self::from_state(ShellState::new(env_vars, cwd, custom_commands))
+  }
+
+  pub fn from_state(state: ShellState) -> Self {
+    Self {
+      last_exit_code: state.last_command_exit_code(),
+      state,
+    }
+  }
+
+  /// Parses and executes a line using the process' standard streams,
+  /// returning its exit code.
+  pub async fn run_line(&mut self, line: &str) -> i32 {
+    self
+      .run_line_with_pipes(
+        line,
+        ShellPipeReader::stdin(),
+        ShellPipeWriter::stdout(),
+        ShellPipeWriter::stderr(),
+      )
+      .await
+  }
+
+  /// Parses and executes a line with the provided pipes, returning its
+  /// exit code. Changes made by the line are kept for the next lines.
+  pub async fn run_line_with_pipes(
+    &mut self,
+    line: &str,
+    stdin: ShellPipeReader,
+    stdout: ShellPipeWriter,
+    mut stderr: ShellPipeWriter,
+  ) -> i32 {
+    let (list, item_texts) = match parse_with_source(line) {
+      Ok(parsed) => parsed,
+      Err(err) => {
+        let _ = stderr.write_line(&format!("Syntax error: {:?}", err));
+        return self.set_exit_code(2);
+      }
+    };
+
+    self.state.reset_cancellation_token();
+    let result = execute_sequential_list_with_source(
+      list,
+      item_texts,
+      self.state.clone(),
+      stdin,
+      stdout,
+      stderr,
+      AsyncCommandBehavior::Wait,
+    )
+    .await;
+
+    match result {
+      ExecuteResult::Exit(exit_code, _) => self.set_exit_code(exit_code),
+      ExecuteResult::Continue(exit_code, changes, _) => {
+        self.state.apply_changes(&changes);
+        self.set_exit_code(exit_code)
+      }
+    }
+  }
+
+  fn set_exit_code(&mut self, exit_code: i32) -> i32 {
+    self.last_exit_code = exit_code;
+    self.state.set_last_command_exit_code(exit_code);
+    self.state.apply_change(&EnvChange::SetShellVar(
+      "?".to_string(),
+      exit_code.to_string(),
+    ));
+    exit_code
+  }
+
+  /// The current working directory of the shell.
+  pub fn cwd(&self) -> &Path {
+    self.state.cwd()
+  }
+
+  /// The exit code of the last line that was run.
+  pub fn last_exit_code(&self) -> i32 {
+    self.last_exit_code
+  }
+
+  pub fn state(&self) -> &ShellState {
+    &self.state
+  }
+
+  pub fn state_mut(&mut self) -> &mut ShellState {
+    &mut self.state
+  }
+}
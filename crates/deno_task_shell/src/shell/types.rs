@@ -1,8 +1,12 @@
 // Copyright 2018-2024 the Deno authors. MIT license.
 
 use std::borrow::Cow;
+use std::cell::Cell;
+use std::cell::RefCell;
 use std::cmp::Ordering;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::fmt;
 use std::fmt::Display;
 use std::fs;
@@ -12,16 +16,18 @@ use std::path::Path;
 use std::path::PathBuf;
 use std::rc::Rc;
 use std::str::FromStr;
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
 
 use futures::future::LocalBoxFuture;
 use miette::Error;
 use miette::IntoDiagnostic;
 use miette::Result;
+use path_dedot::ParseDot;
 use tokio::task::JoinHandle;
 use tokio_util::sync::CancellationToken;
 
-use crate::shell::fs_util;
-
 use super::commands::builtin_commands;
 use super::commands::ShellCommand;
 
@@ -38,7 +44,7 @@ pub struct ShellState {
   /// The commands that are available in the shell
   commands: Rc<HashMap<String, Rc<dyn ShellCommand>>>,
   /// A map of aliases for commands (e.g. `ll=ls -al`)
-  alias: HashMap<String, Vec<String>>,
+  alias: HashMap<String, String>,
   /// Token to cancel execution.
   token: CancellationToken,
   /// Git repository handling.
@@ -49,6 +55,58 @@ pub struct ShellState {
   last_command_exit_code: i32, // Exit code of the last command
   // The shell options to be modified using `set` command
   shell_options: HashMap<ShellOptions, bool>,
+  /// Commands registered with `trap`, keyed by condition (e.g. `ERR`)
+  traps: HashMap<String, String>,
+  /// Variables with the integer attribute (`declare -i`)
+  integer_vars: HashSet<String>,
+  /// Consulted when a command can't be resolved
+  command_not_found_hook: Option<CommandNotFoundHook>,
+  /// The executables started instead of wrapper scripts, keyed by the path
+  /// of the script
+  command_wrappers: Rc<HashMap<PathBuf, CommandWrapper>>,
+  /// Maximum duration of a single command before it is cancelled
+  command_timeout: Option<Duration>,
+  /// State of the generator for `$RANDOM`, shared between the clones so
+  /// consecutive commands get different numbers
+  random_state: Rc<Cell<u32>>,
+  /// The instant `$SECONDS` counts from
+  seconds_start: Instant,
+  /// The background jobs (`command &`), shared between the clones
+  jobs: Rc<RefCell<BTreeMap<usize, Job>>>,
+  /// How many subshells and command substitutions deep the state is
+  subshell_depth: usize,
+  /// Whether the state runs a stage of a pipeline or a background job,
+  /// which bash runs in a subshell of its own
+  in_pipeline_or_job: bool,
+  /// The commands found in `$PATH` (`hash`), shared between the clones
+  command_hash: Rc<RefCell<BTreeMap<String, HashedCommand>>>,
+  /// The standard streams replaced with `exec` (ex. `exec > file`)
+  exec_redirects: Vec<ExecRedirect>,
+  /// The files being executed with `source`, the innermost one last
+  source_files: Vec<String>,
+  /// Whether the commands are typed at a prompt
+  interactive: bool,
+  /// Set once `exit` runs in the file being sourced, shared between the
+  /// clones so every list of the file stops
+  source_exit: Option<Rc<Cell<bool>>>,
+}
+
+/// A command running in the background.
+struct Job {
+  token: CancellationToken,
+  /// The signal the job was killed with
+  signal: Option<i32>,
+}
+
+/// The remembered location of a command.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HashedCommand {
+  pub path: PathBuf,
+  /// How many times the remembered path was used
+  pub hits: usize,
+  /// The `$PATH` the command was found in, the path is only used again
+  /// with the same `$PATH`
+  search_path: String,
 }
 
 impl ShellState {
@@ -75,8 +133,25 @@ impl ShellState {
       shell_options: {
         let mut map = HashMap::new();
         map.insert(ShellOptions::ExitOnError, true);
+        map.insert(ShellOptions::GlobStar, true);
+        map.insert(ShellOptions::NoCaseGlob, true);
         map
       },
+      traps: Default::default(),
+      integer_vars: Default::default(),
+      command_not_found_hook: None,
+      command_wrappers: Default::default(),
+      command_timeout: None,
+      random_state: Rc::new(Cell::new(random_seed())),
+      seconds_start: Instant::now(),
+      jobs: Default::default(),
+      subshell_depth: 0,
+      in_pipeline_or_job: false,
+      command_hash: Default::default(),
+      exec_redirects: Vec::new(),
+      source_files: Vec::new(),
+      interactive: false,
+      source_exit: None,
     };
     // ensure the data is normalized
     for (name, value) in env_vars {
@@ -90,10 +165,15 @@ impl ShellState {
     &self.cwd
   }
 
-  pub fn alias_map(&self) -> &HashMap<String, Vec<String>> {
+  pub fn alias_map(&self) -> &HashMap<String, String> {
     &self.alias
   }
 
+  /// The names of the builtin and custom commands.
+  pub fn command_names(&self) -> impl Iterator<Item = &String> {
+    self.commands.keys()
+  }
+
   pub fn git_repository(&self) -> bool {
     self.git_repository
   }
@@ -122,6 +202,10 @@ impl ShellState {
     &self.env_vars
   }
 
+  pub fn shell_vars(&self) -> &HashMap<String, String> {
+    &self.shell_vars
+  }
+
   pub fn get_var(&self, name: &str) -> Option<&String> {
     let (original_name, updated_name) = if cfg!(windows) {
       (
@@ -137,6 +221,42 @@ impl ShellState {
       .or_else(|| self.shell_vars.get(original_name.as_ref()))
   }
 
+  /// Gets the characters that unquoted expansions are split on, which
+  /// is `$IFS` or else space, tab and newline.
+  pub fn ifs(&self) -> &str {
+    self
+      .get_var("IFS")
+      .map(|ifs| ifs.as_str())
+      .unwrap_or(" \t\n")
+  }
+
+  /// Gets the value of a variable that is computed on every expansion,
+  /// like `$RANDOM` and `$SECONDS`.
+  pub fn get_dynamic_var(&self, name: &str) -> Option<String> {
+    match name {
+      "RANDOM" => Some(self.next_random().to_string()),
+      "SECONDS" => Some(self.seconds_start.elapsed().as_secs().to_string()),
+      // subshells run within the shell's process rather than a fork of
+      // it, so `$BASHPID` is the same as `$$` within them
+      "$" | "BASHPID" => Some(std::process::id().to_string()),
+      // empty outside of a file, like in an interactive bash
+      "BASH_SOURCE" => {
+        Some(self.source_files.last().cloned().unwrap_or_default())
+      }
+      _ => None,
+    }
+  }
+
+  /// Returns the next number between 0 and 32767 of a xorshift generator.
+  fn next_random(&self) -> u32 {
+    let mut x = self.random_state.get();
+    x ^= x << 13;
+    x ^= x >> 17;
+    x ^= x << 5;
+    self.random_state.set(x);
+    x % 32768
+  }
+
   // Update self.git_branch using self.git_root
   pub fn update_git_branch(&mut self) {
     if self.git_repository {
@@ -222,6 +342,192 @@ impl ShellState {
     )
   }
 
+  /// The prefix of the `set -x` trace lines, `$PS4` (default `+ `) with
+  /// its first character repeated for each level of subshell nesting.
+  pub fn trace_prefix(&self) -> String {
+    let ps4 = self.get_var("PS4").map(String::as_str).unwrap_or("+ ");
+    match ps4.chars().next() {
+      Some(first) => {
+        format!("{}{}", first.to_string().repeat(self.subshell_depth), ps4)
+      }
+      None => String::new(),
+    }
+  }
+
+  pub(crate) fn enter_subshell(&mut self) {
+    self.subshell_depth += 1;
+    // an `exit` in a subshell only ends the subshell
+    self.source_exit = None;
+  }
+
+  /// Marks the state as executing a sourced file. An `exit` in the file
+  /// then only stops the file, keeping the changes it made so far.
+  pub fn enter_source(&mut self) {
+    self.source_exit = Some(Default::default());
+  }
+
+  /// Stops the sourced file being executed, returns false when no file is
+  /// being sourced.
+  pub(crate) fn exit_source(&self) -> bool {
+    match &self.source_exit {
+      Some(source_exit) => {
+        source_exit.set(true);
+        true
+      }
+      None => false,
+    }
+  }
+
+  /// Whether `exit` ran in the file being sourced.
+  pub fn source_exited(&self) -> bool {
+    matches!(&self.source_exit, Some(source_exit) if source_exit.get())
+  }
+
+  /// How many subshells and command substitutions deep the state is.
+  pub fn subshell_depth(&self) -> usize {
+    self.subshell_depth
+  }
+
+  pub(crate) fn enter_pipeline_or_job(&mut self) {
+    self.in_pipeline_or_job = true;
+  }
+
+  /// Whether a stage of a pipeline or a background job is executing.
+  pub fn in_pipeline_or_job(&self) -> bool {
+    self.in_pipeline_or_job
+  }
+
+  /// Marks the file as being executed, which is what `$BASH_SOURCE`
+  /// expands to until the state is dropped.
+  pub fn push_source_file(&mut self, path: String) {
+    self.source_files.push(path);
+  }
+
+  pub fn no_clobber(&self) -> bool {
+    matches!(self.shell_options.get(&ShellOptions::NoClobber), Some(true))
+  }
+
+  /// Marks the shell as reading its commands from a prompt.
+  pub fn set_interactive(&mut self, interactive: bool) {
+    self.interactive = interactive;
+  }
+
+  pub fn interactive(&self) -> bool {
+    self.interactive
+  }
+
+  /// Like bash, `set -n` is ignored by interactive shells so that the
+  /// prompt keeps working.
+  pub fn no_exec(&self) -> bool {
+    !self.interactive
+      && matches!(self.shell_options.get(&ShellOptions::NoExec), Some(true))
+  }
+
+  pub fn verbose(&self) -> bool {
+    matches!(self.shell_options.get(&ShellOptions::Verbose), Some(true))
+  }
+
+  pub fn dot_glob(&self) -> bool {
+    matches!(self.shell_options.get(&ShellOptions::DotGlob), Some(true))
+  }
+
+  pub fn glob_star(&self) -> bool {
+    matches!(self.shell_options.get(&ShellOptions::GlobStar), Some(true))
+  }
+
+  pub fn ext_glob(&self) -> bool {
+    matches!(self.shell_options.get(&ShellOptions::ExtGlob), Some(true))
+  }
+
+  pub fn null_glob(&self) -> bool {
+    matches!(self.shell_options.get(&ShellOptions::NullGlob), Some(true))
+  }
+
+  pub fn no_case_glob(&self) -> bool {
+    matches!(
+      self.shell_options.get(&ShellOptions::NoCaseGlob),
+      Some(true)
+    )
+  }
+
+  pub fn inherit_env(&self) -> bool {
+    matches!(
+      self.shell_options.get(&ShellOptions::InheritEnv),
+      Some(true)
+    )
+  }
+
+  pub fn exit_on_error_diagnostics(&self) -> bool {
+    matches!(
+      self
+        .shell_options
+        .get(&ShellOptions::ExitOnErrorDiagnostics),
+      Some(true)
+    )
+  }
+
+  pub fn float_arithmetic(&self) -> bool {
+    matches!(
+      self.shell_options.get(&ShellOptions::FloatArithmetic),
+      Some(true)
+    )
+  }
+
+  pub fn traps(&self) -> &HashMap<String, String> {
+    &self.traps
+  }
+
+  pub fn trap(&self, condition: &str) -> Option<&String> {
+    self.traps.get(condition)
+  }
+
+  /// Whether assignments to the variable are evaluated as arithmetic.
+  pub fn is_integer_var(&self, name: &str) -> bool {
+    self.integer_vars.contains(name)
+  }
+
+  /// Sets a hook that is called with the command name and arguments when
+  /// a command can't be resolved. Returning `None` falls back to the
+  /// default "command not found" error.
+  pub fn set_command_not_found_hook(
+    &mut self,
+    hook: impl Fn(&str, &[String]) -> Option<ExecuteResult> + 'static,
+  ) {
+    self.command_not_found_hook = Some(Rc::new(hook));
+  }
+
+  pub fn command_not_found_hook(&self) -> Option<&CommandNotFoundHook> {
+    self.command_not_found_hook.as_ref()
+  }
+
+  /// Registers the executable that the wrapper script at the path starts,
+  /// so that it's run directly with the variables the script would set.
+  /// This avoids a chain of processes for each command, like a `.bat`
+  /// activation script on Windows that starts `cmd.exe` and then the
+  /// real executable.
+  pub fn set_command_wrapper(
+    &mut self,
+    wrapper: impl Into<PathBuf>,
+    target: CommandWrapper,
+  ) {
+    Rc::make_mut(&mut self.command_wrappers).insert(wrapper.into(), target);
+  }
+
+  pub fn command_wrapper(&self, wrapper: &Path) -> Option<&CommandWrapper> {
+    self.command_wrappers.get(wrapper)
+  }
+
+  /// Sets the maximum duration of each command. A command still running
+  /// after it has elapsed is cancelled and exits with
+  /// `CANCELLATION_EXIT_CODE`.
+  pub fn set_command_timeout(&mut self, timeout: Option<Duration>) {
+    self.command_timeout = timeout;
+  }
+
+  pub fn command_timeout(&self) -> Option<Duration> {
+    self.command_timeout
+  }
+
   pub fn apply_changes(&mut self, changes: &[EnvChange]) {
     self.last_command_cd = false;
     for change in changes {
@@ -233,7 +539,14 @@ impl ShellState {
     match change {
       EnvChange::SetEnvVar(name, value) => self.apply_env_var(name, value),
       EnvChange::SetShellVar(name, value) => {
-        if self.env_vars.contains_key(name) {
+        if name == "SECONDS" {
+          // assigning `SECONDS` makes it count from that value
+          let seconds = value.parse::<u64>().unwrap_or(0);
+          self.seconds_start = Instant::now()
+            .checked_sub(Duration::from_secs(seconds))
+            .unwrap_or_else(Instant::now);
+        }
+        if self.env_vars.contains_key(name) || is_directory_var(name) {
           self.apply_env_var(name, value);
         } else {
           self.shell_vars.insert(name.to_string(), value.to_string());
@@ -241,6 +554,7 @@ impl ShellState {
       }
       EnvChange::UnsetVar(name) => {
         self.shell_vars.remove(name);
+        self.integer_vars.remove(name);
         if cfg!(windows) {
           // environment variables are case insensitive on windows
           self.env_vars.remove(&name.to_uppercase());
@@ -253,10 +567,7 @@ impl ShellState {
         self.last_command_cd = true;
       }
       EnvChange::AliasCommand(alias, cmd) => {
-        self.alias.insert(
-          alias.clone(),
-          cmd.split_whitespace().map(ToString::to_string).collect(),
-        );
+        self.alias.insert(alias.clone(), cmd.clone());
       }
       EnvChange::UnAliasCommand(alias) => {
         self.alias.remove(alias);
@@ -264,7 +575,40 @@ impl ShellState {
       EnvChange::SetShellOptions(option, value) => {
         self.set_shell_option(*option, *value);
       }
+      EnvChange::SetTrap(condition, command) => {
+        self.traps.insert(condition.clone(), command.clone());
+      }
+      EnvChange::RemoveTrap(condition) => {
+        self.traps.remove(condition);
+      }
+      EnvChange::SetIntegerAttribute(name, value) => {
+        if *value {
+          self.integer_vars.insert(name.clone());
+        } else {
+          self.integer_vars.remove(name);
+        }
+      }
+      EnvChange::ExecRedirect(redirect) => {
+        self
+          .exec_redirects
+          .retain(|existing| existing.fd() != redirect.fd());
+        self.exec_redirects.push(redirect.clone());
+      }
+    }
+  }
+
+  /// Replaces the given standard streams with the ones redirected with
+  /// `exec` (ex. `exec > file`).
+  pub fn exec_stdio(
+    &self,
+    mut stdin: ShellPipeReader,
+    mut stdout: ShellPipeWriter,
+    mut stderr: ShellPipeWriter,
+  ) -> (ShellPipeReader, ShellPipeWriter, ShellPipeWriter) {
+    for redirect in &self.exec_redirects {
+      redirect.apply(&mut stdin, &mut stdout, &mut stderr);
     }
+    (stdin, stdout, stderr)
   }
 
   pub fn apply_env_var(&mut self, name: &str, value: &str) {
@@ -274,13 +618,18 @@ impl ShellState {
     } else {
       name.to_string()
     };
-    if name == "PWD" {
-      let cwd = PathBuf::from(value);
-      if cwd.is_absolute() {
-        if let Ok(cwd) = fs_util::canonicalize_path(&cwd) {
-          // this will update the environment variable too
-          self.set_cwd(&cwd);
-        }
+    if name == "PWD" || name == "OLDPWD" {
+      // only existing absolute directories are accepted so the variables
+      // can't go out of sync with the working directory
+      let Some(dir) = normalize_dir(value) else {
+        return;
+      };
+      if name == "PWD" {
+        // this will update the environment variable too
+        self.set_cwd(&dir);
+      } else {
+        self.shell_vars.remove(&name);
+        self.env_vars.insert(name, dir.display().to_string());
       }
     } else {
       self.shell_vars.remove(&name);
@@ -320,6 +669,104 @@ impl ShellState {
   pub fn reset_cancellation_token(&mut self) {
     self.token = CancellationToken::default();
   }
+
+  /// Registers a background job and returns its id along with the state
+  /// it should run with, which is cancelled when the job is killed.
+  pub(crate) fn start_job(&self) -> (usize, ShellState) {
+    let mut state = self.with_child_token();
+    state.enter_pipeline_or_job();
+    let mut jobs = self.jobs.borrow_mut();
+    let id = jobs.keys().next_back().map(|id| id + 1).unwrap_or(1);
+    jobs.insert(
+      id,
+      Job {
+        token: state.token.clone(),
+        signal: None,
+      },
+    );
+    (id, state)
+  }
+
+  /// Removes a finished job and returns the signal it was killed with.
+  pub(crate) fn finish_job(&self, id: usize) -> Option<i32> {
+    self
+      .jobs
+      .borrow_mut()
+      .remove(&id)
+      .and_then(|job| job.signal)
+  }
+
+  /// Gets the id of the most recently started job that is still running
+  /// (`%%` or `%+`).
+  pub fn current_job_id(&self) -> Option<usize> {
+    self.jobs.borrow().keys().next_back().copied()
+  }
+
+  /// Stops a background job, returning `false` when there's no such job.
+  /// Signal `0` only checks that the job exists. Jobs run inside the shell,
+  /// so any other signal stops the job and is only reported in its exit
+  /// code; callers should only send signals that terminate.
+  pub fn kill_job(&self, id: usize, signal: i32) -> bool {
+    let mut jobs = self.jobs.borrow_mut();
+    let Some(job) = jobs.get_mut(&id) else {
+      return false;
+    };
+    if signal != 0 {
+      job.signal.get_or_insert(signal);
+      job.token.cancel();
+    }
+    true
+  }
+
+  /// The remembered command locations, sorted by name.
+  pub fn hashed_commands(&self) -> Vec<(String, HashedCommand)> {
+    let command_hash = self.command_hash.borrow();
+    command_hash
+      .iter()
+      .map(|(name, command)| (name.clone(), command.clone()))
+      .collect()
+  }
+
+  /// Forgets all remembered command locations (`hash -r`).
+  pub fn clear_command_hash(&self) {
+    self.command_hash.borrow_mut().clear();
+  }
+
+  pub(crate) fn forget_hashed_command(&self, name: &str) {
+    self.command_hash.borrow_mut().remove(name);
+  }
+
+  /// Gets the remembered location of the command when it was found in the
+  /// same `$PATH` and still exists.
+  pub(crate) fn hashed_command_path(
+    &self,
+    name: &str,
+    search_path: &str,
+  ) -> Option<PathBuf> {
+    let mut command_hash = self.command_hash.borrow_mut();
+    let command = command_hash.get_mut(name)?;
+    if command.search_path != search_path || !command.path.is_file() {
+      return None;
+    }
+    command.hits += 1;
+    Some(command.path.clone())
+  }
+
+  pub(crate) fn hash_command(
+    &self,
+    name: &str,
+    path: &Path,
+    search_path: &str,
+  ) {
+    self.command_hash.borrow_mut().insert(
+      name.to_string(),
+      HashedCommand {
+        path: path.to_path_buf(),
+        hits: 0,
+        search_path: search_path.to_string(),
+      },
+    );
+  }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, PartialOrd)]
@@ -338,6 +785,75 @@ pub enum EnvChange {
   Cd(PathBuf),
   /// `set -ex`
   SetShellOptions(ShellOptions, bool),
+  /// `trap 'echo failed' ERR`
+  SetTrap(String, String),
+  /// `trap - ERR`
+  RemoveTrap(String),
+  /// `declare -i VAR` or `declare +i VAR`
+  SetIntegerAttribute(String, bool),
+  /// `exec > file`
+  ExecRedirect(ExecRedirect),
+}
+
+/// A standard stream of the shell replaced with `exec` for the commands
+/// that follow it.
+///
+/// Redirects are compared by identity because pipes can't be compared.
+#[derive(Debug, Clone)]
+pub struct ExecRedirect(Rc<StdioPipe>);
+
+#[derive(Debug)]
+pub enum StdioPipe {
+  Stdin(ShellPipeReader),
+  Stdout(ShellPipeWriter),
+  Stderr(ShellPipeWriter),
+}
+
+impl ExecRedirect {
+  pub fn new(pipe: StdioPipe) -> Self {
+    Self(Rc::new(pipe))
+  }
+
+  pub fn pipe(&self) -> &StdioPipe {
+    &self.0
+  }
+
+  /// The file descriptor of the replaced stream.
+  pub fn fd(&self) -> u32 {
+    match self.pipe() {
+      StdioPipe::Stdin(_) => 0,
+      StdioPipe::Stdout(_) => 1,
+      StdioPipe::Stderr(_) => 2,
+    }
+  }
+
+  /// Replaces the matching stream with the redirected one.
+  pub fn apply(
+    &self,
+    stdin: &mut ShellPipeReader,
+    stdout: &mut ShellPipeWriter,
+    stderr: &mut ShellPipeWriter,
+  ) {
+    match self.pipe() {
+      StdioPipe::Stdin(pipe) => *stdin = pipe.clone(),
+      StdioPipe::Stdout(pipe) => *stdout = pipe.clone(),
+      StdioPipe::Stderr(pipe) => *stderr = pipe.clone(),
+    }
+  }
+}
+
+impl PartialEq for ExecRedirect {
+  fn eq(&self, other: &Self) -> bool {
+    Rc::ptr_eq(&self.0, &other.0)
+  }
+}
+
+impl Eq for ExecRedirect {}
+
+impl PartialOrd for ExecRedirect {
+  fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+    (self == other).then_some(std::cmp::Ordering::Equal)
+  }
 }
 
 #[derive(Clone, Copy, Hash, PartialEq, Eq, Debug, PartialOrd)]
@@ -346,6 +862,75 @@ pub enum ShellOptions {
   ExitOnError,
   /// If set, the shell print a trace of simple commands when they are invoked `-x`
   PrintTrace,
+  /// If set, `>` refuses to overwrite existing files `-C`
+  NoClobber,
+  /// If set, commands are parsed but not executed `-n`
+  NoExec,
+  /// If set, the shell prints its input lines before executing them `-v`
+  Verbose,
+  /// If set, globs also match file names starting with a `.` (`shopt -s dotglob`)
+  DotGlob,
+  /// If set, `**` in a glob matches any number of directories (`shopt -s globstar`)
+  GlobStar,
+  /// If set, extended globs like `@(a|b)` and `!(*.txt)` are matched (`shopt -s extglob`)
+  ExtGlob,
+  /// If set, globs without matches expand to nothing instead of failing (`shopt -s nullglob`)
+  NullGlob,
+  /// If set, globs match file names case insensitively (`shopt -s nocaseglob`)
+  NoCaseGlob,
+  /// If set, arithmetic results keep their fractional part instead of being
+  /// truncated to integers like in bash
+  FloatArithmetic,
+  /// If set, external commands inherit the environment of the process with
+  /// the exported variables of the shell on top, instead of only getting
+  /// the exported variables
+  InheritEnv,
+  /// If set, the shell reports the command that failed and its exit code
+  /// on stderr when it stops because of `-e`
+  ExitOnErrorDiagnostics,
+}
+
+fn random_seed() -> u32 {
+  let nanos = SystemTime::now()
+    .duration_since(SystemTime::UNIX_EPOCH)
+    .map(|duration| duration.subsec_nanos())
+    .unwrap_or(0);
+  // xorshift gets stuck at zero
+  (nanos ^ std::process::id()).max(1)
+}
+
+/// Whether the variable holds a directory that is validated on assignment.
+fn is_directory_var(name: &str) -> bool {
+  if cfg!(windows) {
+    name.eq_ignore_ascii_case("PWD") || name.eq_ignore_ascii_case("OLDPWD")
+  } else {
+    name == "PWD" || name == "OLDPWD"
+  }
+}
+
+/// Removes the `.` and `..` of the path when it is an existing absolute
+/// directory. Symlinks are kept so that `$PWD` stays logical.
+fn normalize_dir(value: &str) -> Option<PathBuf> {
+  let path = PathBuf::from(value);
+  if !path.is_absolute() {
+    return None;
+  }
+  path
+    .parse_dot()
+    .ok()
+    .map(|path| path.to_path_buf())
+    .filter(|path| path.is_dir())
+}
+
+pub type CommandNotFoundHook =
+  Rc<dyn Fn(&str, &[String]) -> Option<ExecuteResult>>;
+
+/// The executable a wrapper script starts along with the environment
+/// variables it sets (see [`ShellState::set_command_wrapper`]).
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandWrapper {
+  pub executable: PathBuf,
+  pub env_vars: HashMap<String, String>,
 }
 
 pub type FutureExecuteResult = LocalBoxFuture<'static, ExecuteResult>;
@@ -462,10 +1047,19 @@ impl ShellPipeReader {
       if size == 0 {
         break;
       }
-      writer.write_all(&buffer[0..size]).into_diagnostic()?;
-      if flush {
-        writer.flush().into_diagnostic()?;
+      let result = writer.write_all(&buffer[0..size]).and_then(|_| {
+        if flush {
+          writer.flush()
+        } else {
+          Ok(())
+        }
+      });
+      if is_broken_pipe_result(&result) {
+        // the consumer went away, so stop reading and drop this end of
+        // the pipe in order for the producer to stop as well
+        break;
       }
+      result.into_diagnostic()?;
     }
     Ok(())
   }
@@ -511,6 +1105,15 @@ impl ShellPipeReader {
 ///
 /// Ensure that all of these are dropped when complete in order to
 /// prevent deadlocks where the reader hangs waiting for a read.
+///
+/// Writes are never buffered: pipes and files are written directly and
+/// stdout and stderr are flushed after each write, so every stage of a
+/// pipeline is at least line buffered. Once the reader is dropped, writes
+/// fail, which is how a producer like `yes` in `yes | head` stops.
+///
+/// Builtins write synchronously on the shell's thread though, so a builtin
+/// that writes more than the pipe's buffer to another builtin blocks before
+/// the reader can run, ex. `cat big.txt | head -n 1` hangs.
 #[derive(Debug)]
 pub enum ShellPipeWriter {
   OsPipe(os_pipe::PipeWriter),
@@ -554,6 +1157,17 @@ impl ShellPipeWriter {
     Self::StdFile(std_file)
   }
 
+  /// Whether the output goes to a terminal, as opposed to a pipe or a file.
+  pub fn is_terminal(&self) -> bool {
+    use std::io::IsTerminal;
+    match self {
+      Self::OsPipe(_) | Self::Null => false,
+      Self::StdFile(file) => file.is_terminal(),
+      Self::Stdout => std::io::stdout().is_terminal(),
+      Self::Stderr => std::io::stderr().is_terminal(),
+    }
+  }
+
   pub fn into_stdio(self) -> std::process::Stdio {
     match self {
       Self::OsPipe(pipe) => pipe.into(),
@@ -564,31 +1178,69 @@ impl ShellPipeWriter {
     }
   }
 
+  /// Writes all the bytes to the output.
+  ///
+  /// A broken pipe is reported as a [`BrokenPipeError`], which commands
+  /// turn into a clean exit, see [`ShellPipeWriter::write_command_error`].
   pub fn write_all(&mut self, bytes: &[u8]) -> Result<()> {
-    match self {
-      Self::OsPipe(pipe) => pipe.write_all(bytes).into_diagnostic()?,
-      Self::StdFile(file) => file.write_all(bytes).into_diagnostic()?,
+    let result = match self {
+      Self::OsPipe(pipe) => pipe.write_all(bytes),
+      Self::StdFile(file) => file.write_all(bytes),
       // For both stdout & stderr, we want to flush after each
       // write in order to bypass Rust's internal buffer.
       Self::Stdout => {
         let mut stdout = std::io::stdout().lock();
-        stdout.write_all(bytes).into_diagnostic()?;
-        stdout.flush().into_diagnostic()?;
+        stdout.write_all(bytes).and_then(|_| stdout.flush())
       }
       Self::Stderr => {
         let mut stderr = std::io::stderr().lock();
-        stderr.write_all(bytes).into_diagnostic()?;
-        stderr.flush().into_diagnostic()?;
+        stderr.write_all(bytes).and_then(|_| stderr.flush())
       }
-      Self::Null => {}
+      Self::Null => Ok(()),
+    };
+    if is_broken_pipe_result(&result) {
+      return Err(miette::Report::new(BrokenPipeError));
     }
-    Ok(())
+    result.into_diagnostic()
   }
 
   pub fn write_line(&mut self, line: &str) -> Result<()> {
     let bytes = format!("{line}\n");
     self.write_all(bytes.as_bytes())
   }
+
+  /// Writes the error of a command as `name: error` and returns the exit
+  /// code to use for it.
+  ///
+  /// A [`BrokenPipeError`] only means the consumer stopped reading, so it
+  /// isn't written and the command exits with code 0.
+  pub fn write_command_error(
+    &mut self,
+    command_name: &str,
+    err: &miette::Report,
+  ) -> i32 {
+    if is_broken_pipe(err) {
+      return 0;
+    }
+    let _ = self.write_line(&format!("{command_name}: {err}"));
+    1
+  }
+}
+
+/// The error of writing to a pipe whose reader is gone, ex. when `head`
+/// exits after the first line in `cat file.txt | head -n 1`.
+#[derive(Debug, Clone, Copy, thiserror::Error)]
+#[error("Broken pipe")]
+pub struct BrokenPipeError;
+
+impl miette::Diagnostic for BrokenPipeError {}
+
+fn is_broken_pipe(err: &miette::Report) -> bool {
+  err.downcast_ref::<BrokenPipeError>().is_some()
+}
+
+fn is_broken_pipe_result(result: &std::io::Result<()>) -> bool {
+  matches!(result, Err(err) if err.kind() == std::io::ErrorKind::BrokenPipe)
 }
 
 /// Used to communicate between commands.
@@ -650,6 +1302,15 @@ impl ArithmeticResult {
     self.value = value;
   }
 
+  /// Truncates a float value towards zero, like bash's integer-only
+  /// arithmetic.
+  pub fn truncate(mut self) -> Self {
+    if let ArithmeticValue::Float(val) = self.value {
+      self.value = ArithmeticValue::Integer(val.trunc() as i64);
+    }
+    self
+  }
+
   pub fn checked_add(
     &self,
     other: &ArithmeticResult,
@@ -1145,40 +1806,101 @@ impl ArithmeticResult {
   }
 }
 
-impl From<String> for ArithmeticResult {
-  fn from(value: String) -> Self {
-    if let Ok(int_val) = value.parse::<i64>() {
-      ArithmeticResult::new(ArithmeticValue::Integer(int_val))
-    } else if let Ok(float_val) = value.parse::<f64>() {
-      ArithmeticResult::new(ArithmeticValue::Float(float_val))
+impl FromStr for ArithmeticResult {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let s = s.trim();
+    // like bash, an empty value evaluates to zero
+    if s.is_empty() {
+      Ok(ArithmeticResult::new(ArithmeticValue::Integer(0)))
+    } else if let Some(int_val) = parse_based_integer(s)? {
+      Ok(ArithmeticResult::new(ArithmeticValue::Integer(int_val)))
+    } else if let Ok(int_val) = s.parse::<i64>() {
+      Ok(ArithmeticResult::new(ArithmeticValue::Integer(int_val)))
+    } else if let Ok(float_val) = s.parse::<f64>() {
+      Ok(ArithmeticResult::new(ArithmeticValue::Float(float_val)))
     } else {
-      panic!("Invalid arithmetic result: {}", value);
+      Err(format!("invalid arithmetic operand: {}", s))
     }
   }
 }
 
-impl FromStr for ArithmeticResult {
-  type Err = String;
-
-  fn from_str(s: &str) -> Result<Self, Self::Err> {
-    Ok(s.to_string().into())
+/// Parses the integer literals that are not in base 10: hexadecimal (`0x1f`),
+/// octal (`017`) and `base#digits` (`2#1010`) with a base from 2 to 64.
+/// Returns `None` for other values.
+fn parse_based_integer(s: &str) -> Result<Option<i64>, String> {
+  let (negative, unsigned) = match s.strip_prefix('-') {
+    Some(rest) => (true, rest),
+    None => (false, s.strip_prefix('+').unwrap_or(s)),
+  };
+  let (base, digits) = if let Some((base, digits)) = unsigned.split_once('#') {
+    match base.parse::<u32>() {
+      Ok(base) if (2..=64).contains(&base) => (base, digits),
+      _ => return Err(format!("invalid arithmetic base: {}", s)),
+    }
+  } else if let Some(digits) = unsigned
+    .strip_prefix("0x")
+    .or_else(|| unsigned.strip_prefix("0X"))
+  {
+    (16, digits)
+  } else if unsigned.len() > 1
+    && unsigned.starts_with('0')
+    && unsigned.bytes().all(|b| b.is_ascii_digit())
+  {
+    (8, &unsigned[1..])
+  } else {
+    return Ok(None);
+  };
+
+  if digits.is_empty() {
+    return Err(format!("invalid integer constant: {}", s));
+  }
+  let mut value: i64 = 0;
+  for c in digits.chars() {
+    // like bash, letters of either case are the same digits up to base 36,
+    // after that lowercase, uppercase, `@` and `_` are the digits 10 to 63
+    let digit = match c {
+      '0'..='9' => c as u32 - '0' as u32,
+      'a'..='z' => c as u32 - 'a' as u32 + 10,
+      'A'..='Z' if base <= 36 => c as u32 - 'A' as u32 + 10,
+      'A'..='Z' => c as u32 - 'A' as u32 + 36,
+      '@' => 62,
+      '_' => 63,
+      _ => u32::MAX,
+    };
+    if digit >= base {
+      return Err(format!("value too great for base: {}", s));
+    }
+    value = value
+      .checked_mul(base as i64)
+      .and_then(|value| value.checked_add(digit as i64))
+      .ok_or_else(|| format!("integer overflow: {}", s))?;
   }
+  Ok(Some(if negative { -value } else { value }))
 }
 
 #[derive(Debug, Clone)]
 pub struct WordPartsResult {
   pub value: Vec<String>,
   pub changes: Vec<EnvChange>,
+  /// Exit code of the last command substitution in the words, if any.
+  pub exit_code: Option<i32>,
 }
 
 impl WordPartsResult {
   pub fn new(value: Vec<String>, changes: Vec<EnvChange>) -> Self {
-    WordPartsResult { value, changes }
+    WordPartsResult {
+      value,
+      changes,
+      exit_code: None,
+    }
   }
 
   pub fn extend(&mut self, other: WordPartsResult) {
     self.value.extend(other.value);
     self.changes.extend(other.changes);
+    self.exit_code = other.exit_code.or(self.exit_code);
   }
 
   pub fn join(&self, sep: &str) -> String {
@@ -1200,16 +1922,23 @@ impl From<WordPartsResult> for String {
 pub struct WordResult {
   pub value: String,
   pub changes: Vec<EnvChange>,
+  /// Exit code of the last command substitution in the word, if any.
+  pub exit_code: Option<i32>,
 }
 
 impl WordResult {
   pub fn new(value: String, changes: Vec<EnvChange>) -> Self {
-    WordResult { value, changes }
+    WordResult {
+      value,
+      changes,
+      exit_code: None,
+    }
   }
 
   pub fn extend(&mut self, other: WordResult) {
     self.value.push_str(&other.value);
     self.changes.extend(other.changes);
+    self.exit_code = other.exit_code.or(self.exit_code);
   }
 
   pub fn to_integer(&self) -> Result<i64, Error> {
@@ -1260,13 +1989,21 @@ impl From<WordResult> for String {
 
 impl From<WordPartsResult> for WordResult {
   fn from(parts: WordPartsResult) -> Self {
-    WordResult::new(parts.join(" "), parts.changes)
+    WordResult {
+      value: parts.join(" "),
+      changes: parts.changes,
+      exit_code: parts.exit_code,
+    }
   }
 }
 
 impl From<WordResult> for WordPartsResult {
   fn from(word: WordResult) -> Self {
-    WordPartsResult::new(vec![word.value], word.changes)
+    WordPartsResult {
+      value: vec![word.value],
+      changes: word.changes,
+      exit_code: word.exit_code,
+    }
   }
 }
 
@@ -1292,6 +2029,8 @@ impl From<bool> for ConditionalResult {
 pub enum TextPart {
   Quoted(String),
   Text(String),
+  /// An extended glob, only created when `extglob` is enabled.
+  ExtGlob(String),
 }
 
 impl TextPart {
@@ -1299,6 +2038,7 @@ impl TextPart {
     match self {
       TextPart::Quoted(text) => text,
       TextPart::Text(text) => text,
+      TextPart::ExtGlob(text) => text,
     }
   }
 }
@@ -1316,6 +2056,47 @@ impl Text {
   pub fn into_parts(self) -> Vec<TextPart> {
     self.parts
   }
+
+  /// Splits the text of an unquoted expansion into fields like sh does.
+  ///
+  /// Whitespace in `ifs` is trimmed and runs of it are a single
+  /// separator, while every other character of `ifs` separates a field,
+  /// so `a::b` with an `ifs` of `:` has an empty field in the middle.
+  pub fn split_fields(text: &str, ifs: &str) -> Self {
+    let is_ifs_whitespace = |c: char| c.is_whitespace() && ifs.contains(c);
+    let text = text.trim_matches(is_ifs_whitespace);
+    if ifs.is_empty() || text.is_empty() {
+      return Text::new(
+        Some(text)
+          .filter(|text| !text.is_empty())
+          .map(|text| TextPart::Text(text.to_string()))
+          .into_iter()
+          .collect(),
+      );
+    }
+
+    let mut fields = Vec::new();
+    let mut current = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+      if !ifs.contains(c) {
+        current.push(c);
+        continue;
+      }
+      while chars.next_if(|c| is_ifs_whitespace(*c)).is_some() {}
+      if is_ifs_whitespace(c) {
+        // whitespace around a single non-whitespace separator
+        if chars.next_if(|c| ifs.contains(*c)).is_some() {
+          while chars.next_if(|c| is_ifs_whitespace(*c)).is_some() {}
+        }
+      }
+      fields.push(TextPart::Text(std::mem::take(&mut current)));
+    }
+    if !current.is_empty() {
+      fields.push(TextPart::Text(current));
+    }
+    Text::new(fields)
+  }
 }
 
 impl From<String> for Text {
@@ -0,0 +1,168 @@
+// Copyright 2018-2024 the Shell authors. MIT license.
+
+use futures::future::LocalBoxFuture;
+use miette::bail;
+use miette::Result;
+
+use deno_task_shell::{
+    parse_arg_kinds, ArgKind, ExecuteResult, ShellCommand, ShellCommandContext, ShellState,
+};
+
+use crate::completion::list_executables;
+use crate::completion::list_files;
+
+pub struct CompgenCommand;
+
+impl ShellCommand for CompgenCommand {
+    fn execute(&self, mut context: ShellCommandContext) -> LocalBoxFuture<'static, ExecuteResult> {
+        let result = match execute_compgen(&context.args, &context.state) {
+            Ok(completions) if completions.is_empty() => ExecuteResult::from_exit_code(1),
+            Ok(completions) => {
+                for completion in completions {
+                    let _ = context.stdout.write_line(&completion);
+                }
+                ExecuteResult::from_exit_code(0)
+            }
+            Err(err) => {
+                ExecuteResult::from_exit_code(context.stderr.write_command_error("compgen", &err))
+            }
+        };
+        Box::pin(futures::future::ready(result))
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct CompgenFlags<'a> {
+    /// `-c`, builtins, aliases and executables in `$PATH`
+    commands: bool,
+    /// `-f`
+    files: bool,
+    /// `-d`
+    dirs: bool,
+    /// `-v`
+    variables: bool,
+    /// `-b`
+    builtins: bool,
+    word: &'a str,
+}
+
+/// Returns the sorted completions of the word.
+fn execute_compgen(args: &[String], state: &ShellState) -> Result<Vec<String>> {
+    let flags = parse_args(args)?;
+    let word = flags.word;
+    let mut completions = Vec::new();
+    if flags.builtins || flags.commands {
+        completions.extend(
+            state
+                .command_names()
+                .filter(|name| name.starts_with(word))
+                .cloned(),
+        );
+    }
+    if flags.commands {
+        completions.extend(
+            state
+                .alias_map()
+                .keys()
+                .filter(|name| name.starts_with(word))
+                .cloned(),
+        );
+        if let Some(path) = state.get_var("PATH") {
+            completions.extend(list_executables(path, word));
+        }
+    }
+    if flags.files || flags.dirs {
+        completions.extend(
+            list_files(state.cwd(), word, !flags.files)
+                .into_iter()
+                .map(|(path, _)| path),
+        );
+    }
+    if flags.variables {
+        completions.extend(
+            state
+                .env_vars()
+                .keys()
+                .chain(state.shell_vars().keys())
+                .filter(|name| name.starts_with(word))
+                .cloned(),
+        );
+    }
+    completions.sort();
+    completions.dedup();
+    Ok(completions)
+}
+
+fn parse_args(args: &[String]) -> Result<CompgenFlags<'_>> {
+    let mut flags = CompgenFlags::default();
+    let mut words = Vec::new();
+    for arg in parse_arg_kinds(args) {
+        match arg {
+            ArgKind::ShortFlag('c') => flags.commands = true,
+            ArgKind::ShortFlag('f') => flags.files = true,
+            ArgKind::ShortFlag('d') => flags.dirs = true,
+            ArgKind::ShortFlag('v') => flags.variables = true,
+            ArgKind::ShortFlag('b') => flags.builtins = true,
+            ArgKind::Arg(word) => words.push(word),
+            _ => arg.bail_unsupported()?,
+        }
+    }
+    if words.len() > 1 {
+        bail!("too many arguments");
+    }
+    if let Some(word) = words.pop() {
+        flags.word = word;
+    }
+    Ok(flags)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_args() {
+        assert_eq!(
+            parse_args(&args(&["-b", "-v", "ec"])).unwrap(),
+            CompgenFlags {
+                builtins: true,
+                variables: true,
+                word: "ec",
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            parse_args(&args(&["-c", "a", "b"]))
+                .err()
+                .unwrap()
+                .to_string(),
+            "too many arguments"
+        );
+    }
+
+    #[test]
+    fn lists_completions() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("sub_dir")).unwrap();
+        std::fs::write(dir.path().join("sub_file"), "").unwrap();
+        let state = ShellState::new(
+            HashMap::from([("SUB_VAR".to_string(), "1".to_string())]),
+            dir.path(),
+            HashMap::new(),
+        );
+        let compgen = |args_: &[&str]| execute_compgen(&args(args_), &state).unwrap();
+
+        assert_eq!(compgen(&["-b", "ec"]), vec!["echo"]);
+        assert_eq!(compgen(&["-f", "sub"]), vec!["sub_dir", "sub_file"]);
+        assert_eq!(compgen(&["-d", "sub"]), vec!["sub_dir"]);
+        assert_eq!(compgen(&["-v", "SUB"]), vec!["SUB_VAR"]);
+        assert_eq!(compgen(&["-d", "-v", "SUB"]), vec!["SUB_VAR"]);
+        assert!(compgen(&["-b", "missing"]).is_empty());
+    }
+}
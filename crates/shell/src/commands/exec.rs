@@ -0,0 +1,90 @@
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
+
+use deno_task_shell::{
+    ExecuteCommandArgsContext, ExecuteResult, ShellCommand, ShellCommandContext,
+};
+use futures::{future::LocalBoxFuture, FutureExt};
+
+/// Replaces the shell process with the command.
+///
+/// Builtins and commands within subshells, pipelines and background jobs,
+/// which share the process with the rest of the shell, run in place of the
+/// shell instead. Only the shell, or the subshell, pipeline stage or job,
+/// then exits with their exit code.
+pub struct ExecCommand;
+
+impl ShellCommand for ExecCommand {
+    fn execute(&self, mut context: ShellCommandContext) -> LocalBoxFuture<'static, ExecuteResult> {
+        if context.args.first().map(String::as_str) == Some("--") {
+            context.args.remove(0);
+        }
+        let Some(name) = context.args.first() else {
+            return Box::pin(futures::future::ready(ExecuteResult::from_exit_code(0)));
+        };
+
+        let path = if context.state.subshell_depth() == 0
+            && !context.state.in_pipeline_or_job()
+            && context.state.resolve_custom_command(name).is_none()
+        {
+            context.state.resolve_command_path(name).ok()
+        } else {
+            None
+        };
+        if let Some(path) = path {
+            let exit_code = replace_process(&path, &mut context);
+            return Box::pin(futures::future::ready(ExecuteResult::from_exit_code(
+                exit_code,
+            )));
+        }
+
+        let command = (context.execute_command_args)(ExecuteCommandArgsContext {
+            args: context.args,
+            state: context.state,
+            stdin: context.stdin,
+            stdout: context.stdout,
+            stderr: context.stderr,
+        });
+        async move {
+            match command.await {
+                ExecuteResult::Exit(code, handles) => ExecuteResult::Exit(code, handles),
+                ExecuteResult::Continue(code, _, handles) => ExecuteResult::Exit(code, handles),
+            }
+        }
+        .boxed_local()
+    }
+}
+
+/// Only returns when the command couldn't be started, with the exit code
+/// of the failure.
+fn replace_process(path: &Path, context: &mut ShellCommandContext) -> i32 {
+    let mut command = Command::new(path);
+    command
+        .args(&context.args[1..])
+        .env_clear()
+        .envs(context.state.env_vars())
+        .current_dir(context.state.cwd())
+        .stdin(context.stdin.clone().into_stdio())
+        .stdout(context.stdout.clone().into_stdio())
+        .stderr(context.stderr.clone().into_stdio());
+    let _ = std::io::stdout().flush();
+
+    #[cfg(unix)]
+    let err = {
+        use std::os::unix::process::CommandExt;
+        // like execvp, this only returns on failure
+        command.arg0(&context.args[0]).exec()
+    };
+    #[cfg(windows)]
+    let err = match command.status() {
+        // windows can't replace the process, so exit once the command is done
+        Ok(status) => std::process::exit(status.code().unwrap_or(1)),
+        Err(err) => err,
+    };
+
+    let _ = context
+        .stderr
+        .write_line(&format!("exec: {}: {}", context.args[0], err));
+    126
+}
@@ -0,0 +1,73 @@
+use deno_task_shell::{ExecuteResult, ShellCommand, ShellCommandContext};
+use futures::future::LocalBoxFuture;
+use miette::bail;
+use miette::Result;
+
+use crate::history::History;
+
+pub struct HistoryCommand {
+    history: History,
+}
+
+impl HistoryCommand {
+    pub fn new(history: History) -> Self {
+        HistoryCommand { history }
+    }
+}
+
+impl ShellCommand for HistoryCommand {
+    fn execute(&self, mut context: ShellCommandContext) -> LocalBoxFuture<'static, ExecuteResult> {
+        let result = match execute_history(&context.args, &self.history.entries()) {
+            Ok(output) => {
+                let _ = context.stdout.write_all(output.as_bytes());
+                ExecuteResult::from_exit_code(0)
+            }
+            Err(err) => {
+                ExecuteResult::from_exit_code(context.stderr.write_command_error("history", &err))
+            }
+        };
+        Box::pin(futures::future::ready(result))
+    }
+}
+
+fn execute_history(args: &[String], entries: &[String]) -> Result<String> {
+    let count = match args {
+        [] => entries.len(),
+        [count] => match count.parse::<usize>() {
+            Ok(count) => count.min(entries.len()),
+            Err(_) => bail!("{}: numeric argument required", count),
+        },
+        _ => bail!("too many arguments"),
+    };
+    let start = entries.len() - count;
+    Ok(entries[start..]
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| format!("{:>5}  {}\n", start + index + 1, entry))
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn lists_history() {
+        let entries = vec!["echo 1".to_string(), "ls".to_string()];
+        assert_eq!(
+            execute_history(&[], &entries).unwrap(),
+            "    1  echo 1\n    2  ls\n"
+        );
+        assert_eq!(
+            execute_history(&["1".to_string()], &entries).unwrap(),
+            "    2  ls\n"
+        );
+        assert_eq!(
+            execute_history(&["a".to_string()], &entries)
+                .err()
+                .unwrap()
+                .to_string(),
+            "a: numeric argument required"
+        );
+    }
+}
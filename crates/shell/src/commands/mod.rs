@@ -1,21 +1,34 @@
 use std::{collections::HashMap, ffi::OsString, fs, rc::Rc};
 
-use deno_task_shell::{EnvChange, ExecuteResult, ShellCommand, ShellCommandContext};
+use deno_task_shell::{
+    AsyncCommandBehavior, EnvChange, ExecuteResult, ShellCommand, ShellCommandContext,
+};
 use futures::{future::LocalBoxFuture, FutureExt};
 
+use terminal_size::Width;
 use uu_ls::uumain as uu_ls;
 
 use crate::execute;
 
+pub mod compgen;
 pub mod date;
+pub mod exec;
+pub mod history;
 pub mod set;
+pub mod shopt;
 pub mod touch;
+pub mod trap;
 pub mod uname;
 pub mod which;
 
+pub use compgen::CompgenCommand;
 pub use date::DateCommand;
+pub use exec::ExecCommand;
+pub use history::HistoryCommand;
 pub use set::SetCommand;
+pub use shopt::ShoptCommand;
 pub use touch::TouchCommand;
+pub use trap::TrapCommand;
 pub use uname::UnameCommand;
 pub use which::WhichCommand;
 
@@ -62,25 +75,65 @@ pub fn get_commands() -> HashMap<String, Rc<dyn ShellCommand>> {
             "set".to_string(),
             Rc::new(SetCommand) as Rc<dyn ShellCommand>,
         ),
+        (
+            "shopt".to_string(),
+            Rc::new(ShoptCommand) as Rc<dyn ShellCommand>,
+        ),
+        (
+            "compgen".to_string(),
+            Rc::new(CompgenCommand) as Rc<dyn ShellCommand>,
+        ),
+        (
+            "trap".to_string(),
+            Rc::new(TrapCommand) as Rc<dyn ShellCommand>,
+        ),
+        (
+            "exec".to_string(),
+            Rc::new(ExecCommand) as Rc<dyn ShellCommand>,
+        ),
     ])
 }
 
 impl ShellCommand for AliasCommand {
-    fn execute(&self, context: ShellCommandContext) -> LocalBoxFuture<'static, ExecuteResult> {
-        if context.args.len() != 1 {
-            return Box::pin(futures::future::ready(ExecuteResult::from_exit_code(1)));
+    fn execute(&self, mut context: ShellCommandContext) -> LocalBoxFuture<'static, ExecuteResult> {
+        let result = execute_alias(&mut context);
+        Box::pin(futures::future::ready(result))
+    }
+}
+
+fn execute_alias(context: &mut ShellCommandContext) -> ExecuteResult {
+    let aliases = context.state.alias_map();
+    if context.args.is_empty() {
+        let mut names = aliases.keys().collect::<Vec<_>>();
+        names.sort();
+        for name in names {
+            let _ = context
+                .stdout
+                .write_line(&format_alias(name, &aliases[name]));
         }
+        return ExecuteResult::from_exit_code(0);
+    }
 
-        // parse the args
-        let env_change = if let Some((alias, cmd)) = context.args[0].split_once('=') {
-            vec![EnvChange::AliasCommand(alias.into(), cmd.into())]
+    let mut exit_code = 0;
+    let mut changes = Vec::new();
+    for arg in &context.args {
+        if let Some((alias, cmd)) = arg.split_once('=') {
+            changes.push(EnvChange::AliasCommand(alias.into(), cmd.into()));
+        } else if let Some(value) = aliases.get(arg) {
+            let _ = context.stdout.write_line(&format_alias(arg, value));
         } else {
-            return Box::pin(futures::future::ready(ExecuteResult::from_exit_code(1)));
-        };
-
-        let result = ExecuteResult::Continue(0, env_change, Vec::default());
-        Box::pin(futures::future::ready(result))
+            let _ = context
+                .stderr
+                .write_line(&format!("alias: {arg}: not found"));
+            exit_code = 1;
+        }
     }
+    ExecuteResult::Continue(exit_code, changes, Vec::default())
+}
+
+/// Formats an alias so it can be used as input again (ex. `alias ll='ls -al'`).
+fn format_alias(name: &str, value: &str) -> String {
+    format!("alias {}='{}'", name, value.replace('\'', "'\\''"))
 }
 
 impl ShellCommand for UnAliasCommand {
@@ -106,43 +159,193 @@ impl ShellCommand for LsCommand {
 }
 
 fn execute_ls(context: ShellCommandContext) -> ExecuteResult {
-    let mut args: Vec<OsString> = vec![OsString::from("ls"), OsString::from("--color=auto")];
+    let color = ls_color_mode(
+        context.stdout.is_terminal(),
+        context.state.get_var("NO_COLOR").map(String::as_str),
+        context.state.get_var("CLICOLOR_FORCE").map(String::as_str),
+    );
+    let layout = ls_layout(
+        context.stdout.is_terminal(),
+        context.state.get_var("COLUMNS").map(String::as_str),
+        || terminal_size::terminal_size().map(|(Width(width), _)| width),
+    );
+    let exit_code = uu_ls(ls_args(color, layout, &context.args).into_iter());
+    ExecuteResult::from_exit_code(exit_code)
+}
+
+/// How `ls` lays out the entries.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LsLayout {
+    /// One entry per line, used when not writing to a terminal
+    OnePerLine,
+    /// Columns fitting in the width
+    Width(u16),
+    /// The layout `uu_ls` picks, used when the terminal size is unknown
+    Default,
+}
 
-    context
-        .args
-        .iter()
-        .for_each(|arg| args.push(OsString::from(arg)));
+fn ls_args(color: &str, layout: LsLayout, args: &[String]) -> Vec<OsString> {
+    let mut result = vec![
+        OsString::from("ls"),
+        OsString::from(format!("--color={color}")),
+    ];
+    // explicit layout flags passed by the user come later and win
+    match layout {
+        LsLayout::OnePerLine => result.push(OsString::from("-1")),
+        LsLayout::Width(width) => result.push(OsString::from(format!("--width={width}"))),
+        LsLayout::Default => {}
+    }
+    result.extend(args.iter().map(OsString::from));
+    result
+}
 
-    let exit_code = uu_ls(args.into_iter());
-    ExecuteResult::from_exit_code(exit_code)
+/// Picks the layout of `ls`, with the width preferably from `$COLUMNS`
+/// over querying the terminal.
+fn ls_layout(
+    is_terminal: bool,
+    columns: Option<&str>,
+    query_terminal: impl FnOnce() -> Option<u16>,
+) -> LsLayout {
+    if !is_terminal {
+        return LsLayout::OnePerLine;
+    }
+    columns
+        .and_then(|columns| columns.trim().parse::<u16>().ok())
+        .filter(|columns| *columns > 0)
+        .or_else(query_terminal)
+        .map_or(LsLayout::Default, LsLayout::Width)
+}
+
+/// Picks the `--color` mode of `ls`. `NO_COLOR` disables colors and
+/// `CLICOLOR_FORCE` enables them even when not writing to a terminal.
+fn ls_color_mode(is_terminal: bool, no_color: Option<&str>, force: Option<&str>) -> &'static str {
+    let is_set = |value: Option<&str>| matches!(value, Some(value) if !value.is_empty());
+    if is_set(no_color) {
+        "never"
+    } else if is_set(force) && force != Some("0") {
+        "always"
+    } else if is_terminal {
+        "auto"
+    } else {
+        "never"
+    }
 }
 
 impl ShellCommand for SourceCommand {
-    fn execute(&self, context: ShellCommandContext) -> LocalBoxFuture<'static, ExecuteResult> {
+    fn execute(&self, mut context: ShellCommandContext) -> LocalBoxFuture<'static, ExecuteResult> {
         if context.args.len() != 1 {
+            let _ = context.stderr.write_line("source: expected one argument");
             return Box::pin(futures::future::ready(ExecuteResult::from_exit_code(1)));
         }
 
         let script = context.args[0].clone();
-        let script_file = context.state.cwd().join(script);
+        let script_file = context.state.cwd().join(&script);
         match fs::read_to_string(&script_file) {
-            Ok(content) => {
-                let state = context.state.clone();
-                async move {
-                    execute::execute_inner(&content, state)
-                        .await
-                        .unwrap_or_else(|e| {
-                            eprintln!("Could not source script: {:?}", script_file);
-                            eprintln!("Error: {}", e);
-                            ExecuteResult::from_exit_code(1)
-                        })
-                }
-                .boxed_local()
+            Ok(content) => async move {
+                // `$BASH_SOURCE` is the path as given, like in bash
+                let mut state = context.state;
+                state.push_source_file(script);
+                state.enter_source();
+                // the changes of the script are returned so they apply to
+                // the sourcing shell, like variables, aliases and the cwd
+                let mut stderr = context.stderr.clone();
+                execute::execute_inner(
+                    &content,
+                    state,
+                    context.stdin,
+                    context.stdout,
+                    context.stderr,
+                    // the caller waits for the jobs started by the file
+                    AsyncCommandBehavior::Yield,
+                )
+                .await
+                .map(|result| match result {
+                    // an `exit` in the sourced file only ends the file, so
+                    // that an rc file can't end the session during startup,
+                    // this is left for the lists stopped by a cancellation
+                    ExecuteResult::Exit(exit_code, handles) => {
+                        ExecuteResult::Continue(exit_code, Vec::new(), handles)
+                    }
+                    result => result,
+                })
+                .unwrap_or_else(|e| {
+                    let _ = stderr.write_line(&format!(
+                        "Could not source script: {:?}\nError: {}",
+                        script_file, e
+                    ));
+                    ExecuteResult::from_exit_code(1)
+                })
             }
+            .boxed_local(),
             Err(e) => {
-                eprintln!("Could not read file: {:?} ({})", script_file, e);
+                let _ = context
+                    .stderr
+                    .write_line(&format!("Could not read file: {:?} ({})", script_file, e));
                 Box::pin(futures::future::ready(ExecuteResult::from_exit_code(1)))
             }
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ls_color_args() {
+        let args = ls_args(
+            ls_color_mode(true, Some("1"), None),
+            LsLayout::Width(80),
+            &["-a".to_string()],
+        );
+        assert_eq!(args, vec!["ls", "--color=never", "--width=80", "-a"]);
+
+        assert_eq!(ls_color_mode(true, None, None), "auto");
+        assert_eq!(ls_color_mode(false, None, None), "never");
+        assert_eq!(ls_color_mode(false, None, Some("1")), "always");
+        assert_eq!(ls_color_mode(false, None, Some("0")), "never");
+        assert_eq!(ls_color_mode(true, Some(""), None), "auto");
+        assert_eq!(ls_color_mode(true, Some("1"), Some("1")), "never");
+    }
+
+    #[test]
+    fn ls_has_no_colors_when_piped() {
+        let (_reader, writer) = deno_task_shell::pipe();
+        assert!(!writer.is_terminal());
+        let args = ls_args(
+            ls_color_mode(writer.is_terminal(), None, None),
+            LsLayout::OnePerLine,
+            &[],
+        );
+        assert_eq!(args, vec!["ls", "--color=never", "-1"]);
+    }
+
+    #[test]
+    fn ls_width_from_columns_or_terminal() {
+        assert_eq!(
+            ls_layout(true, Some("120"), || Some(80)),
+            LsLayout::Width(120)
+        );
+        assert_eq!(ls_layout(true, None, || Some(80)), LsLayout::Width(80));
+        assert_eq!(
+            ls_layout(true, Some("abc"), || Some(80)),
+            LsLayout::Width(80)
+        );
+        // uu_ls picks the layout when the size is unknown
+        assert_eq!(ls_layout(true, Some("0"), || None), LsLayout::Default);
+        assert_eq!(ls_layout(true, None, || None), LsLayout::Default);
+        assert_eq!(
+            ls_args("auto", LsLayout::Default, &[]),
+            vec!["ls", "--color=auto"]
+        );
+    }
+
+    #[test]
+    fn ls_uses_one_entry_per_line_when_piped() {
+        let (_reader, writer) = deno_task_shell::pipe();
+        let layout = ls_layout(writer.is_terminal(), Some("120"), || Some(80));
+        assert_eq!(layout, LsLayout::OnePerLine);
+        let args = ls_args("never", layout, &[]);
+        assert_eq!(args, vec!["ls", "--color=never", "-1"]);
+    }
+}
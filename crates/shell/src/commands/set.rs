@@ -16,7 +16,7 @@ impl ShellCommand for SetCommand {
         let result = match execute_set(context.args) {
             Ok((code, env_changes)) => ExecuteResult::Continue(code, env_changes, Vec::new()),
             Err(err) => {
-                context.stderr.write_line(&format!("set: {err}")).unwrap();
+                let _ = context.stderr.write_line(&format!("set: {err}"));
                 ExecuteResult::Exit(2, Vec::new())
             }
         };
@@ -27,7 +27,8 @@ impl ShellCommand for SetCommand {
 fn execute_set(args: Vec<String>) -> Result<(i32, Vec<EnvChange>)> {
     let args = parse_arg_kinds(&args);
     let mut env_changes = Vec::new();
-    for arg in args {
+    let mut iterator = args.into_iter();
+    while let Some(arg) = iterator.next() {
         match arg {
             ArgKind::ShortFlag('e') => {
                 env_changes.push(EnvChange::SetShellOptions(ShellOptions::ExitOnError, true));
@@ -41,6 +42,37 @@ fn execute_set(args: Vec<String>) -> Result<(i32, Vec<EnvChange>)> {
             ArgKind::PlusFlag('x') => {
                 env_changes.push(EnvChange::SetShellOptions(ShellOptions::PrintTrace, false));
             }
+            ArgKind::ShortFlag('C') => {
+                env_changes.push(EnvChange::SetShellOptions(ShellOptions::NoClobber, true));
+            }
+            ArgKind::PlusFlag('C') => {
+                env_changes.push(EnvChange::SetShellOptions(ShellOptions::NoClobber, false));
+            }
+            ArgKind::ShortFlag('n') => {
+                env_changes.push(EnvChange::SetShellOptions(ShellOptions::NoExec, true));
+            }
+            ArgKind::PlusFlag('n') => {
+                env_changes.push(EnvChange::SetShellOptions(ShellOptions::NoExec, false));
+            }
+            ArgKind::ShortFlag('v') => {
+                env_changes.push(EnvChange::SetShellOptions(ShellOptions::Verbose, true));
+            }
+            ArgKind::PlusFlag('v') => {
+                env_changes.push(EnvChange::SetShellOptions(ShellOptions::Verbose, false));
+            }
+            ArgKind::ShortFlag('o') | ArgKind::PlusFlag('o') => {
+                let value = matches!(arg, ArgKind::ShortFlag(_));
+                let option = match iterator.next() {
+                    Some(ArgKind::Arg("errexit")) => ShellOptions::ExitOnError,
+                    Some(ArgKind::Arg("xtrace")) => ShellOptions::PrintTrace,
+                    Some(ArgKind::Arg("noclobber")) => ShellOptions::NoClobber,
+                    Some(ArgKind::Arg("noexec")) => ShellOptions::NoExec,
+                    Some(ArgKind::Arg("verbose")) => ShellOptions::Verbose,
+                    Some(ArgKind::Arg(name)) => bail!("{}: invalid option name", name),
+                    _ => bail!("option requires an argument -- 'o'"),
+                };
+                env_changes.push(EnvChange::SetShellOptions(option, value));
+            }
             _ => bail!(format!("Unsupported argument: {:?}", arg)),
         }
     }
@@ -81,5 +113,38 @@ async fn test_exit_on_error() {
         )
     );
 
+    assert_eq!(
+        execute_set(vec!["-o".to_string(), "noclobber".to_string()]).unwrap(),
+        (
+            0,
+            vec![EnvChange::SetShellOptions(ShellOptions::NoClobber, true)]
+        )
+    );
+
+    assert_eq!(
+        execute_set(vec!["+C".to_string()]).unwrap(),
+        (
+            0,
+            vec![EnvChange::SetShellOptions(ShellOptions::NoClobber, false)]
+        )
+    );
+
+    assert_eq!(
+        execute_set(vec!["-o".to_string(), "noexec".to_string()]).unwrap(),
+        (
+            0,
+            vec![EnvChange::SetShellOptions(ShellOptions::NoExec, true)]
+        )
+    );
+
+    assert_eq!(
+        execute_set(vec!["-v".to_string()]).unwrap(),
+        (
+            0,
+            vec![EnvChange::SetShellOptions(ShellOptions::Verbose, true)]
+        )
+    );
+
     assert!(execute_set(vec!["-t".to_string()]).is_err());
+    assert!(execute_set(vec!["-o".to_string(), "unknown".to_string()]).is_err());
 }
@@ -0,0 +1,139 @@
+// Copyright 2018-2024 the Shell authors. MIT license.
+
+use futures::future::LocalBoxFuture;
+use miette::bail;
+use miette::Result;
+
+use deno_task_shell::{
+    parse_arg_kinds, ArgKind, EnvChange, ExecuteResult, ShellCommand, ShellCommandContext,
+    ShellOptions, ShellState,
+};
+
+/// The options that can be changed with `shopt` along with how to read
+/// them, in the order they are listed.
+type ShoptOption = (&'static str, ShellOptions, fn(&ShellState) -> bool);
+
+const OPTIONS: [ShoptOption; 5] = [
+    ("dotglob", ShellOptions::DotGlob, ShellState::dot_glob),
+    ("extglob", ShellOptions::ExtGlob, ShellState::ext_glob),
+    ("globstar", ShellOptions::GlobStar, ShellState::glob_star),
+    (
+        "nocaseglob",
+        ShellOptions::NoCaseGlob,
+        ShellState::no_case_glob,
+    ),
+    ("nullglob", ShellOptions::NullGlob, ShellState::null_glob),
+];
+
+pub struct ShoptCommand;
+
+impl ShellCommand for ShoptCommand {
+    fn execute(&self, mut context: ShellCommandContext) -> LocalBoxFuture<'static, ExecuteResult> {
+        let result = match execute_shopt(&context.args, &context.state) {
+            Ok((output, env_changes)) => {
+                if !output.is_empty() {
+                    let _ = context.stdout.write_all(output.as_bytes());
+                }
+                ExecuteResult::Continue(0, env_changes, Vec::new())
+            }
+            Err(err) => {
+                ExecuteResult::from_exit_code(context.stderr.write_command_error("shopt", &err))
+            }
+        };
+        Box::pin(futures::future::ready(result))
+    }
+}
+
+fn execute_shopt(args: &[String], state: &ShellState) -> Result<(String, Vec<EnvChange>)> {
+    let mut value = None;
+    let mut names = Vec::new();
+    for arg in parse_arg_kinds(args) {
+        match arg {
+            ArgKind::ShortFlag('s') => value = Some(true),
+            ArgKind::ShortFlag('u') => value = Some(false),
+            ArgKind::Arg(name) => names.push(name),
+            _ => bail!("Unsupported argument: {:?}", arg),
+        }
+    }
+
+    let options = if names.is_empty() {
+        OPTIONS.to_vec()
+    } else {
+        names
+            .into_iter()
+            .map(
+                |name| match OPTIONS.iter().find(|(option, _, _)| *option == name) {
+                    Some(option) => Ok(*option),
+                    None => bail!("{}: invalid shell option name", name),
+                },
+            )
+            .collect::<Result<Vec<_>>>()?
+    };
+
+    match value {
+        Some(value) => {
+            let env_changes = options
+                .into_iter()
+                .map(|(_, option, _)| EnvChange::SetShellOptions(option, value))
+                .collect();
+            Ok((String::new(), env_changes))
+        }
+        None => {
+            let output = options
+                .into_iter()
+                .map(|(name, _, is_enabled)| {
+                    format!(
+                        "{}\t{}\n",
+                        name,
+                        if is_enabled(state) { "on" } else { "off" }
+                    )
+                })
+                .collect();
+            Ok((output, Vec::new()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn sets_options() {
+        let state = ShellState::new(HashMap::new(), &std::env::temp_dir(), HashMap::new());
+        assert_eq!(
+            execute_shopt(&args(&["-s", "dotglob"]), &state).unwrap(),
+            (
+                String::new(),
+                vec![EnvChange::SetShellOptions(ShellOptions::DotGlob, true)]
+            )
+        );
+        assert_eq!(
+            execute_shopt(&args(&["-u", "dotglob", "globstar"]), &state).unwrap(),
+            (
+                String::new(),
+                vec![
+                    EnvChange::SetShellOptions(ShellOptions::DotGlob, false),
+                    EnvChange::SetShellOptions(ShellOptions::GlobStar, false),
+                ]
+            )
+        );
+        assert_eq!(
+            execute_shopt(&args(&[]), &state).unwrap().0,
+            "dotglob\toff\nextglob\toff\nglobstar\ton\nnocaseglob\ton\nnullglob\toff\n"
+        );
+        assert_eq!(
+            execute_shopt(&args(&["-s", "failglob"]), &state)
+                .err()
+                .unwrap()
+                .to_string(),
+            "failglob: invalid shell option name"
+        );
+    }
+}
@@ -21,8 +21,7 @@ impl ShellCommand for TouchCommand {
         Box::pin(futures::future::ready(match execute_touch(&mut context) {
             Ok(_) => ExecuteResult::from_exit_code(0),
             Err(e) => {
-                let _ = context.stderr.write_all(format!("{:?}", e).as_bytes());
-                ExecuteResult::from_exit_code(1)
+                ExecuteResult::from_exit_code(context.stderr.write_command_error("touch", &e))
             }
         }))
     }
@@ -119,13 +118,10 @@ fn execute_touch(context: &mut ShellCommandContext) -> Result<()> {
             }
 
             if matches.get_flag(options::NO_DEREF) {
-                let _ = context.stderr.write_all(
-                    format!(
-                        "setting times of {:?}: No such file or directory",
-                        path.display()
-                    )
-                    .as_bytes(),
-                );
+                let _ = context.stderr.write_line(&format!(
+                    "touch: setting times of {:?}: No such file or directory",
+                    path.display()
+                ));
                 continue;
             }
 
@@ -0,0 +1,129 @@
+// Copyright 2018-2024 the Shell authors. MIT license.
+
+use std::collections::HashMap;
+
+use futures::future::LocalBoxFuture;
+use miette::bail;
+use miette::Result;
+
+use deno_task_shell::{EnvChange, ExecuteResult, ShellCommand, ShellCommandContext};
+
+/// The trap conditions that are currently supported.
+const SUPPORTED_CONDITIONS: &[&str] = &["ERR"];
+
+pub struct TrapCommand;
+
+impl ShellCommand for TrapCommand {
+    fn execute(&self, mut context: ShellCommandContext) -> LocalBoxFuture<'static, ExecuteResult> {
+        let result = match execute_trap(context.args, context.state.traps()) {
+            Ok((output, env_changes)) => {
+                if !output.is_empty() {
+                    let _ = context.stdout.write_all(output.as_bytes());
+                }
+                ExecuteResult::Continue(0, env_changes, Vec::new())
+            }
+            Err(err) => {
+                ExecuteResult::from_exit_code(context.stderr.write_command_error("trap", &err))
+            }
+        };
+        Box::pin(futures::future::ready(result))
+    }
+}
+
+fn execute_trap(
+    args: Vec<String>,
+    traps: &HashMap<String, String>,
+) -> Result<(String, Vec<EnvChange>)> {
+    let mut args = args.into_iter().peekable();
+    if matches!(args.peek().map(String::as_str), None | Some("-p")) {
+        let mut conditions = traps.keys().collect::<Vec<_>>();
+        conditions.sort();
+        let output = conditions
+            .into_iter()
+            .map(|condition| {
+                format!(
+                    "trap -- '{}' {}\n",
+                    traps[condition].replace('\'', "'\\''"),
+                    condition
+                )
+            })
+            .collect();
+        return Ok((output, Vec::new()));
+    }
+
+    let first = args.next().unwrap();
+    let (command, conditions) = if args.peek().is_none() {
+        // a single condition without a command resets it
+        (None, vec![first])
+    } else if first == "-" {
+        (None, args.collect())
+    } else {
+        (Some(first), args.collect())
+    };
+
+    let mut env_changes = Vec::new();
+    for condition in conditions {
+        if !SUPPORTED_CONDITIONS.contains(&condition.as_str()) {
+            bail!("unsupported condition: {}", condition);
+        }
+        env_changes.push(match &command {
+            Some(command) => EnvChange::SetTrap(condition, command.clone()),
+            None => EnvChange::RemoveTrap(condition),
+        });
+    }
+    Ok((String::new(), env_changes))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_args() {
+        let traps = HashMap::new();
+        assert_eq!(
+            execute_trap(args(&["echo failed", "ERR"]), &traps).unwrap(),
+            (
+                String::new(),
+                vec![EnvChange::SetTrap(
+                    "ERR".to_string(),
+                    "echo failed".to_string()
+                )]
+            )
+        );
+        assert_eq!(
+            execute_trap(args(&["-", "ERR"]), &traps).unwrap(),
+            (
+                String::new(),
+                vec![EnvChange::RemoveTrap("ERR".to_string())]
+            )
+        );
+        assert_eq!(
+            execute_trap(args(&["ERR"]), &traps).unwrap(),
+            (
+                String::new(),
+                vec![EnvChange::RemoveTrap("ERR".to_string())]
+            )
+        );
+        assert_eq!(
+            execute_trap(args(&["echo", "INT"]), &traps)
+                .err()
+                .unwrap()
+                .to_string(),
+            "unsupported condition: INT"
+        );
+    }
+
+    #[test]
+    fn lists_traps() {
+        let traps = HashMap::from([("ERR".to_string(), "echo 'failed'".to_string())]);
+        assert_eq!(
+            execute_trap(Vec::new(), &traps).unwrap().0,
+            "trap -- 'echo '\\''failed'\\''' ERR\n"
+        );
+    }
+}
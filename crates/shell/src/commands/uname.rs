@@ -1,5 +1,6 @@
 use deno_task_shell::{ExecuteResult, ShellCommand, ShellCommandContext};
 use futures::future::LocalBoxFuture;
+use miette::IntoDiagnostic;
 use uu_uname::{options, UNameOutput};
 pub struct UnameCommand;
 
@@ -29,21 +30,20 @@ impl ShellCommand for UnameCommand {
         Box::pin(async move {
             match execute_uname(&mut context) {
                 Ok(_) => ExecuteResult::from_exit_code(0),
-                Err(e) => {
-                    context.stderr.write_line(&e).ok();
-                    ExecuteResult::from_exit_code(1)
+                Err(err) => {
+                    ExecuteResult::from_exit_code(context.stderr.write_command_error("uname", &err))
                 }
             }
         })
     }
 }
 
-fn execute_uname(context: &mut ShellCommandContext) -> Result<(), String> {
+fn execute_uname(context: &mut ShellCommandContext) -> miette::Result<()> {
     let matches = uu_uname::uu_app()
         .override_usage("uname [OPTION]...")
         .no_binary_name(true)
         .try_get_matches_from(&context.args)
-        .map_err(|e| e.to_string())?;
+        .into_diagnostic()?;
 
     let options = uu_uname::Options {
         all: matches.get_flag(options::ALL),
@@ -58,10 +58,7 @@ fn execute_uname(context: &mut ShellCommandContext) -> Result<(), String> {
     };
 
     let uname = UNameOutput::new(&options).unwrap();
-    context
-        .stdout
-        .write_line(display(&uname).trim_end())
-        .map_err(|e| e.to_string())?;
+    context.stdout.write_line(display(&uname).trim_end())?;
 
     Ok(())
 }
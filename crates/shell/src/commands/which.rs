@@ -14,7 +14,10 @@ impl ShellCommand for WhichCommand {
 
 fn execute_which(context: &mut ShellCommandContext) -> Result<(), i32> {
     if context.args.len() != 1 {
-        context.stderr.write_line("Expected one argument").ok();
+        context
+            .stderr
+            .write_line("which: expected one argument")
+            .ok();
         return Err(1);
     }
 
@@ -23,7 +26,7 @@ fn execute_which(context: &mut ShellCommandContext) -> Result<(), i32> {
     if let Some(alias) = context.state.alias_map().get(arg) {
         context
             .stdout
-            .write_line(&format!("alias: \"{}\"", alias.join(" ")))
+            .write_line(&format!("alias: \"{}\"", alias))
             .ok();
         return Ok(());
     }
@@ -46,7 +49,7 @@ fn execute_which(context: &mut ShellCommandContext) -> Result<(), i32> {
 
     context
         .stderr
-        .write_line(&format!("{} not found", arg))
+        .write_line(&format!("which: {} not found", arg))
         .ok();
 
     Err(1)
@@ -5,15 +5,74 @@ use rustyline::hint::Hinter;
 use rustyline::validate::Validator;
 use rustyline::{Context, Helper};
 use std::borrow::Cow::{self, Owned};
+use std::collections::HashMap;
 use std::env;
 use std::fs;
 use std::path::Path;
+use std::path::PathBuf;
 
-pub struct ShellCompleter;
+/// Option flags offered when completing a word starting with `-`, keyed by
+/// the command at the start of the line. Only the flags the builtins accept
+/// are listed.
+const COMMAND_FLAGS: &[(&str, &[&str])] = &[
+    ("cd", &["-L", "-P"]),
+    ("cp", &["-r", "--recursive"]),
+    ("head", &["-n", "-c", "--lines=", "--bytes="]),
+    (
+        "ls",
+        &["-a", "-l", "-h", "-R", "-t", "-r", "--all", "--color"],
+    ),
+    ("mkdir", &["-p", "--parents"]),
+    ("rm", &["-r", "-f", "-d", "--recursive", "--force", "--dir"]),
+    (
+        "set",
+        &[
+            "-e", "+e", "-x", "+x", "-C", "+C", "-n", "+n", "-v", "+v", "-o", "+o",
+        ],
+    ),
+    ("uname", &["-a", "-s", "-n", "-r", "-v", "-m", "--all"]),
+];
 
-impl Default for ShellCompleter {
-    fn default() -> Self {
-        ShellCompleter
+/// Completes the arguments of a command, registered with
+/// [`ShellCompleter::register_completer`].
+pub type CommandCompleter = Box<dyn Fn(&CompletionContext) -> Vec<String>>;
+
+/// The command line a [`CommandCompleter`] is called with.
+pub struct CompletionContext<'a> {
+    /// The words up to the cursor, starting with the command name. The
+    /// last word is the one being completed, which is empty after a space.
+    pub words: Vec<&'a str>,
+    /// The index of the word being completed in `words`
+    pub word_index: usize,
+    /// The position of the cursor in the line
+    pub pos: usize,
+    pub line: &'a str,
+}
+
+#[derive(Default)]
+pub struct ShellCompleter {
+    /// Names of the environment and shell variables for `$VAR` completion
+    var_names: Vec<String>,
+    /// Completers of the arguments, keyed by command name
+    completers: HashMap<String, CommandCompleter>,
+}
+
+impl ShellCompleter {
+    pub fn set_var_names(&mut self, var_names: Vec<String>) {
+        self.var_names = var_names;
+    }
+
+    /// Registers a function completing the arguments of a command (ex. the
+    /// subcommands of `git`), replacing any previous one for the command.
+    ///
+    /// The candidates not starting with the word being completed are
+    /// ignored. When none are left, the arguments are completed as usual.
+    pub fn register_completer(
+        &mut self,
+        command: impl Into<String>,
+        completer: impl Fn(&CompletionContext) -> Vec<String> + 'static,
+    ) {
+        self.completers.insert(command.into(), Box::new(completer));
     }
 }
 
@@ -29,9 +88,40 @@ impl Completer for ShellCompleter {
         let mut matches = Vec::new();
         let (start, word) = extract_word(line, pos);
 
+        // Complete variable names
+        if let Some(var_start) = complete_variables(word, &self.var_names, &mut matches) {
+            return Ok((start + var_start, matches));
+        }
+
         let is_start = start == 0;
+        // `cd` and `pushd` only take directories
+        let dirs_only = !is_start && matches!(line.split_whitespace().next(), Some("cd" | "pushd"));
+        // Complete with the completer registered for the command
+        if !is_start && self.complete_registered(line, pos, word, &mut matches) {
+            return Ok((start, matches));
+        }
+
+        // Complete option flags of known commands
+        if !is_start && complete_flags(line, word, &mut matches) {
+            return Ok((start, matches));
+        }
+
+        // Complete filenames within an unclosed quote, keeping the quoting
+        if let Some(quote) = word.chars().next().filter(|c| *c == '"' || *c == '\'') {
+            complete_filenames(is_start, dirs_only, &word[1..], &mut matches);
+            for pair in &mut matches {
+                // close the quote unless descending into a directory
+                pair.replacement = if pair.replacement.ends_with('/') {
+                    format!("{quote}{}", pair.replacement)
+                } else {
+                    format!("{quote}{}{quote}", pair.replacement)
+                };
+            }
+            return Ok((start, matches));
+        }
+
         // Complete filenames
-        complete_filenames(is_start, word, &mut matches);
+        complete_filenames(is_start, dirs_only, word, &mut matches);
 
         // Complete shell commands
         complete_shell_commands(is_start, word, &mut matches);
@@ -43,16 +133,130 @@ impl Completer for ShellCompleter {
     }
 }
 
+impl ShellCompleter {
+    /// Returns `false` if there's no registered completer for the command
+    /// or none of its candidates match.
+    fn complete_registered(
+        &self,
+        line: &str,
+        pos: usize,
+        word: &str,
+        matches: &mut Vec<Pair>,
+    ) -> bool {
+        let mut words = line[..pos].split_whitespace().collect::<Vec<_>>();
+        let Some(completer) = words.first().and_then(|name| self.completers.get(*name)) else {
+            return false;
+        };
+        if word.is_empty() {
+            words.push("");
+        }
+        let context = CompletionContext {
+            word_index: words.len() - 1,
+            words,
+            pos,
+            line,
+        };
+        for candidate in completer(&context) {
+            if candidate.starts_with(word) {
+                matches.push(Pair {
+                    display: candidate.clone(),
+                    replacement: candidate,
+                });
+            }
+        }
+        !matches.is_empty()
+    }
+}
+
+/// Finds the word before the cursor. The spaces within quotes are part of
+/// the word, including the ones after a quote that isn't closed yet (ex.
+/// `"some dir/f`).
 fn extract_word(line: &str, pos: usize) -> (usize, &str) {
-    if line.ends_with(' ') {
-        return (pos, "");
+    let mut word_start = 0;
+    let mut quote = None;
+    for (i, c) in line[..pos].char_indices() {
+        match quote {
+            Some(open) if c == open => quote = None,
+            Some(_) => {}
+            None if c.is_whitespace() => word_start = i + c.len_utf8(),
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None => {}
+        }
     }
-    let words: Vec<_> = line[..pos].split_whitespace().collect();
-    let word_start = words.last().map_or(0, |w| line.rfind(w).unwrap());
     (word_start, &line[word_start..pos])
 }
 
-fn complete_filenames(_is_start: bool, word: &str, matches: &mut Vec<Pair>) {
+/// Completes the variable name after the last `$` (or `${`) in the word.
+/// Returns the offset in the word where the replacement starts, or `None`
+/// if the word doesn't end with a variable name.
+fn complete_variables(word: &str, var_names: &[String], matches: &mut Vec<Pair>) -> Option<usize> {
+    let dollar = word.rfind('$')?;
+    let (braced, partial_name) = match word[dollar + 1..].strip_prefix('{') {
+        Some(name) => (true, name),
+        None => (false, &word[dollar + 1..]),
+    };
+    if !partial_name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        return None;
+    }
+
+    let mut names = var_names
+        .iter()
+        .filter(|name| name.starts_with(partial_name))
+        .collect::<Vec<_>>();
+    names.sort();
+    names.dedup();
+    for name in names {
+        let replacement = if braced {
+            format!("${{{}}}", name)
+        } else {
+            format!("${}", name)
+        };
+        matches.push(Pair {
+            display: name.clone(),
+            replacement,
+        });
+    }
+    Some(dollar)
+}
+
+/// Completes the flags of the command at the start of the line from
+/// `COMMAND_FLAGS`. Returns `false` if the word is not a flag.
+fn complete_flags(line: &str, word: &str, matches: &mut Vec<Pair>) -> bool {
+    if !word.starts_with(['-', '+']) {
+        return false;
+    }
+    let Some(command) = line.split_whitespace().next() else {
+        return false;
+    };
+    let Some((_, flags)) = COMMAND_FLAGS.iter().find(|(name, _)| *name == command) else {
+        return false;
+    };
+    for flag in flags.iter().filter(|flag| flag.starts_with(word)) {
+        matches.push(Pair {
+            display: flag.to_string(),
+            replacement: flag.to_string(),
+        });
+    }
+    true
+}
+
+fn complete_filenames(_is_start: bool, dirs_only: bool, word: &str, matches: &mut Vec<Pair>) {
+    for (path, is_dir) in list_files(Path::new("."), word, dirs_only) {
+        let path = if is_dir { path + "/" } else { path };
+        matches.push(Pair {
+            display: path.clone(),
+            replacement: path,
+        });
+    }
+}
+
+/// Lists the files starting with the word, relative to `cwd` unless the word
+/// is absolute or starts with `~`. Returns the paths as written after the
+/// word's directory, along with whether each one is a directory.
+pub fn list_files(cwd: &Path, word: &str, dirs_only: bool) -> Vec<(String, bool)> {
     // Split the word into directory path and partial filename
     let (dir_path, partial_name) = match word.rfind('/') {
         Some(last_slash) => (&word[..=last_slash], &word[last_slash + 1..]),
@@ -61,38 +265,30 @@ fn complete_filenames(_is_start: bool, word: &str, matches: &mut Vec<Pair>) {
 
     // Determine the full directory path to search
     let search_dir = if dir_path.starts_with('/') {
-        dir_path.to_string()
+        PathBuf::from(dir_path)
     } else if let Some(stripped) = dir_path.strip_prefix('~') {
         let home_dir = dirs::home_dir().unwrap();
-        format!("{}{}", home_dir.display(), stripped)
+        PathBuf::from(format!("{}{}", home_dir.display(), stripped))
     } else {
-        format!("./{}", dir_path)
+        cwd.join(dir_path)
     };
 
-    if let Ok(entries) = fs::read_dir(Path::new(&search_dir)) {
+    let mut files = Vec::new();
+    if let Ok(entries) = fs::read_dir(search_dir) {
         for entry in entries.flatten() {
             if let Ok(name) = entry.file_name().into_string() {
                 if name.starts_with(partial_name) {
                     let full_path = format!("{}{}", dir_path, name);
                     match entry.file_type() {
-                        Ok(file_type) if file_type.is_dir() => {
-                            matches.push(Pair {
-                                display: full_path.clone() + "/",
-                                replacement: full_path + "/",
-                            });
-                        }
-                        Ok(_) => {
-                            matches.push(Pair {
-                                display: full_path.clone(),
-                                replacement: full_path,
-                            });
-                        }
-                        Err(_) => {}
+                        Ok(file_type) if file_type.is_dir() => files.push((full_path, true)),
+                        Ok(_) if !dirs_only => files.push((full_path, false)),
+                        _ => {}
                     }
                 }
             }
         }
     }
+    files
 }
 
 fn complete_shell_commands(is_start: bool, word: &str, matches: &mut Vec<Pair>) {
@@ -115,21 +311,31 @@ fn complete_executables_in_path(is_start: bool, word: &str, matches: &mut Vec<Pa
         return;
     }
     if let Ok(paths) = env::var("PATH") {
-        for path in env::split_paths(&paths) {
-            if let Ok(entries) = fs::read_dir(path) {
-                for entry in entries.flatten() {
-                    if let Ok(name) = entry.file_name().into_string() {
-                        if name.starts_with(word) && entry.path().is_file() {
-                            matches.push(Pair {
-                                display: name.clone(),
-                                replacement: name,
-                            });
-                        }
+        for name in list_executables(&paths, word) {
+            matches.push(Pair {
+                display: name.clone(),
+                replacement: name,
+            });
+        }
+    }
+}
+
+/// Lists the names of the files starting with the word in the directories
+/// of the `PATH` value.
+pub fn list_executables(paths: &str, word: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    for path in env::split_paths(paths) {
+        if let Ok(entries) = fs::read_dir(path) {
+            for entry in entries.flatten() {
+                if let Ok(name) = entry.file_name().into_string() {
+                    if name.starts_with(word) && entry.path().is_file() {
+                        names.push(name);
                     }
                 }
             }
         }
     }
+    names
 }
 
 impl Hinter for ShellCompleter {
@@ -145,3 +351,198 @@ impl Highlighter for ShellCompleter {
 impl Validator for ShellCompleter {}
 
 impl Helper for ShellCompleter {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rustyline::history::DefaultHistory;
+
+    fn complete(completer: &ShellCompleter, line: &str) -> (usize, Vec<String>) {
+        let history = DefaultHistory::new();
+        let ctx = Context::new(&history);
+        let (start, matches) = completer.complete(line, line.len(), &ctx).unwrap();
+        (start, matches.into_iter().map(|m| m.replacement).collect())
+    }
+
+    #[test]
+    fn completes_variables() {
+        let mut completer = ShellCompleter::default();
+        completer.set_var_names(vec![
+            "PATH".to_string(),
+            "PATHEXT".to_string(),
+            "HOME".to_string(),
+        ]);
+
+        assert_eq!(
+            complete(&completer, "echo $PA"),
+            (5, vec!["$PATH".to_string(), "$PATHEXT".to_string()])
+        );
+        assert_eq!(
+            complete(&completer, "echo ${HO"),
+            (5, vec!["${HOME}".to_string()])
+        );
+        assert_eq!(
+            complete(&completer, "echo foo:$HO"),
+            (9, vec!["$HOME".to_string()])
+        );
+        assert_eq!(complete(&completer, "echo $X"), (5, Vec::new()));
+    }
+
+    #[test]
+    fn completes_with_registered_completer() {
+        let mut completer = ShellCompleter::default();
+        completer.register_completer("git", |context| {
+            if context.word_index == 1 {
+                vec!["checkout".to_string(), "commit".to_string()]
+            } else {
+                Vec::new()
+            }
+        });
+
+        assert_eq!(
+            complete(&completer, "git "),
+            (4, vec!["checkout".to_string(), "commit".to_string()])
+        );
+        assert_eq!(
+            complete(&completer, "git ch"),
+            (4, vec!["checkout".to_string()])
+        );
+        // without candidates the arguments are completed as usual
+        assert_eq!(
+            complete(&completer, "git commit -"),
+            complete(&ShellCompleter::default(), "git commit -")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn completes_within_quotes() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path().canonicalize().unwrap();
+        let dir = dir.to_str().unwrap();
+        fs::create_dir_all(format!("{dir}/some dir/folder")).unwrap();
+        fs::write(format!("{dir}/some dir/file.txt"), "").unwrap();
+        let completer = ShellCompleter::default();
+
+        let line = format!("cat \"{dir}/some dir/f");
+        let (start, mut matches) = complete(&completer, &line);
+        matches.sort();
+        assert_eq!(start, 4);
+        assert_eq!(
+            matches,
+            vec![
+                format!("\"{dir}/some dir/file.txt\""),
+                format!("\"{dir}/some dir/folder/"),
+            ]
+        );
+
+        let line = format!("cat '{dir}/some d");
+        assert_eq!(
+            complete(&completer, &line),
+            (4, vec![format!("'{dir}/some dir/")])
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn completes_only_directories_for_cd() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path().canonicalize().unwrap();
+        let dir = dir.to_str().unwrap();
+        fs::create_dir(format!("{dir}/sub")).unwrap();
+        fs::create_dir(format!("{dir}/src")).unwrap();
+        fs::write(format!("{dir}/file.txt"), "").unwrap();
+        let completer = ShellCompleter::default();
+
+        for command in ["cd", "pushd"] {
+            let line = format!("{command} {dir}/");
+            let (start, mut matches) = complete(&completer, &line);
+            matches.sort();
+            assert_eq!(start, command.len() + 1);
+            assert_eq!(matches, vec![format!("{dir}/src/"), format!("{dir}/sub/")]);
+        }
+
+        let (_, matches) = complete(&completer, &format!("cat {dir}/f"));
+        assert_eq!(matches, vec![format!("{dir}/file.txt")]);
+    }
+
+    #[test]
+    fn extracts_words() {
+        assert_eq!(extract_word("cat foo", 7), (4, "foo"));
+        assert_eq!(extract_word("cat foo ", 8), (8, ""));
+        assert_eq!(extract_word("cat \"a b", 8), (4, "\"a b"));
+        assert_eq!(extract_word("cat \"a b\" c", 11), (10, "c"));
+        assert_eq!(extract_word("echo a a", 8), (7, "a"));
+    }
+
+    #[test]
+    fn completes_flags() {
+        let completer = ShellCompleter::default();
+        assert_eq!(
+            complete(&completer, "ls --co"),
+            (3, vec!["--color".to_string()])
+        );
+        assert_eq!(
+            complete(&completer, "mkdir -"),
+            (6, vec!["-p".to_string(), "--parents".to_string()])
+        );
+        assert_eq!(
+            complete(&completer, "head -n 1 --"),
+            (
+                10,
+                vec!["--lines=", "--bytes="]
+                    .into_iter()
+                    .map(String::from)
+                    .collect()
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn builtins_accept_command_flags() {
+        use deno_task_shell::{AsyncCommandBehavior, ShellPipeReader, ShellPipeWriter, ShellState};
+
+        for (command, flags) in COMMAND_FLAGS {
+            for flag in *flags {
+                let dir = tempfile::tempdir().unwrap();
+                fs::write(dir.path().join("file"), "text\n").unwrap();
+                // ls lists the directory in the process' cwd, so it's given
+                // the full path
+                let dir_path = dir.path().display().to_string();
+                let operands = match *command {
+                    "cd" => ".",
+                    "ls" => &dir_path,
+                    "cp" => "file copy",
+                    "head" => "1 file",
+                    "mkdir" => "dir",
+                    "rm" => "file",
+                    "set" if flag.ends_with('o') => "errexit",
+                    _ => "",
+                };
+                // the value of a long flag ending with `=` is attached to it
+                let separator = if flag.ends_with('=') { "" } else { " " };
+                let text = format!("{command} {flag}{separator}{operands}");
+                let state =
+                    ShellState::new(HashMap::new(), dir.path(), crate::commands::get_commands());
+                let (stderr_reader, stderr) = deno_task_shell::pipe();
+                let stderr_handle = stderr_reader.pipe_to_string_handle();
+                let (exit_code, _) = crate::execute::execute_inner(
+                    &text,
+                    state,
+                    ShellPipeReader::stdin(),
+                    ShellPipeWriter::null(),
+                    stderr,
+                    AsyncCommandBehavior::Wait,
+                )
+                .await
+                .unwrap()
+                .into_exit_code_and_handles();
+                assert_eq!(
+                    (exit_code, stderr_handle.await.unwrap()),
+                    (0, String::new()),
+                    "{text}"
+                );
+            }
+        }
+    }
+}
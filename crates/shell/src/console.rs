@@ -0,0 +1,32 @@
+/// Enables the processing of ANSI escape codes (ex. the colors of `ls`) by
+/// the Windows console. Returns false when the console doesn't support them.
+#[cfg(windows)]
+pub fn enable_ansi_support() -> bool {
+    use windows_sys::Win32::System::Console::{
+        GetConsoleMode, GetStdHandle, SetConsoleMode, ENABLE_VIRTUAL_TERMINAL_PROCESSING,
+        STD_ERROR_HANDLE, STD_OUTPUT_HANDLE,
+    };
+
+    let mut supported = true;
+    for std_handle in [STD_OUTPUT_HANDLE, STD_ERROR_HANDLE] {
+        // SAFETY: the handle is only passed to the console functions, which
+        // fail for handles that aren't a console
+        unsafe {
+            let handle = GetStdHandle(std_handle);
+            let mut mode = 0;
+            if GetConsoleMode(handle, &mut mode) == 0 {
+                // not a console (ex. a pipe), so the codes are kept as is
+                continue;
+            }
+            if SetConsoleMode(handle, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING) == 0 {
+                supported = false;
+            }
+        }
+    }
+    supported
+}
+
+#[cfg(not(windows))]
+pub fn enable_ansi_support() -> bool {
+    true
+}
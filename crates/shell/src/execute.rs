@@ -1,29 +1,36 @@
+use std::collections::HashMap;
+
 use deno_task_shell::{
-    execute_sequential_list, AsyncCommandBehavior, ExecuteResult, ShellPipeReader, ShellPipeWriter,
-    ShellState,
+    execute_sequential_list_with_source, AsyncCommandBehavior, EnvChange, ExecuteResult,
+    ShellPipeReader, ShellPipeWriter, ShellState,
 };
 use miette::{Context, IntoDiagnostic};
 
-pub async fn execute_inner(text: &str, state: ShellState) -> miette::Result<ExecuteResult> {
-    let list = deno_task_shell::parser::parse(text);
-
-    let mut stderr = ShellPipeWriter::stderr();
-    let stdout = ShellPipeWriter::stdout();
-    let stdin = ShellPipeReader::stdin();
-
-    if let Err(e) = list {
-        stderr.write_all(format!("Syntax error: {:?}", e).as_bytes())?;
-        return Ok(ExecuteResult::Exit(1, vec![]));
-    }
+pub async fn execute_inner(
+    text: &str,
+    state: ShellState,
+    stdin: ShellPipeReader,
+    stdout: ShellPipeWriter,
+    mut stderr: ShellPipeWriter,
+    async_command_behavior: AsyncCommandBehavior,
+) -> miette::Result<ExecuteResult> {
+    let (list, item_texts) = match deno_task_shell::parser::parse_with_source(text) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            stderr.write_all(format!("Syntax error: {:?}", e).as_bytes())?;
+            return Ok(ExecuteResult::Exit(1, vec![]));
+        }
+    };
 
     // spawn a sequential list and pipe its output to the environment
-    let result = execute_sequential_list(
-        list.unwrap(),
+    let result = execute_sequential_list_with_source(
+        list,
+        item_texts,
         state,
         stdin,
         stdout,
         stderr,
-        AsyncCommandBehavior::Wait,
+        async_command_behavior,
     )
     .await;
 
@@ -31,7 +38,25 @@ pub async fn execute_inner(text: &str, state: ShellState) -> miette::Result<Exec
 }
 
 pub async fn execute(text: &str, state: &mut ShellState) -> miette::Result<i32> {
-    let result = execute_inner(text, state.clone()).await?;
+    let (exit_code, _) = execute_collecting_changes(text, state).await?;
+    Ok(exit_code)
+}
+
+/// Executes the text, applies the resulting changes to the state and
+/// returns them together with the exit code.
+pub async fn execute_collecting_changes(
+    text: &str,
+    state: &mut ShellState,
+) -> miette::Result<(i32, Vec<EnvChange>)> {
+    let result = execute_inner(
+        text,
+        state.clone(),
+        ShellPipeReader::stdin(),
+        ShellPipeWriter::stdout(),
+        ShellPipeWriter::stderr(),
+        AsyncCommandBehavior::Wait,
+    )
+    .await?;
 
     match result {
         ExecuteResult::Continue(exit_code, changes, _) => {
@@ -40,8 +65,91 @@ pub async fn execute(text: &str, state: &mut ShellState) -> miette::Result<i32>
             std::env::set_current_dir(state.cwd())
                 .into_diagnostic()
                 .context("Failed to set CWD")?;
-            Ok(exit_code)
+            Ok((exit_code, changes))
         }
-        ExecuteResult::Exit(_, _) => Ok(0),
+        ExecuteResult::Exit(exit_code, _) => Ok((exit_code, Vec::new())),
+    }
+}
+
+/// Serializes the exported variables as NUL-delimited `NAME=value` entries,
+/// sorted by name, so values may contain newlines.
+pub fn serialize_env(env_vars: &HashMap<String, String>) -> Vec<u8> {
+    let mut names = env_vars.keys().collect::<Vec<_>>();
+    names.sort();
+    let mut result = Vec::new();
+    for name in names {
+        result.extend_from_slice(name.as_bytes());
+        result.push(b'=');
+        result.extend_from_slice(env_vars[name].as_bytes());
+        result.push(0);
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn serializes_exported_env() {
+        let cwd = std::env::current_dir().unwrap();
+        let mut state = ShellState::new(
+            HashMap::from([("EXISTING".to_string(), "1".to_string())]),
+            &cwd,
+            HashMap::new(),
+        );
+        let script = r#"
+export FOO=bar
+export MULTI="a
+b"
+export PATH_LIKE="/a:/b"
+LOCAL=ignored
+export EXISTING=2
+unset FOO
+export EMPTY=""
+"#;
+        let (exit_code, _) = execute_collecting_changes(script, &mut state)
+            .await
+            .unwrap();
+        assert_eq!(exit_code, 0);
+
+        let dump = serialize_env(state.env_vars());
+        let entries = dump
+            .split(|b| *b == 0)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let entry = std::str::from_utf8(entry).unwrap();
+                let (name, value) = entry.split_once('=').unwrap();
+                (name.to_string(), value.to_string())
+            })
+            .collect::<HashMap<_, _>>();
+        assert_eq!(entries.get("MULTI").map(String::as_str), Some("a\nb"));
+        assert_eq!(entries.get("PATH_LIKE").map(String::as_str), Some("/a:/b"));
+        assert_eq!(entries.get("EXISTING").map(String::as_str), Some("2"));
+        assert_eq!(entries.get("EMPTY").map(String::as_str), Some(""));
+        assert!(!entries.contains_key("FOO"));
+        assert!(!entries.contains_key("LOCAL"));
+    }
+
+    #[tokio::test]
+    async fn no_exec_still_reports_syntax_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("file.txt"), "").unwrap();
+        let state = ShellState::new(HashMap::new(), dir.path(), crate::commands::get_commands());
+        let (stderr_reader, stderr) = deno_task_shell::pipe();
+        let stderr_handle = stderr_reader.pipe_to_string_handle();
+        let result = execute_inner(
+            "set -n; rm file.txt; echo 'unterminated",
+            state,
+            ShellPipeReader::stdin(),
+            ShellPipeWriter::null(),
+            stderr,
+            AsyncCommandBehavior::Wait,
+        )
+        .await
+        .unwrap();
+        assert!(matches!(result, ExecuteResult::Exit(1, _)));
+        assert!(stderr_handle.await.unwrap().starts_with("Syntax error"));
+        assert!(dir.path().join("file.txt").exists());
     }
 }
@@ -18,10 +18,17 @@ pub(crate) struct ShellPromptHelper {
     pub colored_prompt: String,
 }
 
+impl ShellPromptHelper {
+    /// Updates the variable names offered when completing `$VAR`.
+    pub fn set_var_names(&mut self, var_names: Vec<String>) {
+        self.completer.set_var_names(var_names);
+    }
+}
+
 impl Default for ShellPromptHelper {
     fn default() -> Self {
         Self {
-            completer: completion::ShellCompleter,
+            completer: completion::ShellCompleter::default(),
             validator: MatchingBracketValidator::new(),
             colored_prompt: String::new(),
         }
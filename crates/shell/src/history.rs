@@ -0,0 +1,314 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use miette::bail;
+use miette::Result;
+
+/// The in-memory command history of an interactive session, shared with
+/// the `history` builtin.
+#[derive(Clone, Default)]
+pub struct History(Rc<RefCell<Vec<String>>>);
+
+impl History {
+    pub fn push(&self, entry: String) {
+        self.0.borrow_mut().push(entry);
+    }
+
+    pub fn entries(&self) -> Vec<String> {
+        self.0.borrow().clone()
+    }
+
+    pub fn last(&self) -> Option<String> {
+        self.0.borrow().last().cloned()
+    }
+
+    /// Drops the oldest entries so that at most `len` are kept.
+    pub fn set_max_len(&self, len: usize) {
+        let mut entries = self.0.borrow_mut();
+        if entries.len() > len {
+            let excess = entries.len() - len;
+            entries.drain(..excess);
+        }
+    }
+}
+
+/// The number of entries kept when `HISTSIZE` is not set, like bash.
+const DEFAULT_HISTORY_SIZE: usize = 500;
+
+/// How the history is recorded, read from `HISTSIZE`, `HISTFILESIZE` and
+/// `HISTCONTROL`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistorySettings {
+    /// The number of entries kept in memory.
+    pub size: usize,
+    /// The number of entries written to the history file.
+    pub file_size: usize,
+    pub ignore_dups: bool,
+    pub ignore_space: bool,
+}
+
+impl HistorySettings {
+    /// Reads the settings from the shell variables. A negative size means no
+    /// limit, and without `HISTCONTROL` both duplicates and lines starting
+    /// with a space are ignored.
+    pub fn from_vars<'a>(get_var: impl Fn(&str) -> Option<&'a str>) -> Self {
+        let size = get_var("HISTSIZE")
+            .and_then(parse_size)
+            .unwrap_or(DEFAULT_HISTORY_SIZE);
+        let file_size = get_var("HISTFILESIZE").and_then(parse_size).unwrap_or(size);
+        let (ignore_dups, ignore_space) = match get_var("HISTCONTROL") {
+            Some(control) => control
+                .split(':')
+                .fold((false, false), |(dups, space), value| match value {
+                    "ignoredups" => (true, space),
+                    "ignorespace" => (dups, true),
+                    "ignoreboth" => (true, true),
+                    _ => (dups, space),
+                }),
+            None => (true, true),
+        };
+        Self {
+            size,
+            file_size,
+            ignore_dups,
+            ignore_space,
+        }
+    }
+
+    /// Returns whether `line` should be added after the `last_entry`.
+    pub fn should_add(&self, line: &str, last_entry: Option<&str>) -> bool {
+        if self.size == 0 || line.is_empty() {
+            return false;
+        }
+        if self.ignore_space && line.starts_with(char::is_whitespace) {
+            return false;
+        }
+        !(self.ignore_dups && last_entry == Some(line))
+    }
+}
+
+fn parse_size(value: &str) -> Option<usize> {
+    match value.trim().parse::<i64>() {
+        Ok(size) if size < 0 => Some(usize::MAX),
+        Ok(size) => usize::try_from(size).ok(),
+        Err(_) => None,
+    }
+}
+
+/// Expands `!!`, `!n`, `!-n` and `!prefix` in a line using the history
+/// entries (oldest first). Returns `None` if nothing was expanded.
+///
+/// Like bash, nothing is expanded inside single quotes, and a `!` followed
+/// by whitespace, `=`, `(`, `"`, an operator or the end of the line is kept
+/// as is.
+pub fn expand_history(line: &str, history: &[String]) -> Result<Option<String>> {
+    let mut result = String::with_capacity(line.len());
+    let mut expanded = false;
+    let mut in_single_quotes = false;
+    let mut chars = line.char_indices();
+
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '\'' => {
+                in_single_quotes = !in_single_quotes;
+                result.push(c);
+            }
+            '\\' if !in_single_quotes => {
+                result.push(c);
+                if let Some((_, next)) = chars.next() {
+                    result.push(next);
+                }
+            }
+            '!' if !in_single_quotes => {
+                let rest = &line[index + 1..];
+                let designator_len = match rest.chars().next() {
+                    Some('!') => 1,
+                    Some(c) if c.is_ascii_digit() || c == '-' => {
+                        1 + rest[1..].chars().take_while(|c| c.is_ascii_digit()).count()
+                    }
+                    Some(c)
+                        if !c.is_whitespace()
+                            && !matches!(c, '=' | '(' | '"' | ';' | '&' | '|') =>
+                    {
+                        rest.find(|c: char| c.is_whitespace() || matches!(c, '"' | ';' | '&' | '|'))
+                            .unwrap_or(rest.len())
+                    }
+                    _ => {
+                        result.push(c);
+                        continue;
+                    }
+                };
+                let designator = &rest[..designator_len];
+                result.push_str(resolve_event(designator, history)?);
+                expanded = true;
+                for _ in 0..designator.chars().count() {
+                    chars.next();
+                }
+            }
+            _ => result.push(c),
+        }
+    }
+
+    Ok(expanded.then_some(result))
+}
+
+fn resolve_event<'a>(designator: &str, history: &'a [String]) -> Result<&'a str> {
+    let entry = if designator == "!" {
+        history.last()
+    } else if let Ok(number) = designator.parse::<isize>() {
+        if number > 0 {
+            history.get(number as usize - 1)
+        } else if number < 0 {
+            history
+                .len()
+                .checked_sub(number.unsigned_abs())
+                .and_then(|index| history.get(index))
+        } else {
+            None
+        }
+    } else {
+        history
+            .iter()
+            .rev()
+            .find(|entry| entry.starts_with(designator))
+    };
+    match entry {
+        Some(entry) => Ok(entry),
+        None => bail!("!{}: event not found", designator),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn history() -> Vec<String> {
+        vec![
+            "echo first".to_string(),
+            "ls -al".to_string(),
+            "echo last".to_string(),
+        ]
+    }
+
+    #[test]
+    fn expands_history() {
+        let history = history();
+        let expand = |line: &str| expand_history(line, &history).unwrap();
+
+        assert_eq!(expand("!!"), Some("echo last".to_string()));
+        assert_eq!(expand("!! && !2"), Some("echo last && ls -al".to_string()));
+        assert_eq!(expand("!1"), Some("echo first".to_string()));
+        assert_eq!(expand("!-2"), Some("ls -al".to_string()));
+        assert_eq!(expand("!ec"), Some("echo last".to_string()));
+        assert_eq!(expand("!l | cat"), Some("ls -al | cat".to_string()));
+        assert_eq!(expand("echo hi"), None);
+        assert_eq!(expand("! false"), None);
+        assert_eq!(expand("echo '!!'"), None);
+        assert_eq!(expand("echo \\!!"), None);
+        assert_eq!(expand("[[ a != b ]]"), None);
+        assert_eq!(expand("echo \"hi!\""), None);
+        assert_eq!(expand("echo hi!"), None);
+        assert_eq!(expand("echo \"hi! there\""), None);
+        assert_eq!(
+            expand("echo \"!ec\""),
+            Some("echo \"echo last\"".to_string())
+        );
+    }
+
+    #[test]
+    fn reports_missing_events() {
+        let history = history();
+        for line in ["!4", "!0", "!-4", "!cat"] {
+            assert_eq!(
+                expand_history(line, &history).err().unwrap().to_string(),
+                format!("{}: event not found", line)
+            );
+        }
+        assert!(expand_history("!!", &[]).is_err());
+    }
+
+    #[test]
+    fn reads_history_settings() {
+        let settings = |vars: &[(&'static str, &'static str)]| {
+            HistorySettings::from_vars(|name| {
+                vars.iter()
+                    .find(|(var, _)| *var == name)
+                    .map(|(_, value)| *value)
+            })
+        };
+
+        assert_eq!(
+            settings(&[]),
+            HistorySettings {
+                size: 500,
+                file_size: 500,
+                ignore_dups: true,
+                ignore_space: true,
+            }
+        );
+        assert_eq!(
+            settings(&[("HISTSIZE", "10"), ("HISTCONTROL", "ignorespace")]),
+            HistorySettings {
+                size: 10,
+                file_size: 10,
+                ignore_dups: false,
+                ignore_space: true,
+            }
+        );
+        assert_eq!(
+            settings(&[
+                ("HISTSIZE", "-1"),
+                ("HISTFILESIZE", "20"),
+                ("HISTCONTROL", "erasedups:ignoredups"),
+            ]),
+            HistorySettings {
+                size: usize::MAX,
+                file_size: 20,
+                ignore_dups: true,
+                ignore_space: false,
+            }
+        );
+        assert_eq!(settings(&[("HISTSIZE", "abc")]).size, 500);
+        assert!(!settings(&[("HISTCONTROL", "")]).ignore_dups);
+    }
+
+    #[test]
+    fn filters_history_entries() {
+        let settings = HistorySettings {
+            size: 500,
+            file_size: 500,
+            ignore_dups: true,
+            ignore_space: true,
+        };
+        assert!(settings.should_add("ls", None));
+        assert!(settings.should_add("ls", Some("pwd")));
+        assert!(!settings.should_add("ls", Some("ls")));
+        assert!(!settings.should_add(" ls", None));
+        assert!(!settings.should_add("", None));
+
+        let keep_all = HistorySettings {
+            ignore_dups: false,
+            ignore_space: false,
+            ..settings.clone()
+        };
+        assert!(keep_all.should_add("ls", Some("ls")));
+        assert!(keep_all.should_add(" ls", None));
+
+        let disabled = HistorySettings {
+            size: 0,
+            ..settings
+        };
+        assert!(!disabled.should_add("ls", None));
+    }
+
+    #[test]
+    fn limits_history_length() {
+        let history = History::default();
+        for entry in ["a", "b", "c"] {
+            history.push(entry.to_string());
+        }
+        history.set_max_len(2);
+        assert_eq!(history.entries(), vec!["b".to_string(), "c".to_string()]);
+        assert_eq!(history.last(), Some("c".to_string()));
+    }
+}
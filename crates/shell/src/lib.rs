@@ -1,2 +1,6 @@
 pub mod commands;
+pub mod completion;
 pub mod execute;
+pub mod history;
+pub mod prompt;
+pub mod startup;
@@ -1,46 +1,116 @@
+use std::io::Read;
 use std::path::Path;
 use std::path::PathBuf;
+use std::rc::Rc;
 
 use clap::Parser;
 use deno_task_shell::parser::debug_parse;
+use deno_task_shell::EnvChange;
+use deno_task_shell::ShellCommand;
 use deno_task_shell::ShellState;
+use history::expand_history;
+use history::History;
+use history::HistorySettings;
 use miette::Context;
 use miette::IntoDiagnostic;
 use rustyline::error::ReadlineError;
+use rustyline::history::History as _;
 use rustyline::{CompletionType, Config, Editor};
+use shell::completion;
+use shell::prompt::format_cwd;
+use shell::prompt::CwdStyle;
 
 mod commands;
-mod completion;
+mod console;
 mod execute;
 mod helper;
+mod history;
+mod startup;
 
 pub use execute::execute;
+use execute::execute_collecting_changes;
+use execute::serialize_env;
+use startup::StartupOptions;
 #[derive(Parser)]
 struct Options {
-    /// The path to the file that should be executed
+    /// The path to the file that should be executed, or `-` to read the
+    /// script from stdin
     file: Option<PathBuf>,
 
+    /// Execute this script instead of a file
+    #[clap(short = 'c', long, value_name = "SCRIPT", conflicts_with = "file")]
+    command: Option<String>,
+
     /// Continue in interactive mode after the file has been executed
     #[clap(long)]
     interact: bool,
 
-    /// Do not source ~/.shellrc on startup
+    /// Do not source any startup files (~/.shellrc or the login files)
     #[clap(long)]
     norc: bool,
 
+    /// Act as a login shell and also source ~/.shell_profile (or ~/.profile)
+    #[clap(short, long)]
+    login: bool,
+
+    /// Source this file instead of ~/.shellrc (or $SHELLRC)
+    #[clap(long, value_name = "PATH")]
+    rcfile: Option<PathBuf>,
+
     #[clap(short, long)]
     debug: bool,
+
+    /// Print the syntax tree of the file as JSON instead of executing it
+    #[cfg(feature = "serialization")]
+    #[clap(long, requires = "file")]
+    debug_json: bool,
+
+    /// Execute the file and then write the exported environment variables as
+    /// NUL-delimited `NAME=value` entries to this path (e.g. `/dev/fd/3`)
+    #[clap(long, value_name = "PATH", requires = "file")]
+    eval_and_print_env: Option<PathBuf>,
 }
 
-fn init_state() -> ShellState {
+fn init_state(history: &History) -> ShellState {
     let env_vars = std::env::vars().collect();
     let cwd = std::env::current_dir().unwrap();
-    ShellState::new(env_vars, &cwd, commands::get_commands())
+    let mut commands = commands::get_commands();
+    commands.insert(
+        "history".to_string(),
+        Rc::new(commands::HistoryCommand::new(history.clone())) as Rc<dyn ShellCommand>,
+    );
+    let mut state = ShellState::new(env_vars, &cwd, commands);
+    state.apply_change(&EnvChange::SetShellVar(
+        "0".to_string(),
+        "shell".to_string(),
+    ));
+    if !console::enable_ansi_support() {
+        // the raw escape codes would be printed otherwise
+        state.apply_change(&EnvChange::SetShellVar(
+            "NO_COLOR".to_string(),
+            "1".to_string(),
+        ));
+    }
+    state
+}
+
+fn history_settings(state: &ShellState) -> HistorySettings {
+    HistorySettings::from_vars(|name| state.get_var(name).map(String::as_str))
 }
 
-async fn interactive(state: Option<ShellState>, norc: bool) -> miette::Result<()> {
+async fn interactive(
+    state: Option<ShellState>,
+    history: History,
+    startup_options: StartupOptions,
+) -> miette::Result<()> {
+    // The history is filtered and limited by `HistorySettings` instead, so
+    // that `HISTSIZE` and `HISTCONTROL` can change during the session
     let config = Config::builder()
-        .history_ignore_space(true)
+        .history_ignore_space(false)
+        .history_ignore_dups(false)
+        .into_diagnostic()?
+        .max_history_size(usize::MAX)
+        .into_diagnostic()?
         .completion_type(CompletionType::List)
         .build();
 
@@ -54,7 +124,8 @@ async fn interactive(state: Option<ShellState>, norc: bool) -> miette::Result<()
     let helper = helper::ShellPromptHelper::default();
     rl.set_helper(Some(helper));
 
-    let mut state = state.unwrap_or_else(init_state);
+    let mut state = state.unwrap_or_else(|| init_state(&history));
+    state.set_interactive(true);
 
     let home = dirs::home_dir().ok_or(miette::miette!("Couldn't get home directory"))?;
 
@@ -66,17 +137,18 @@ async fn interactive(state: Option<ShellState>, norc: bool) -> miette::Result<()
         rl.load_history(history_file.as_path())
             .into_diagnostic()
             .context("Failed to read the command history")?;
+        for entry in rl.history().iter() {
+            history.push(entry.clone());
+        }
     }
 
-    // Load ~/.shellrc
-    let shellrc_file: PathBuf = [home.as_path(), Path::new(".shellrc")].iter().collect();
-    if !norc && Path::new(shellrc_file.as_path()).exists() {
-        let line = "source '".to_owned() + shellrc_file.to_str().unwrap() + "'";
-        let prev_exit_code = execute(&line, &mut state)
-            .await
-            .context("Failed to source ~/.shellrc")?;
-        state.set_last_command_exit_code(prev_exit_code);
-    }
+    // Load the login files and ~/.shellrc
+    startup::source_startup_files(&mut state, &home, &startup_options).await?;
+    let settings = history_settings(&state);
+    rl.history_mut()
+        .set_max_len(settings.size)
+        .into_diagnostic()?;
+    history.set_max_len(settings.size);
 
     let mut _prev_exit_code = 0;
     loop {
@@ -85,10 +157,6 @@ async fn interactive(state: Option<ShellState>, norc: bool) -> miette::Result<()
 
         // Display the prompt and read a line
         let readline = {
-            let cwd = state.cwd().to_string_lossy().to_string();
-            let home_str = home.to_str().ok_or(miette::miette!(
-                "Couldn't convert home directory path to UTF-8 string"
-            ))?;
             if !state.last_command_cd() {
                 state.update_git_branch();
             }
@@ -108,22 +176,48 @@ async fn interactive(state: Option<ShellState>, norc: bool) -> miette::Result<()
                 git_branch = "(".to_owned() + &git_branch + ")";
             }
 
-            let display_cwd = if let Some(stripped) = cwd.strip_prefix(home_str) {
-                format!("~{}", stripped.replace('\\', "/"))
-            } else {
-                cwd.to_string()
-            };
+            let display_cwd = format_cwd(state.cwd(), &home, CwdStyle::Full);
 
             let prompt = format!("{}{git_branch}$ ", display_cwd);
             let color_prompt = format!("\x1b[34m{}\x1b[32m{git_branch}\x1b[0m$ ", display_cwd);
-            rl.helper_mut().unwrap().colored_prompt = color_prompt;
+            let helper = rl.helper_mut().unwrap();
+            helper.colored_prompt = color_prompt;
+            helper.set_var_names(
+                state
+                    .env_vars()
+                    .keys()
+                    .chain(state.shell_vars().keys())
+                    .cloned()
+                    .collect(),
+            );
             rl.readline(&prompt)
         };
 
         match readline {
             Ok(line) => {
+                // Expand `!!`, `!n` and `!prefix` before running the line
+                let line = match expand_history(&line, &history.entries()) {
+                    Ok(Some(expanded)) => {
+                        println!("{}", expanded);
+                        expanded
+                    }
+                    Ok(None) => line,
+                    Err(err) => {
+                        eprintln!("shell: {}", err);
+                        continue;
+                    }
+                };
+
                 // Add the line to history
-                rl.add_history_entry(line.as_str()).into_diagnostic()?;
+                let settings = history_settings(&state);
+                if settings.should_add(&line, history.last().as_deref()) {
+                    rl.history_mut()
+                        .set_max_len(settings.size)
+                        .into_diagnostic()?;
+                    rl.add_history_entry(line.as_str()).into_diagnostic()?;
+                    history.push(line.clone());
+                    history.set_max_len(settings.size);
+                }
 
                 // Process the input (here we just echo it back)
                 let prev_exit_code = execute(&line, &mut state)
@@ -152,6 +246,9 @@ async fn interactive(state: Option<ShellState>, norc: bool) -> miette::Result<()
             }
         }
     }
+    rl.history_mut()
+        .set_max_len(history_settings(&state).file_size)
+        .into_diagnostic()?;
     rl.save_history(history_file.as_path())
         .into_diagnostic()
         .context("Failed to write the command history")?;
@@ -162,20 +259,71 @@ async fn interactive(state: Option<ShellState>, norc: bool) -> miette::Result<()
 #[tokio::main]
 async fn main() -> miette::Result<()> {
     let options = Options::parse();
+    let history = History::default();
+    let startup_options = StartupOptions {
+        login: options.login,
+        norc: options.norc,
+        rcfile: options.rcfile,
+    };
 
-    if let Some(file) = options.file {
-        let script_text = std::fs::read_to_string(&file).unwrap();
-        let mut state = init_state();
+    let script = if let Some(command) = options.command {
+        Some((command, None))
+    } else if let Some(file) = options.file {
+        if file.as_os_str() == "-" {
+            let mut script_text = String::new();
+            std::io::stdin()
+                .read_to_string(&mut script_text)
+                .into_diagnostic()
+                .context("Failed to read the script from stdin")?;
+            Some((script_text, None))
+        } else {
+            let script_text = std::fs::read_to_string(&file)
+                .into_diagnostic()
+                .with_context(|| format!("Failed to read {}", file.display()))?;
+            Some((script_text, Some(file)))
+        }
+    } else {
+        None
+    };
+
+    if let Some((script_text, file)) = script {
+        let mut state = init_state(&history);
+        if let Some(file) = file {
+            // `$0` is the path of the script, like in bash
+            state.apply_change(&EnvChange::SetShellVar(
+                "0".to_string(),
+                file.display().to_string(),
+            ));
+            state.push_source_file(file.display().to_string());
+        }
         if options.debug {
             debug_parse(&script_text);
             return Ok(());
         }
-        execute(&script_text, &mut state).await?;
+        #[cfg(feature = "serialization")]
+        if options.debug_json {
+            let list = deno_task_shell::parser::parse(&script_text)?;
+            println!("{}", list.to_json_pretty()?);
+            return Ok(());
+        }
+        if let Some(env_path) = options.eval_and_print_env {
+            let (exit_code, _) = execute_collecting_changes(&script_text, &mut state).await?;
+            std::fs::write(&env_path, serialize_env(state.env_vars()))
+                .into_diagnostic()
+                .with_context(|| {
+                    format!("Failed to write environment to {}", env_path.display())
+                })?;
+            std::process::exit(exit_code);
+        }
+        let exit_code = execute(&script_text, &mut state).await?;
         if options.interact {
-            interactive(Some(state), options.norc).await?;
+            interactive(Some(state), history, startup_options).await?;
+        } else {
+            // the exit code of the shell is the one of the last command
+            std::process::exit(exit_code);
         }
     } else {
-        interactive(None, options.norc).await?;
+        interactive(None, history, startup_options).await?;
     }
 
     Ok(())
@@ -0,0 +1,78 @@
+use std::path::Path;
+
+/// How the current directory is shown, like bash's `\w` and `\W` prompt
+/// escapes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CwdStyle {
+    /// The full path with the home directory shortened to `~` (`\w`)
+    #[default]
+    Full,
+    /// Only the last component, or `~` for the home directory (`\W`)
+    Basename,
+}
+
+/// Replaces the home directory at the start of `cwd` with `~`.
+///
+/// Only whole components are matched, so `/home/user2` is not shortened
+/// for the home `/home/user`.
+pub fn shorten_path(cwd: &Path, home: &Path) -> String {
+    match cwd.strip_prefix(home) {
+        Ok(rest) if rest.as_os_str().is_empty() => "~".to_string(),
+        Ok(rest) => format!("~/{}", rest.to_string_lossy().replace('\\', "/")),
+        Err(_) => cwd.to_string_lossy().to_string(),
+    }
+}
+
+/// Renders the current directory for the prompt in the given style.
+pub fn format_cwd(cwd: &Path, home: &Path, style: CwdStyle) -> String {
+    match style {
+        CwdStyle::Full => shorten_path(cwd, home),
+        CwdStyle::Basename if cwd == home => "~".to_string(),
+        CwdStyle::Basename => match cwd.file_name() {
+            Some(name) => name.to_string_lossy().to_string(),
+            // the root directory has no name
+            None => cwd.to_string_lossy().to_string(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn shortens_paths_inside_home() {
+        let home = Path::new("/home/user");
+        assert_eq!(
+            shorten_path(Path::new("/home/user/src/shell"), home),
+            "~/src/shell"
+        );
+        assert_eq!(shorten_path(Path::new("/home/user"), home), "~");
+        assert_eq!(shorten_path(Path::new("/home/user/"), home), "~");
+    }
+
+    #[test]
+    fn keeps_paths_outside_home() {
+        let home = Path::new("/home/user");
+        assert_eq!(shorten_path(Path::new("/usr/bin"), home), "/usr/bin");
+        assert_eq!(shorten_path(Path::new("/home"), home), "/home");
+        assert_eq!(
+            shorten_path(Path::new("/home/user2/src"), home),
+            "/home/user2/src"
+        );
+    }
+
+    #[test]
+    fn formats_basename() {
+        let home = Path::new("/home/user");
+        let basename = |cwd| format_cwd(Path::new(cwd), home, CwdStyle::Basename);
+        assert_eq!(basename("/home/user/src/shell"), "shell");
+        assert_eq!(basename("/home/user"), "~");
+        assert_eq!(basename("/usr/bin"), "bin");
+        assert_eq!(basename("/"), "/");
+        assert_eq!(
+            format_cwd(Path::new("/home/user/src"), home, CwdStyle::Full),
+            "~/src"
+        );
+    }
+}
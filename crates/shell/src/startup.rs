@@ -0,0 +1,147 @@
+use std::path::Path;
+use std::path::PathBuf;
+
+use deno_task_shell::ShellState;
+use miette::Context;
+
+use crate::execute::execute;
+
+/// The environment variable that overrides the path of the rc file.
+pub const SHELLRC_ENV_VAR: &str = "SHELLRC";
+
+/// The files sourced by a login shell, the first one that exists is used.
+const LOGIN_FILES: [&str; 2] = [".shell_profile", ".profile"];
+
+#[derive(Debug, Default)]
+pub struct StartupOptions {
+    /// Also source the login files (`--login`)
+    pub login: bool,
+    /// Skip all startup files (`--norc`)
+    pub norc: bool,
+    /// Source this file instead of `~/.shellrc` (`--rcfile`)
+    pub rcfile: Option<PathBuf>,
+}
+
+/// Returns the existing startup files in the order they are sourced.
+///
+/// The login file (`~/.shell_profile` or else `~/.profile`) comes before
+/// the interactive rc file. The rc file is the first of `--rcfile`,
+/// `$SHELLRC` and `~/.shellrc`. `--norc` skips all of them.
+pub fn startup_files(
+    home: &Path,
+    options: &StartupOptions,
+    env_rcfile: Option<PathBuf>,
+) -> Vec<PathBuf> {
+    if options.norc {
+        return Vec::new();
+    }
+
+    let mut files = Vec::new();
+    if options.login {
+        files.extend(
+            LOGIN_FILES
+                .iter()
+                .map(|name| home.join(name))
+                .find(|path| path.is_file()),
+        );
+    }
+    let rcfile = options
+        .rcfile
+        .clone()
+        .or(env_rcfile)
+        .unwrap_or_else(|| home.join(".shellrc"));
+    if rcfile.is_file() {
+        files.push(rcfile);
+    }
+    files
+}
+
+/// Sources the startup files into the state.
+pub async fn source_startup_files(
+    state: &mut ShellState,
+    home: &Path,
+    options: &StartupOptions,
+) -> miette::Result<()> {
+    let env_rcfile = state.get_var(SHELLRC_ENV_VAR).map(PathBuf::from);
+    for file in startup_files(home, options, env_rcfile) {
+        let line = format!("source '{}'", file.display());
+        let exit_code = execute(&line, state)
+            .await
+            .with_context(|| format!("Failed to source {}", file.display()))?;
+        state.set_last_command_exit_code(exit_code);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn orders_startup_files() {
+        let home = tempfile::tempdir().unwrap();
+        let home = home.path();
+        fs::write(home.join(".shellrc"), "").unwrap();
+        fs::write(home.join(".profile"), "").unwrap();
+        fs::write(home.join("custom_rc"), "").unwrap();
+
+        let options = StartupOptions::default();
+        assert_eq!(
+            startup_files(home, &options, None),
+            vec![home.join(".shellrc")]
+        );
+
+        let options = StartupOptions {
+            login: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            startup_files(home, &options, None),
+            vec![home.join(".profile"), home.join(".shellrc")]
+        );
+        fs::write(home.join(".shell_profile"), "").unwrap();
+        assert_eq!(
+            startup_files(home, &options, Some(home.join("missing_rc"))),
+            vec![home.join(".shell_profile")]
+        );
+
+        let options = StartupOptions {
+            rcfile: Some(home.join("custom_rc")),
+            ..Default::default()
+        };
+        assert_eq!(
+            startup_files(home, &options, Some(home.join(".shellrc"))),
+            vec![home.join("custom_rc")]
+        );
+
+        let options = StartupOptions {
+            login: true,
+            norc: true,
+            rcfile: Some(home.join("custom_rc")),
+        };
+        assert!(startup_files(home, &options, None).is_empty());
+    }
+
+    #[tokio::test]
+    async fn sources_rcfile() {
+        let home = tempfile::tempdir().unwrap();
+        let home = home.path();
+        fs::write(home.join(".shellrc"), "export FROM_SHELLRC=1\n").unwrap();
+        fs::write(home.join("custom_rc"), "export FROM_CUSTOM=1\n").unwrap();
+
+        let cwd = std::env::current_dir().unwrap();
+        let mut state = ShellState::new(HashMap::new(), &cwd, crate::commands::get_commands());
+        let options = StartupOptions {
+            rcfile: Some(home.join("custom_rc")),
+            ..Default::default()
+        };
+        source_startup_files(&mut state, home, &options)
+            .await
+            .unwrap();
+        assert_eq!(state.get_var("FROM_CUSTOM").map(String::as_str), Some("1"));
+        assert_eq!(state.get_var("FROM_SHELLRC"), None);
+    }
+}
@@ -0,0 +1,72 @@
+use std::io::Write;
+use std::process::Command;
+use std::process::Output;
+use std::process::Stdio;
+
+fn shell(args: &[&str], stdin: &str) -> Output {
+    let dir = tempfile::tempdir().unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_shell"))
+        .args(args)
+        .current_dir(dir.path())
+        .env("HOME", dir.path())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn command_exits_with_last_exit_code() {
+    assert_eq!(shell(&["-c", "true; false"], "").status.code(), Some(1));
+    assert_eq!(
+        shell(&["-c", "set +e; false; true"], "").status.code(),
+        Some(0)
+    );
+    // errexit is on by default, so the script stops at `false`
+    let output = shell(&["-c", "false; echo unreachable"], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "");
+    assert_eq!(shell(&["--command", "exit 3"], "").status.code(), Some(3));
+}
+
+#[test]
+fn command_runs_multiple_statements() {
+    let output = shell(
+        &[
+            "-c",
+            "echo a; echo b\nif [[ $(echo x) == x ]]; then\n  echo yes\nelse\n  echo no\nfi\necho c && echo d",
+        ],
+        "",
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "a\nb\nyes\nc\nd\n");
+}
+
+#[test]
+fn script_from_stdin() {
+    let output = shell(&["-"], "echo from stdin\nexit 4\n");
+    assert_eq!(output.status.code(), Some(4));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "from stdin\n");
+}
+
+#[test]
+fn script_file_sets_script_name_and_line() {
+    let dir = tempfile::tempdir().unwrap();
+    let script = dir.path().join("script.sh");
+    std::fs::write(&script, "echo $0\n\necho line $LINENO\n").unwrap();
+    let script = script.display().to_string();
+    let output = shell(&[&script], "");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        format!("{script}\nline 3\n")
+    );
+}
@@ -6,6 +6,10 @@ mod test_builder;
 #[cfg(test)]
 use deno_task_shell::ExecuteResult;
 #[cfg(test)]
+use deno_task_shell::ShellCommandContext;
+#[cfg(test)]
+use deno_task_shell::ShellOptions;
+#[cfg(test)]
 use futures::FutureExt;
 #[cfg(test)]
 use test_builder::TestBuilder;
@@ -109,6 +113,31 @@ async fn commands() {
 
     TestBuilder::new().command("unset").run().await;
 
+    TestBuilder::new()
+        .command(r#"FOO=1; unset FOO; echo "[${FOO:-}]""#)
+        .assert_stdout("[]\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command(r#"export FOO=1; unset -v FOO; echo "[${FOO:-}]""#)
+        .assert_stdout("[]\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command(r#"FOO=1; unset -f FOO; echo $FOO"#)
+        .assert_stdout("1\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("unset -fv FOO")
+        .assert_stderr("unset: cannot simultaneously unset a function and a variable\n")
+        .assert_exit_code(1)
+        .run()
+        .await;
+
     TestBuilder::new()
         .command("a=1 && echo $((a=2, a + 1)) && echo $a")
         .assert_stdout("3\n2\n")
@@ -147,6 +176,34 @@ async fn boolean_logic() {
         .assert_stdout("2\n3\n")
         .run()
         .await;
+
+    // `&&` and `||` have the same precedence and group from the left
+    TestBuilder::new()
+        .command("false || true && echo x")
+        .assert_stdout("x\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("true || false && echo 1 && false || echo 2")
+        .assert_stdout("1\n2\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("true && false || false && echo 1")
+        .assert_stdout("")
+        .assert_exit_code(1)
+        .run()
+        .await;
+
+    // only the last command of the list runs the ERR trap
+    TestBuilder::new()
+        .command("trap 'echo trapped' ERR; true && false || echo 1; false || false && echo 2")
+        .assert_stdout("1\n")
+        .assert_exit_code(1)
+        .run()
+        .await;
 }
 
 #[tokio::test]
@@ -190,10 +247,34 @@ async fn exit() {
 
     TestBuilder::new()
         .command("exit ; echo 2")
+        .assert_exit_code(0)
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("(exit 257)")
+        .assert_exit_code(1)
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("(exit -1)")
+        .assert_exit_code(255)
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("set +e; false; exit; echo 2")
         .assert_exit_code(1)
         .run()
         .await;
 
+    TestBuilder::new()
+        .command("true; exit; echo 2")
+        .assert_exit_code(0)
+        .run()
+        .await;
+
     TestBuilder::new()
         .command("exit bad args")
         .assert_stderr("exit: too many arguments\n")
@@ -228,6 +309,45 @@ async fn command_substitution() {
         .assert_stderr("1: command not found\n")
         .run()
         .await;
+
+    // assignments keep the inner newlines, unlike arguments
+    TestBuilder::new()
+        .command("FOO=$(echo a && echo b)\necho \"$FOO\"\necho $(echo a && echo b)")
+        .assert_stdout("a\nb\na b\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("FOO=$(echo a && echo && echo b) && echo \"$FOO\"")
+        .assert_stdout("a\n\nb\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .file("file.txt", "")
+        .command("FOO=*.txt\necho \"$FOO\"")
+        .assert_stdout("*.txt\n")
+        .run()
+        .await;
+
+    // reads the file without running a command
+    TestBuilder::new()
+        .file("file.txt", "hello\n")
+        .command(r#"echo "$(< file.txt)" && echo $(<file.txt) there"#)
+        .assert_stdout("hello\nhello there\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .ensure_temp_dir()
+        .command(r#"echo "$(< missing.txt)""#)
+        .assert_stdout("\n")
+        .assert_stderr(&format!(
+            "error opening file for redirect ($TEMP_DIR{FOLDER_SEPARATOR}missing.txt). {}\n",
+            no_such_file_error_text()
+        ))
+        .run()
+        .await;
 }
 
 #[tokio::test]
@@ -298,6 +418,15 @@ async fn pwd() {
         .run()
         .await;
 
+    TestBuilder::new()
+        .directory("sub_dir")
+        .command("cd sub_dir && pwd -P && pwd -L")
+        .assert_stdout(&format!(
+            "$TEMP_DIR{FOLDER_SEPARATOR}sub_dir\n$TEMP_DIR{FOLDER_SEPARATOR}sub_dir\n"
+        ))
+        .run()
+        .await;
+
     TestBuilder::new()
         .command("pwd -M")
         .assert_stderr("pwd: unsupported flag: -M\n")
@@ -347,8 +476,8 @@ async fn subshells() {
 async fn pwd_logical() {
     TestBuilder::new()
         .directory("main")
-        .command("ln -s main symlinked_main && cd symlinked_main && pwd && pwd -L")
-        .assert_stdout("$TEMP_DIR/symlinked_main\n$TEMP_DIR/main\n")
+        .command("ln -s main symlinked_main && cd symlinked_main && pwd && pwd -L && pwd -P")
+        .assert_stdout("$TEMP_DIR/symlinked_main\n$TEMP_DIR/symlinked_main\n$TEMP_DIR/main\n")
         .run()
         .await;
 }
@@ -494,6 +623,30 @@ async fn head() {
         .assert_stdout("foo\nbar\nbaz\n")
         .run()
         .await;
+
+    // -c
+    TestBuilder::new()
+        .command("head -c 3")
+        .stdin("abcdef")
+        .assert_stdout("abc")
+        .run()
+        .await;
+
+    // --bytes, larger than the read buffer
+    TestBuilder::new()
+        .command("head --bytes=600 file")
+        .file("file", &"a".repeat(1000))
+        .assert_stdout(&"a".repeat(600))
+        .run()
+        .await;
+
+    // -c -N
+    TestBuilder::new()
+        .command("head -c -2 file")
+        .file("file", "abcdef")
+        .assert_stdout("abcd")
+        .run()
+        .await;
 }
 
 // Basic integration tests as there are unit tests in the commands
@@ -587,6 +740,30 @@ async fn mkdir() {
         .await;
 }
 
+#[tokio::test]
+async fn rmdir() {
+    TestBuilder::new()
+        .command("mkdir a/b -p && rmdir a/b && rmdir a")
+        .assert_not_exists("a")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("mkdir -p a/b/c && rmdir -p a/b/c")
+        .assert_not_exists("a")
+        .run()
+        .await;
+
+    // error message
+    TestBuilder::new()
+        .command("mkdir -p a/b && rmdir a")
+        .assert_stderr("rmdir: failed to remove 'a': Directory not empty\n")
+        .assert_exit_code(1)
+        .assert_exists("a/b")
+        .run()
+        .await;
+}
+
 // Basic integration tests as there are unit tests in the commands
 #[tokio::test]
 async fn rm() {
@@ -629,13 +806,38 @@ async fn custom_command() {
                     for val in context.args {
                         sum += val.parse::<usize>().unwrap();
                     }
-                    let _ = context.stderr.write_line(&sum.to_string());
+                    let _ = context.stdout.write_line(&sum.to_string());
                     ExecuteResult::from_exit_code(0)
                 }
                 .boxed_local()
             }),
         )
-        .assert_stderr("3\n")
+        .assert_stdout("3\n")
+        .run()
+        .await;
+
+    // redirects reach the streams of custom commands
+    let report = |mut context: ShellCommandContext| {
+        async move {
+            let _ = context.stdout.write_line("output");
+            let _ = context.stderr.write_line("report: warning");
+            ExecuteResult::from_exit_code(0)
+        }
+        .boxed_local()
+    };
+    TestBuilder::new()
+        .command("report > out.txt")
+        .custom_command("report", Box::new(report))
+        .assert_stderr("report: warning\n")
+        .assert_file_equals("out.txt", "output\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("report 2> err.txt")
+        .custom_command("report", Box::new(report))
+        .assert_stdout("output\n")
+        .assert_file_equals("err.txt", "report: warning\n")
         .run()
         .await;
 }
@@ -826,7 +1028,7 @@ async fn which() {
     TestBuilder::new()
         .command("which bla foo")
         .assert_exit_code(1)
-        .assert_stderr("Expected one argument\n")
+        .assert_stderr("which: expected one argument\n")
         .run()
         .await;
 
@@ -893,6 +1095,64 @@ async fn arithmetic() {
         .assert_stdout("16\n")
         .run()
         .await;
+
+    TestBuilder::new()
+        .command(r#"x=abc; echo $((x + 1)) || echo $?; y=""; echo $((y + 1))"#)
+        .assert_stderr("x: invalid arithmetic operand: abc\n")
+        .assert_stdout("2\n1\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("echo $((0xff)) $((010)) $((2#1010)) $((16#fF + 64#_)); x=0x1f; echo $((x + 1))")
+        .assert_stdout("255 8 10 318\n32\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("echo $((09)) || echo $?")
+        .assert_stderr("value too great for base: 09\n")
+        .assert_stdout("2\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("i=1; echo $((i++)); echo $i; echo $((++i)) $((i--)) $((--i)) $i")
+        .assert_stdout("1\n2\n3 3 1 1\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("echo $((-5)) $((-(2))) $((!0)) $((~0))")
+        .assert_stdout("-5 -2 1 -1\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("echo $((1 ? 10 : 20)) $((0 ? 10 : 20)) $((a=0, b=2, a ? 1 : b))")
+        .assert_stdout("10 20 2\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("echo $((i++ ? 10 : 20)) $((i++ ? 10 : 20)) $i")
+        .assert_stdout("20 10 2\n")
+        .run()
+        .await;
+
+    // like bash, results are truncated to integers
+    TestBuilder::new()
+        .command("echo $((10 / 3)) $((-10 / 3)) $((7 / 2.0)) $((-7 / 2.0)) $((2 ** -1)); declare -i n=5/2.0; echo $n")
+        .assert_stdout("3 -3 3 -3 0\n2\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("echo $((10 / 3)) $((7 / 2.0))")
+        .shell_option(ShellOptions::FloatArithmetic, true)
+        .assert_stdout("3 3.5\n")
+        .run()
+        .await;
 }
 
 #[tokio::test]
@@ -931,6 +1191,74 @@ async fn if_clause() {
         .run()
         .await;
 
+    TestBuilder::new()
+        .file("a.txt", "a")
+        .file("b.txt", "b")
+        .command(r#"if [[ -f a.txt && -f b.txt ]]; then echo "both"; else echo "missing"; fi"#)
+        .assert_stdout("both\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .file("a.txt", "a")
+        .command(r#"if [[ -f a.txt && -f b.txt ]]; then echo "both"; else echo "missing"; fi"#)
+        .assert_stdout("missing\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .file("a.txt", "a")
+        .command(r#"if [[ -d a.txt || -f a.txt ]]; then echo "exists"; fi"#)
+        .assert_stdout("exists\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command(r#"if [[ ! -d missing ]]; then echo "no dir"; fi"#)
+        .assert_stdout("no dir\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .directory("sub_dir")
+        .command(r#"FOO=bar; if [[ ! -d sub_dir || $FOO == bar && -n $FOO ]]; then echo "yes"; else echo "no"; fi"#)
+        .assert_stdout("yes\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command(r#"FOO=""; if [[ -z $FOO && ! $FOO == bar ]]; then echo "empty"; fi"#)
+        .assert_stdout("empty\n")
+        .run()
+        .await;
+
+    // the right side must not be evaluated when the left side decides the result
+    TestBuilder::new()
+        .command(r#"if [[ -n x || ${FOO:=set} == set ]]; then echo "$FOO"; fi"#)
+        .assert_stdout("\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command(r#"if [[ foo.txt == *.txt ]]; then echo "match"; else echo "no match"; fi"#)
+        .assert_stdout("match\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command(r#"if [[ foo.txt == "*.txt" ]]; then echo "match"; else echo "no match"; fi"#)
+        .assert_stdout("no match\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command(
+            r#"FILE=dir/foo.txt; if [[ $FILE != *.md && $FILE == d?r/* ]]; then echo "match"; fi"#,
+        )
+        .assert_stdout("match\n")
+        .run()
+        .await;
+
     TestBuilder::new()
         .script_file("../../scripts/if_else.sh")
         .assert_exit_code(0)
@@ -942,6 +1270,68 @@ async fn if_clause() {
         .await;
 }
 
+#[tokio::test]
+async fn test_command_operators() {
+    TestBuilder::new()
+        .file("a", "a")
+        .file("b", "b")
+        .command(r#"if [ -f a -a -f b ]; then echo "both"; else echo "missing"; fi"#)
+        .assert_stdout("both\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .file("a", "a")
+        .command(r#"if [ -f a -a -f b ]; then echo "both"; else echo "missing"; fi"#)
+        .assert_stdout("missing\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .file("y", "y")
+        .command(r#"if [ ! -d x -o -f y ]; then echo "yes"; fi"#)
+        .assert_stdout("yes\n")
+        .run()
+        .await;
+
+    // `!` only applies to the expression right after it
+    TestBuilder::new()
+        .file("a", "a")
+        .command(r#"if [ ! -f a -o -f c ]; then echo "yes"; else echo "no"; fi"#)
+        .assert_stdout("no\n")
+        .run()
+        .await;
+
+    // `-a` binds tighter than `-o`
+    TestBuilder::new()
+        .command(r#"if [ 1 -eq 1 -o 1 -eq 2 -a 1 -eq 3 ]; then echo "yes"; fi"#)
+        .assert_stdout("yes\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .file("a", "a")
+        .command(r#"if [ ! \( -f a -o -f c \) ]; then echo "yes"; else echo "no"; fi"#)
+        .assert_stdout("no\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .file("a", "a")
+        .command(r#"if test \( -f c -o -f a \) -a -n x; then echo "yes"; fi"#)
+        .assert_stdout("yes\n")
+        .run()
+        .await;
+
+    // `-a` with a single operand is still the file test
+    TestBuilder::new()
+        .file("a", "a")
+        .command(r#"if [ -a a ]; then echo "exists"; fi"#)
+        .assert_stdout("exists\n")
+        .run()
+        .await;
+}
+
 #[tokio::test]
 async fn touch() {
     TestBuilder::new()
@@ -1072,28 +1462,49 @@ async fn touch() {
 }
 
 #[tokio::test]
-async fn variable_expansion() {
-    // DEFAULT VALUE EXPANSION
+async fn comments() {
     TestBuilder::new()
-        .command("echo ${FOO:-5}")
-        .assert_stdout("5\n")
+        .command("echo hi # this is a comment")
+        .assert_stdout("hi\n")
         .run()
         .await;
 
     TestBuilder::new()
-        .command(r#"echo "${FOO:-5}""#)
-        .assert_stdout("5\n")
+        .command("echo a#b")
+        .assert_stdout("a#b\n")
         .run()
         .await;
 
     TestBuilder::new()
-        .command(r#"FOO=1 && echo ${FOO:-5}"#)
-        .assert_stdout("1\n")
+        .command("# comment\necho 'a # b' # comment && echo no\n# other comment")
+        .assert_stdout("a # b\n")
         .run()
         .await;
+}
 
+#[tokio::test]
+async fn variable_expansion() {
+    // DEFAULT VALUE EXPANSION
     TestBuilder::new()
-        .command(r#"FOO=1 && echo "${FOO:-5}""#)
+        .command("echo ${FOO:-5}")
+        .assert_stdout("5\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command(r#"echo "${FOO:-5}""#)
+        .assert_stdout("5\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command(r#"FOO=1 && echo ${FOO:-5}"#)
+        .assert_stdout("1\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command(r#"FOO=1 && echo "${FOO:-5}""#)
         .assert_stdout("1\n")
         .run()
         .await;
@@ -1304,6 +1715,18 @@ async fn variable_expansion() {
         .assert_stdout("23\n")
         .run()
         .await;
+    // LENGTH EXPANSION
+    TestBuilder::new()
+        .command("FOO=12345 && echo ${#FOO} ${#BAR}")
+        .assert_stdout("5 0\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command(r#"FOO="héllo" && echo "${#FOO}""#)
+        .assert_stdout("5\n")
+        .run()
+        .await;
 }
 
 #[tokio::test]
@@ -1408,7 +1831,8 @@ async fn test_set() {
         echo "hi"
         "#,
         )
-        .assert_stdout("+ echo hi\nhi\n")
+        .assert_stdout("hi\n")
+        .assert_stderr("+ echo hi\n")
         .run()
         .await;
 
@@ -1419,7 +1843,8 @@ async fn test_set() {
         echo "hi" && echo "This should be printed" || echo "This should not be printed"
         "#,
         )
-        .assert_stdout("+ echo hi\nhi\n+ echo This should be printed\nThis should be printed\n")
+        .assert_stdout("hi\nThis should be printed\n")
+        .assert_stderr("+ echo hi\n+ echo This should be printed\n")
         .run()
         .await;
 
@@ -1431,7 +1856,8 @@ async fn test_set() {
         echo $FOO
         "#,
         )
-        .assert_stdout("+ FOO=1\n+ echo 1\n1\n")
+        .assert_stdout("1\n")
+        .assert_stderr("+ FOO=1\n+ echo 1\n")
         .run()
         .await;
 
@@ -1445,7 +1871,8 @@ async fn test_set() {
         echo "This should be printed"
         "#,
         )
-        .assert_stdout("+ FOO=1\n+ echo 1\n1\n+ set +x\nThis should be printed\n")
+        .assert_stdout("1\nThis should be printed\n")
+        .assert_stderr("+ FOO=1\n+ echo 1\n+ set +x\n")
         .run()
         .await;
 
@@ -1455,7 +1882,1770 @@ async fn test_set() {
             echo $((10 + 20))
         "#,
         )
-        .assert_stdout("+ echo 30\n30\n")
+        .assert_stdout("30\n")
+        .assert_stderr("+ echo 30\n")
+        .run()
+        .await;
+}
+
+#[tokio::test]
+async fn exit_on_error_diagnostics() {
+    TestBuilder::new()
+        .command("echo 1; false; echo 2")
+        .shell_option(ShellOptions::ExitOnErrorDiagnostics, true)
+        .assert_exit_code(1)
+        .assert_stdout("1\n")
+        .assert_stderr("shell: exiting because `false` failed with exit code 1\n")
+        .run()
+        .await;
+
+    // the statement of the input is named, not the command inside of it
+    TestBuilder::new()
+        .command("if [[ 1 == 1 ]]; then exit_code 3; fi; echo 2")
+        .shell_option(ShellOptions::ExitOnErrorDiagnostics, true)
+        .custom_command(
+            "exit_code",
+            Box::new(|context| {
+                let code = context.args[0].parse().unwrap();
+                futures::future::ready(ExecuteResult::from_exit_code(code)).boxed_local()
+            }),
+        )
+        .assert_exit_code(3)
+        .assert_stderr(
+            "shell: exiting because `if [[ 1 == 1 ]]; then exit_code 3; fi` failed with exit code 3\n",
+        )
+        .run()
+        .await;
+
+    // quiet without the option
+    TestBuilder::new()
+        .command("echo 1; false; echo 2")
+        .assert_exit_code(1)
+        .assert_stdout("1\n")
+        .assert_stderr("")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("set +e; false; echo 2")
+        .shell_option(ShellOptions::ExitOnErrorDiagnostics, true)
+        .assert_exit_code(0)
+        .assert_stdout("2\n")
+        .assert_stderr("")
+        .run()
+        .await;
+}
+
+#[tokio::test]
+async fn trap_err() {
+    TestBuilder::new()
+        .command(r#"trap 'echo failed with $?' ERR; false"#)
+        .assert_stdout("failed with 1\n")
+        .assert_exit_code(1)
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command(r#"set +e; trap 'echo trapped' ERR; false; echo after"#)
+        .assert_stdout("trapped\nafter\n")
+        .run()
+        .await;
+
+    // not triggered inside conditions, boolean lists or negations
+    TestBuilder::new()
+        .command(
+            r#"set +e; trap 'echo trapped' ERR; false || echo recovered; false && echo no; ! true; echo done"#,
+        )
+        .assert_stdout("recovered\ndone\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command(r#"set +e; trap 'echo trapped' ERR; true && false; echo after"#)
+        .assert_stdout("trapped\nafter\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command(r#"set +e; trap 'echo trapped' ERR; if [[ 1 == 1 ]]; then false; fi"#)
+        .assert_stdout("trapped\n")
+        .assert_exit_code(1)
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command(r#"set +e; trap 'echo trapped' ERR; trap - ERR; false; echo after"#)
+        .assert_stdout("after\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command(r#"trap 'echo trapped' ERR; trap"#)
+        .assert_stdout("trap -- 'echo trapped' ERR\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command(r#"trap 'echo trapped' INT"#)
+        .assert_stderr("trap: unsupported condition: INT\n")
+        .assert_exit_code(1)
+        .run()
+        .await;
+}
+
+#[tokio::test]
+async fn declare_integer() {
+    TestBuilder::new()
+        .command("declare -i x; x=3+4; echo $x")
+        .assert_stdout("7\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("declare -i x; x=abc; echo $x")
+        .assert_stdout("0\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("y=5; declare -i x='y*2'; echo $x; x=x+1; echo $x")
+        .assert_stdout("10\n11\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("declare -i x; declare +i x; x=3+4; echo $x")
+        .assert_stdout("3+4\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("x=3+4; echo $x")
+        .assert_stdout("3+4\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("declare -i x; unset x; x=3+4; echo $x")
+        .assert_stdout("3+4\n")
+        .run()
+        .await;
+}
+
+#[tokio::test]
+async fn shell_keeps_state_between_lines() {
+    use deno_task_shell::pipe;
+    use deno_task_shell::Shell;
+    use deno_task_shell::ShellPipeWriter;
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let cwd = temp_dir.path().canonicalize().unwrap();
+    std::fs::create_dir(cwd.join("sub")).unwrap();
+    let mut shell = Shell::new(Default::default(), &cwd, Default::default());
+    let (stdout_reader, stdout) = pipe();
+    let stdout_handle = stdout_reader.pipe_to_string_handle();
+
+    let local_set = tokio::task::LocalSet::new();
+    local_set
+        .run_until(async {
+            for (line, expected_exit_code) in [
+                ("FOO=1", 0),
+                ("echo $FOO", 0),
+                ("cd sub", 0),
+                ("echo (", 2),
+                ("exit 3", 3),
+            ] {
+                let (stdin, _) = pipe();
+                let exit_code = shell
+                    .run_line_with_pipes(line, stdin, stdout.clone(), ShellPipeWriter::null())
+                    .await;
+                assert_eq!(exit_code, expected_exit_code, "\n\nFailed for: {}", line);
+            }
+        })
+        .await;
+    assert_eq!(shell.last_exit_code(), 3);
+    assert_eq!(shell.cwd(), cwd.join("sub"));
+    drop(stdout);
+    assert_eq!(stdout_handle.await.unwrap(), "1\n");
+}
+
+#[tokio::test]
+async fn command_not_found_hook() {
+    use deno_task_shell::execute_capture;
+    use deno_task_shell::parser::parse;
+    use deno_task_shell::ShellState;
+
+    let cwd = std::env::temp_dir().canonicalize().unwrap();
+    let mut state = ShellState::new(Default::default(), &cwd, Default::default());
+    state.set_command_not_found_hook(|name, args| {
+        (name == "foo" && args == ["bar"]).then(|| ExecuteResult::from_exit_code(0))
+    });
+
+    let local_set = tokio::task::LocalSet::new();
+    let output = local_set
+        .run_until(execute_capture(
+            parse("foo bar && echo handled").unwrap(),
+            state.clone(),
+        ))
+        .await;
+    assert_eq!(output.stdout, "handled\n");
+    assert_eq!(output.stderr, "");
+    assert_eq!(output.exit_code, 0);
+
+    let output = local_set
+        .run_until(execute_capture(parse("unknown_command").unwrap(), state))
+        .await;
+    assert_eq!(output.stderr, "unknown_command: command not found\n");
+    assert_eq!(output.exit_code, 127);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn command_wrapper() {
+    use std::collections::HashMap;
+
+    use deno_task_shell::execute_capture;
+    use deno_task_shell::parser::parse;
+    use deno_task_shell::CommandWrapper;
+    use deno_task_shell::ShellState;
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let cwd = temp_dir.path().canonicalize().unwrap();
+    // the script itself fails, so the output shows it was skipped
+    std::fs::write(cwd.join("wrapper"), "exit 1").unwrap();
+    let mut state = ShellState::new(
+        HashMap::from([("PATH".to_string(), cwd.display().to_string())]),
+        &cwd,
+        Default::default(),
+    );
+    state.set_command_wrapper(
+        cwd.join("wrapper"),
+        CommandWrapper {
+            executable: "/bin/sh".into(),
+            env_vars: HashMap::from([("WRAPPED".to_string(), "1".to_string())]),
+        },
+    );
+
+    let local_set = tokio::task::LocalSet::new();
+    let output = local_set
+        .run_until(execute_capture(
+            parse("wrapper -c 'echo wrapped $WRAPPED' && echo ${WRAPPED:-unset}").unwrap(),
+            state,
+        ))
+        .await;
+    assert_eq!(output.stdout, "wrapped 1\nunset\n");
+    assert_eq!(output.exit_code, 0);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn inherit_env() {
+    use deno_task_shell::execute_capture;
+    use deno_task_shell::parser::parse;
+    use deno_task_shell::EnvChange;
+    use deno_task_shell::ShellOptions;
+    use deno_task_shell::ShellState;
+
+    // only in the process environment, not in the one of the shell
+    std::env::set_var("SHELL_TEST_PROCESS_ONLY", "process");
+    let cwd = std::env::temp_dir().canonicalize().unwrap();
+    let env_vars = std::env::vars()
+        .filter(|(name, _)| name == "PATH")
+        .collect();
+    let mut state = ShellState::new(env_vars, &cwd, Default::default());
+    let command = "sh -c 'echo ${SHELL_TEST_PROCESS_ONLY:-unset}'";
+
+    let local_set = tokio::task::LocalSet::new();
+    let output = local_set
+        .run_until(execute_capture(parse(command).unwrap(), state.clone()))
+        .await;
+    assert_eq!(output.stdout, "unset\n");
+
+    state.apply_change(&EnvChange::SetShellOptions(ShellOptions::InheritEnv, true));
+    let output = local_set
+        .run_until(execute_capture(parse(command).unwrap(), state))
+        .await;
+    assert_eq!(output.stdout, "process\n");
+}
+
+#[tokio::test]
+async fn command_timeout() {
+    use std::time::Duration;
+    use std::time::Instant;
+
+    use deno_task_shell::execute_capture;
+    use deno_task_shell::parser::parse;
+    use deno_task_shell::ShellState;
+
+    let cwd = std::env::temp_dir().canonicalize().unwrap();
+
+    let local_set = tokio::task::LocalSet::new();
+    let mut commands = vec![("sleep 5", ""), ("echo fast && sleep 5", "fast\n")];
+    if cfg!(unix) {
+        // an external process is killed
+        commands.push(("/bin/sleep 5", ""));
+    }
+    for (command, expected_stdout) in commands {
+        let mut state = ShellState::new(std::env::vars().collect(), &cwd, Default::default());
+        state.set_command_timeout(Some(Duration::from_millis(100)));
+        let start = Instant::now();
+        let output = local_set
+            .run_until(execute_capture(parse(command).unwrap(), state))
+            .await;
+        assert_eq!(output.exit_code, 130, "\n\nFailed for: {}", command);
+        assert_eq!(
+            output.stdout, expected_stdout,
+            "\n\nFailed for: {}",
+            command
+        );
+        assert!(
+            start.elapsed() < Duration::from_secs(2),
+            "\n\nFailed for: {}\nTook {:?}",
+            command,
+            start.elapsed()
+        );
+    }
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn cancellation_kills_external_process() {
+    use std::time::Duration;
+    use std::time::Instant;
+
+    use deno_task_shell::execute_capture;
+    use deno_task_shell::parser::parse;
+    use deno_task_shell::ShellState;
+
+    let cwd = std::env::temp_dir().canonicalize().unwrap();
+    let state = ShellState::new(std::env::vars().collect(), &cwd, Default::default());
+    let token = state.token().clone();
+
+    let local_set = tokio::task::LocalSet::new();
+    let start = Instant::now();
+    let output = local_set
+        .run_until(async move {
+            let handle = tokio::task::spawn_local(execute_capture(
+                parse("/bin/sleep 5 && echo done").unwrap(),
+                state,
+            ));
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            token.cancel();
+            handle.await.unwrap()
+        })
+        .await;
+    assert_eq!(output.exit_code, 130);
+    assert_eq!(output.stdout, "");
+    // the captured stdout only closes once the child holding it has exited
+    assert!(
+        start.elapsed() < Duration::from_secs(2),
+        "Took {:?}",
+        start.elapsed()
+    );
+}
+
+#[tokio::test]
+async fn tr() {
+    TestBuilder::new()
+        .command("echo HELLO | tr A-Z a-z")
+        .assert_stdout("hello\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command(r#"echo "aabbcc" | tr -s a-c"#)
+        .assert_stdout("abc\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("echo 'a1b2c3' | tr -d '[:digit:]'")
+        .assert_stdout("abc\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("tr '[:lower:]' '[:upper:]'")
+        .stdin("some\ntext")
+        .assert_stdout("SOME\nTEXT")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("echo a | tr a")
+        .assert_stderr("tr: missing operand after 'a'\n")
+        .assert_exit_code(1)
+        .run()
+        .await;
+}
+
+#[tokio::test]
+async fn nl() {
+    TestBuilder::new()
+        .command("nl")
+        .stdin("a\n\nb\n")
+        .assert_stdout("     1\ta\n       \n     2\tb\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("nl -b a -s ': ' file")
+        .file("file", "a\n\nb\n")
+        .assert_stdout("     1: a\n     2: \n     3: b\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("nl missing")
+        .assert_stderr(&format!("nl: missing: {}\n", no_such_file_error_text()))
+        .assert_exit_code(1)
+        .run()
+        .await;
+}
+
+#[tokio::test]
+async fn xargs() {
+    TestBuilder::new()
+        .command("xargs echo")
+        .stdin("a\nb\n")
+        .assert_stdout("a b\n")
+        .run()
+        .await;
+
+    // defaults to echo
+    TestBuilder::new()
+        .command("echo 'a b' | xargs")
+        .assert_stdout("a b\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("xargs -0 echo")
+        .stdin("a b\0c")
+        .assert_stdout("a b c\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("xargs -n 2 echo 1")
+        .stdin("a b c d e")
+        .assert_stdout("1 a b\n1 c d\n1 e\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("xargs -I '{}' echo 'before-{}-after'")
+        .stdin("a b\n\n  c\n")
+        .assert_stdout("before-a b-after\nbefore-c-after\n")
+        .run()
+        .await;
+
+    // runs within the current shell state
+    TestBuilder::new()
+        .directory("sub")
+        .command("cd sub && echo a | xargs -I % pwd")
+        .assert_stdout(&format!("$TEMP_DIR{FOLDER_SEPARATOR}sub\n"))
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("echo a b | xargs -n 1 false")
+        .assert_exit_code(1)
+        .run()
+        .await;
+}
+
+#[tokio::test]
+async fn find() {
+    TestBuilder::new()
+        .directory("sub/deeper")
+        .file("a.txt", "")
+        .file("b.md", "")
+        .file("sub/c.txt", "")
+        .file("sub/deeper/d.txt", "")
+        .command("find . -name '*.txt'")
+        .assert_stdout("./a.txt\n./sub/c.txt\n./sub/deeper/d.txt\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .directory("sub/deeper")
+        .file("a.txt", "")
+        .file("sub/c.txt", "")
+        .file("sub/deeper/d.txt", "")
+        .command("find sub -type d")
+        .assert_stdout("sub\nsub/deeper\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .directory("sub")
+        .file("a.txt", "")
+        .file("sub/c.txt", "")
+        .command("find . -maxdepth 1 -type f")
+        .assert_stdout("./a.txt\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .directory("sub")
+        .command("cd sub && find")
+        .assert_stdout(".\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .directory("sub")
+        .command("find -- . -maxdepth 0 && find . --")
+        .assert_stdout(".\n")
+        .assert_stderr("find: unknown predicate '--'\n")
+        .assert_exit_code(1)
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("find missing")
+        .assert_stderr(&format!("find: 'missing': {}\n", no_such_file_error_text()))
+        .assert_exit_code(1)
+        .run()
+        .await;
+}
+
+#[tokio::test]
+async fn noclobber() {
+    TestBuilder::new()
+        .file("existing.txt", "original\n")
+        .command("set -o noclobber && echo x > existing.txt")
+        .assert_stderr("existing.txt: cannot overwrite existing file\n")
+        .assert_file_equals("existing.txt", "original\n")
+        .assert_exit_code(1)
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .file("existing.txt", "original\n")
+        .command("set -o noclobber && echo x >| existing.txt && echo y >> existing.txt")
+        .assert_file_equals("existing.txt", "x\ny\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("set -C && echo x > new.txt")
+        .assert_file_equals("new.txt", "x\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .file("existing.txt", "original\n")
+        .command("set -C && set +o noclobber && echo x > existing.txt")
+        .assert_file_equals("existing.txt", "x\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("set -C && echo x > /dev/null")
+        .assert_exit_code(0)
+        .run()
+        .await;
+}
+
+#[tokio::test]
+#[cfg(unix)]
+async fn file_size_and_symlink_tests() {
+    TestBuilder::new()
+        .file("empty.txt", "")
+        .file("full.txt", "content")
+        .command(r#"if [[ -s full.txt ]]; then echo "full"; fi && if [[ ! -s empty.txt ]]; then echo "empty"; fi && if [[ ! -s missing.txt ]]; then echo "missing"; fi"#)
+        .assert_stdout("full\nempty\nmissing\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .file("full.txt", "content")
+        .command(r#"ln -s full.txt link.txt && if [[ -L link.txt ]]; then echo "link"; fi && if [[ -h link.txt ]]; then echo "link"; fi && if [[ ! -L full.txt ]]; then echo "file"; fi"#)
+        .assert_stdout("link\nlink\nfile\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .ensure_temp_dir()
+        .command(r#"ln -s missing.txt dangling.txt && if [[ -L dangling.txt && ! -e dangling.txt ]]; then echo "dangling"; fi"#)
+        .assert_stdout("dangling\n")
+        .run()
+        .await;
+}
+
+#[tokio::test]
+async fn variable_set_tests() {
+    TestBuilder::new()
+        .command(r#"FOO=1; if [[ -v FOO ]]; then echo yes; fi"#)
+        .assert_stdout("yes\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command(r#"if [[ -v BAR ]]; then echo yes; else echo no; fi"#)
+        .assert_stdout("no\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .env_var("BAZ", "")
+        .command(r#"if [[ -v BAZ ]]; then echo set; fi"#)
+        .assert_stdout("set\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command(r#"FOO=1; if [[ ! -R FOO ]]; then echo "not a reference"; fi"#)
+        .assert_stdout("not a reference\n")
+        .run()
+        .await;
+}
+
+#[tokio::test]
+async fn time() {
+    TestBuilder::new()
+        .command("time true")
+        .assert_stderr_contains("\nreal\t0m0.")
+        .assert_exit_code(0)
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("time echo 1 && time exit 3")
+        .assert_stdout("1\n")
+        .assert_stderr_contains("real\t")
+        .assert_exit_code(3)
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("time cd sub_dir && pwd")
+        .directory("sub_dir")
+        .assert_stdout(&format!("$TEMP_DIR{FOLDER_SEPARATOR}sub_dir\n"))
+        .assert_stderr_contains("real\t")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("time true")
+        .assert_stderr_contains("s\nuser\t0m")
+        .run()
+        .await;
+}
+
+#[tokio::test]
+async fn time_format() {
+    // only the real time in seconds, ex. `0.001`
+    TestBuilder::new()
+        .command("TIMEFORMAT='%R'; time true")
+        .assert_stderr_contains("0.")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("TIMEFORMAT='%0R'; time true")
+        .assert_stderr("0\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("TIMEFORMAT='took %0lR'; time true")
+        .assert_stderr("took 0m0s\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("TIMEFORMAT=''; time echo 1")
+        .assert_stdout("1\n")
+        .assert_stderr("")
+        .run()
+        .await;
+}
+
+#[tokio::test]
+async fn alias() {
+    TestBuilder::new()
+        .command("alias ll=\"ls -al\" && alias gs=\"git status\" && alias")
+        .assert_stdout("alias gs='git status'\nalias ll='ls -al'\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("alias ll=\"ls -al\" && alias ll")
+        .assert_stdout("alias ll='ls -al'\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("alias ll")
+        .assert_stderr("alias: ll: not found\n")
+        .assert_exit_code(1)
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command(r#"alias ll="ls -al" la="ls -a" && alias la && alias ll"#)
+        .assert_stdout("alias la='ls -a'\nalias ll='ls -al'\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command(r#"alias one="echo 1 2" two="echo 'a  b'" && one && two x"#)
+        .assert_stdout("1 2\na  b x\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command(r#"alias quoted="echo 'a'" && alias quoted"#)
+        .assert_stdout("alias quoted='echo '\\''a'\\'''\n")
+        .run()
+        .await;
+
+    // aliases are expanded recursively
+    TestBuilder::new()
+        .command(r#"alias a=b && alias b="echo hi" && a && a there"#)
+        .assert_stdout("hi\nhi there\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command(r#"alias echo="echo prefix" && echo hi"#)
+        .assert_stdout("prefix hi\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command(r#"alias a=b && alias b=a && a"#)
+        .assert_stderr("a: command not found\n")
+        .assert_exit_code(127)
+        .run()
+        .await;
+
+    // a trailing space expands the next word too
+    TestBuilder::new()
+        .command(r#"alias please="echo " && alias greet="hi there" && please greet greet"#)
+        .assert_stdout("hi there greet\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command(r#"alias say=echo && alias greet="hi there" && say greet"#)
+        .assert_stdout("greet\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command(r#"alias please="echo " && alias sudo="please " && alias greet=hi && sudo greet"#)
+        .assert_stdout("hi\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command(r#"alias nothing="" && alias greet="echo hi" && nothing greet"#)
+        .assert_stdout("hi\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("alias ll=\"ls -al\" && unalias ll && alias")
+        .assert_stdout("")
+        .run()
+        .await;
+}
+
+#[tokio::test]
+async fn source() {
+    TestBuilder::new()
+        .file("script.sh", "export FOO=1\nalias x=echo\nBAR=2\necho sourced\n")
+        .directory("sub")
+        .file("sub/cd.sh", "cd ..\n")
+        .command("source script.sh | cat && source script.sh && x $FOO $BAR && cd sub && source cd.sh && pwd")
+        .assert_stdout("sourced\nsourced\n1 2\n$TEMP_DIR\n")
+        .run()
+        .await;
+}
+
+#[tokio::test]
+async fn dynamic_variables() {
+    TestBuilder::new()
+        .command(r#"A=$RANDOM; B=$RANDOM; C=$RANDOM; if [[ $A == $B && $B == $C ]]; then echo same; else echo differ; fi"#)
+        .assert_stdout("differ\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command(r#"A=$RANDOM; if [[ $A -ge 0 && $A -le 32767 ]]; then echo "in range"; fi"#)
+        .assert_stdout("in range\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command(r#"if [[ $SECONDS -ge 0 && $SECONDS -lt 5 ]]; then echo "started"; fi"#)
+        .assert_stdout("started\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command(
+            r#"SECONDS=100; if [[ $SECONDS -ge 100 && $SECONDS -lt 105 ]]; then echo "reset"; fi"#,
+        )
+        .assert_stdout("reset\n")
+        .run()
+        .await;
+}
+
+#[tokio::test]
+async fn lineno_and_script_name() {
+    TestBuilder::new()
+        .command("echo $LINENO\n\necho ${LINENO} \"$LINENO\"")
+        .assert_stdout("1\n3 3\n")
+        .run()
+        .await;
+
+    // an assigned value is used instead of the line
+    TestBuilder::new()
+        .command("LINENO=10\necho $LINENO")
+        .assert_stdout("10\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command(r#"echo \$0 '$0'"#)
+        .assert_stdout("$0 $0\n")
+        .run()
+        .await;
+}
+
+#[tokio::test]
+async fn ifs_word_splitting() {
+    let cases = [
+        (r#"P=a:b:c; IFS=: ; args $P"#, "[a][b][c]\n"),
+        (r#"P=a:b:c; IFS=: ; args "$P""#, "[a:b:c]\n"),
+        (
+            r#"P='a,b'; IFS=',' ; args $P $(echo 'c,d')"#,
+            "[a][b][c][d]\n",
+        ),
+        (r#"P='a::b:'; IFS=: ; args $P"#, "[a][][b]\n"),
+        (r#"P='a  b'; args $P "$P""#, "[a][b][a  b]\n"),
+        (r#"P=' a : b '; IFS=': ' ; args $P"#, "[a][b]\n"),
+        (
+            r#"args $(echo a; echo b) "$(echo a; echo b)""#,
+            "[a][b][a\nb]\n",
+        ),
+        (r#"P='a b'; IFS='' ; args $P"#, "[a b]\n"),
+    ];
+    assert_args_output(&cases).await;
+}
+
+#[tokio::test]
+async fn split_field_concatenation() {
+    let cases = [
+        (r#"x='a b'; args pre${x}post"#, "[prea][bpost]\n"),
+        (r#"x='a'; args pre${x}post"#, "[preapost]\n"),
+        (r#"x=' a b '; args pre${x}post"#, "[pre][a][b][post]\n"),
+        (
+            r#"x='a b'; args "pre"$x"post" pre$x$x"#,
+            "[prea][bpost][prea][ba][b]\n",
+        ),
+        (r#"x=' '; args pre${x}post"#, "[pre][post]\n"),
+        (r#"x=''; args pre${x}post"#, "[prepost]\n"),
+        (r#"x='a:b:'; IFS=: ; args pre${x}post"#, "[prea][b][post]\n"),
+        (r#"args pre$(echo a b)post"#, "[prea][bpost]\n"),
+        (
+            r#"x=':a'; IFS=: ; args pre${x}post $x"#,
+            "[pre][apost][][a]\n",
+        ),
+        (
+            r#"x=' :a'; IFS=': ' ; args pre${x}post $x"#,
+            "[pre][apost][][a]\n",
+        ),
+    ];
+    assert_args_output(&cases).await;
+}
+
+/// Runs each command with an `args` command printing its arguments in
+/// brackets (ex. `[a][b]`), asserting the output.
+#[cfg(test)]
+async fn assert_args_output(cases: &[(&str, &str)]) {
+    for (command, expected) in cases.iter().copied() {
+        TestBuilder::new()
+            .command(command)
+            .custom_command(
+                "args",
+                Box::new(|mut context| {
+                    async move {
+                        let text = context
+                            .args
+                            .iter()
+                            .map(|arg| format!("[{arg}]"))
+                            .collect::<String>();
+                        let _ = context.stdout.write_line(&text);
+                        ExecuteResult::from_exit_code(0)
+                    }
+                    .boxed_local()
+                }),
+            )
+            .assert_stdout(expected)
+            .run()
+            .await;
+    }
+}
+
+#[tokio::test]
+async fn kill() {
+    // the job would keep the test running for 30 seconds if not killed
+    TestBuilder::new()
+        .command("sleep 30 & kill %1 && echo killed")
+        .assert_stdout("killed\n")
+        .assert_exit_code(143)
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("sleep 30 & kill -0 %1 && kill -s KILL %%")
+        .assert_exit_code(137)
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("kill %1")
+        .assert_stderr("kill: %1: no such job\n")
+        .assert_exit_code(1)
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("kill -l 143; kill -l INT")
+        .assert_stdout("TERM\n2\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("kill -FOO %1")
+        .assert_stderr("kill: FOO: invalid signal specification\n")
+        .assert_exit_code(1)
+        .run()
+        .await;
+
+    // jobs can only be stopped, other signals are rejected
+    TestBuilder::new()
+        .command("set +e; sleep 30 & kill -HUP %1; echo $?; kill -INT %1; kill %1")
+        .assert_stdout("1\n")
+        .assert_stderr(
+            "kill: %1: HUP: signal can't be sent to a job\n\
+             kill: %1: INT: signal can't be sent to a job\n",
+        )
+        .assert_exit_code(143)
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("kill --")
+        .assert_stderr(
+            "kill: usage: kill [-s sigspec | -n signum | -sigspec] pid | jobspec ... or kill -l [sigspec]\n",
+        )
+        .assert_exit_code(2)
+        .run()
+        .await;
+
+    #[cfg(unix)]
+    TestBuilder::new()
+        .command("/bin/sleep 30 & kill %1")
+        .assert_exit_code(143)
+        .run()
+        .await;
+}
+
+#[tokio::test]
+async fn no_exec() {
+    TestBuilder::new()
+        .file("file.txt", "text\n")
+        .command("set -n; rm -rf file.txt; echo 1 > new.txt; x=1")
+        .assert_exists("file.txt")
+        .assert_not_exists("new.txt")
+        .assert_stdout("")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("set -n && echo $(echo 1) && set +n && echo 2")
+        .assert_stdout("2\n")
+        .run()
+        .await;
+
+    // `set +n` and `exit` still run so the shell can leave noexec
+    TestBuilder::new()
+        .command("set +e; set -n; set +n; echo hi; exit 3")
+        .assert_stdout("hi\n")
+        .assert_exit_code(3)
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("set -n; exit 4; echo unreachable")
+        .assert_exit_code(4)
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("set -x; set -o noexec; echo \"$HOME\" > out.txt; x=$(pwd)")
+        .assert_stderr("+ set -o noexec\n+ echo \"$HOME\" > out.txt\n+ x=$(pwd)\n")
+        .assert_not_exists("out.txt")
+        .run()
+        .await;
+}
+
+#[tokio::test]
+async fn no_exec_is_ignored_when_interactive() {
+    use deno_task_shell::execute_capture;
+    use deno_task_shell::parser::parse;
+    use deno_task_shell::EnvChange;
+    use deno_task_shell::ShellOptions;
+    use deno_task_shell::ShellState;
+
+    let cwd = std::env::temp_dir().canonicalize().unwrap();
+    let mut state = ShellState::new(Default::default(), &cwd, Default::default());
+    state.apply_change(&EnvChange::SetShellOptions(ShellOptions::NoExec, true));
+    state.set_interactive(true);
+
+    let local_set = tokio::task::LocalSet::new();
+    let output = local_set
+        .run_until(execute_capture(parse("echo hi").unwrap(), state))
+        .await;
+    assert_eq!(output.stdout, "hi\n");
+}
+
+#[tokio::test]
+async fn trace_prefix_and_redirects() {
+    TestBuilder::new()
+        .command("PS4='> '; set -x; echo hi")
+        .assert_stdout("hi\n")
+        .assert_stderr("> echo hi\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("set -x; FILE=file.txt; echo hi > \"$FILE\"; cat < file.txt")
+        .assert_stdout("hi\n")
+        .assert_stderr("+ FILE=file.txt\n+ echo hi > file.txt\n+ cat < file.txt\n")
+        .assert_file_equals("file.txt", "hi\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("set -x; (echo 1; (echo 2))")
+        .assert_stdout("1\n2\n")
+        .assert_stderr("++ echo 1\n+++ echo 2\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("PS4=''; set -x; echo hi")
+        .assert_stdout("hi\n")
+        .assert_stderr("echo hi\n")
+        .run()
+        .await;
+
+    // the trace isn't captured by a command substitution
+    TestBuilder::new()
+        .command(r#"x=$(set -x; echo hi); echo "[$x]""#)
+        .assert_stdout("[hi]\n")
+        .assert_stderr("++ echo hi\n")
+        .run()
+        .await;
+}
+
+#[tokio::test]
+async fn printenv() {
+    TestBuilder::new()
+        .env_var("PATH", "/usr/bin")
+        .command("printenv PATH")
+        .assert_stdout("/usr/bin\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("printenv NOPE")
+        .assert_exit_code(1)
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("LOCAL=1; export EXPORTED=2; printenv EXPORTED LOCAL")
+        .assert_stdout("2\n")
+        .assert_exit_code(1)
+        .run()
+        .await;
+}
+
+#[tokio::test]
+async fn null_and_unset_modifiers() {
+    TestBuilder::new()
+        .command(r#"VAR=''; echo "[${VAR+x}]" "[${VAR:+x}]" "[${NOPE+x}]""#)
+        .assert_stdout("[x] [] []\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command(r#"VAR=''; echo "[${VAR-d}]" "[${VAR:-d}]" "[${NOPE-d}]""#)
+        .assert_stdout("[] [d] [d]\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command(r#"A=''; B=''; echo "[${A=a}]" "[${B:=b}]" "[${C=c}]"; echo "[$A]" "[$B]" "[$C]""#)
+        .assert_stdout("[] [b] [c]\n[] [b] [c]\n")
+        .run()
+        .await;
+}
+
+#[tokio::test]
+async fn verbose() {
+    TestBuilder::new()
+        .command("set -v; echo hi")
+        .assert_stdout("hi\n")
+        .assert_stderr("echo hi\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command(
+            "set -o verbose; if [[ a == a ]]; then echo 1; fi; (echo 2); echo $(echo 3); set +v; echo 4",
+        )
+        .assert_stdout("1\n2\n3\n4\n")
+        .assert_stderr("if [[ a == a ]]; then echo 1; fi\n(echo 2)\necho $(echo 3)\nset +v\n")
+        .run()
+        .await;
+
+    // items are printed as written
+    TestBuilder::new()
+        .command("set -v;  echo   \"a\"  $X; sleep 0&\necho b &&echo c # note")
+        .assert_stdout("a\nb\nc\n")
+        .assert_stderr("echo   \"a\"  $X\nsleep 0 &\necho b &&echo c # note\n")
+        .run()
+        .await;
+}
+
+#[tokio::test]
+#[cfg(unix)]
+async fn hash() {
+    let mut builder = TestBuilder::new();
+    let bin_dir = builder.temp_dir_path().join("bin");
+    builder
+        .env_var("PATH", &format!("{}:/usr/bin:/bin", bin_dir.display()))
+        .directory("bin")
+        .file("bin/tool", "#!/bin/sh\necho tool\n")
+        .command(
+            "chmod +x bin/tool && hash -r && tool && tool && hash && hash -r && hash && tool && hash",
+        )
+        .assert_stdout(&format!(
+            "tool\ntool\nhits\tcommand\n   1\t{0}/tool\nhash: hash table empty\ntool\nhits\tcommand\n   0\t{0}/tool\n",
+            bin_dir.display()
+        ))
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("hash -r && hash cd && hash no_such_command")
+        .assert_stderr("hash: no_such_command: not found\n")
+        .assert_exit_code(1)
+        .run()
+        .await;
+}
+
+#[tokio::test]
+async fn exec() {
+    // redirects without a command apply to the rest of the shell
+    TestBuilder::new()
+        .command("exec > out.txt; echo 1; echo 2")
+        .assert_stdout("")
+        .assert_file_equals("out.txt", "1\n2\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("exec 2> err.txt; cat missing.txt || echo failed")
+        .assert_stdout("failed\n")
+        .assert_file_equals(
+            "err.txt",
+            "cat: missing.txt: No such file or directory (os error 2)\n",
+        )
+        .run()
+        .await;
+
+    // within a subshell the redirect only applies to the subshell
+    TestBuilder::new()
+        .command("(exec > out.txt; echo 1); echo 2")
+        .assert_stdout("2\n")
+        .assert_file_equals("out.txt", "1\n")
+        .run()
+        .await;
+
+    // the shell exits with the exit code of the command
+    TestBuilder::new()
+        .command("exec echo 1; echo 2")
+        .assert_stdout("1\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("exec cat missing.txt; echo 2")
+        .assert_stderr("cat: missing.txt: No such file or directory (os error 2)\n")
+        .assert_exit_code(1)
+        .run()
+        .await;
+
+    // a pipeline stage or a background job only ends itself
+    #[cfg(unix)]
+    TestBuilder::new()
+        .command("exec /bin/echo hi | /bin/cat; echo after")
+        .assert_stdout("hi\nafter\n")
+        .run()
+        .await;
+    #[cfg(unix)]
+    TestBuilder::new()
+        .command("exec /bin/echo job & sleep 0.2; echo after")
+        .assert_stdout("job\nafter\n")
+        .run()
+        .await;
+}
+
+#[tokio::test]
+async fn exec_redirects_the_shell_stdout() {
+    use deno_task_shell::pipe;
+    use deno_task_shell::Shell;
+    use deno_task_shell::ShellPipeWriter;
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let cwd = temp_dir.path().canonicalize().unwrap();
+    let mut shell = Shell::new(Default::default(), &cwd, Default::default());
+    let (stdout_reader, stdout) = pipe();
+    let stdout_handle = stdout_reader.pipe_to_string_handle();
+
+    let local_set = tokio::task::LocalSet::new();
+    local_set
+        .run_until(async {
+            for line in ["echo 1", "exec > out.txt", "echo 2", "echo 3"] {
+                let (stdin, _) = pipe();
+                let exit_code = shell
+                    .run_line_with_pipes(line, stdin, stdout.clone(), ShellPipeWriter::null())
+                    .await;
+                assert_eq!(exit_code, 0, "\n\nFailed for: {}", line);
+            }
+        })
+        .await;
+    drop(stdout);
+    assert_eq!(stdout_handle.await.unwrap(), "1\n");
+    assert_eq!(
+        std::fs::read_to_string(cwd.join("out.txt")).unwrap(),
+        "2\n3\n"
+    );
+}
+
+#[tokio::test]
+#[cfg(unix)]
+async fn file_comparison_tests() {
+    TestBuilder::new()
+        .file("old.txt", "")
+        .file("new.txt", "")
+        .command(r#"touch -d 2000-01-01 old.txt && if [[ new.txt -nt old.txt ]]; then echo "newer"; fi && if [[ old.txt -ot new.txt ]]; then echo "older"; fi && if [[ ! old.txt -nt new.txt && ! new.txt -ot old.txt ]]; then echo "ok"; fi"#)
+        .assert_stdout("newer\nolder\nok\n")
+        .run()
+        .await;
+
+    // a missing file is older than an existing one
+    TestBuilder::new()
+        .file("file.txt", "")
+        .command(r#"if [[ file.txt -nt missing.txt && missing.txt -ot file.txt ]]; then echo "missing"; fi && if [[ ! missing.txt -nt other.txt && ! missing.txt -ot other.txt ]]; then echo "both"; fi"#)
+        .assert_stdout("missing\nboth\n")
+        .run()
+        .await;
+
+    // numeric names are still compared as files
+    TestBuilder::new()
+        .file("1", "")
+        .file("2", "")
+        .command(r#"touch -d 2000-01-01 2 && if [[ 1 -nt 2 ]]; then echo "newer"; fi"#)
+        .assert_stdout("newer\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .file("file.txt", "")
+        .file("other.txt", "")
+        .command(r#"ln -s file.txt link.txt && if [[ link.txt -ef file.txt ]]; then echo "same"; fi && if [[ ! file.txt -ef other.txt && ! file.txt -ef missing.txt ]]; then echo "different"; fi"#)
+        .assert_stdout("same\ndifferent\n")
+        .run()
+        .await;
+}
+
+#[tokio::test]
+async fn process_id() {
+    let pid = std::process::id();
+    TestBuilder::new()
+        .command("echo $$ ${$} $BASHPID; (echo $$ $BASHPID); echo $(echo $$)")
+        .assert_stdout(&format!("{pid} {pid} {pid}\n{pid} {pid}\n{pid}\n"))
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command(r#"A=$$; if [[ $A -gt 0 && $A == $$ ]]; then echo "stable"; fi"#)
+        .assert_stdout("stable\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("echo '$$'")
+        .assert_stdout("$$\n")
+        .run()
+        .await;
+}
+
+#[tokio::test]
+#[cfg(unix)]
+async fn stat() {
+    TestBuilder::new()
+        .file("file.txt", "hello")
+        .command("stat -c '%s' file.txt")
+        .assert_stdout("5\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .file("file.txt", "")
+        .directory("dir")
+        .command("chmod 640 file.txt && chmod 750 dir && stat --format='%n: %F %a' file.txt dir")
+        .assert_stdout("file.txt: regular empty file 640\ndir: directory 750\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("stat -c '%s' missing.txt")
+        .assert_stderr(&format!(
+            "stat: cannot stat 'missing.txt': {}\n",
+            no_such_file_error_text()
+        ))
+        .assert_exit_code(1)
+        .run()
+        .await;
+}
+
+#[tokio::test]
+async fn command_substitution_exit_code() {
+    TestBuilder::new()
+        .command("set -e; x=$(false); echo after")
+        .assert_stdout("")
+        .assert_exit_code(1)
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("set +e; x=$(exit 3); echo $?; $(false); echo $?; x=$(true); echo $?")
+        .assert_stdout("3\n1\n0\n")
+        .run()
+        .await;
+
+    // the exit code of a command with arguments is its own
+    TestBuilder::new()
+        .command("echo $(false); echo $?; x=$(false) || echo failed $?")
+        .assert_stdout("\n0\nfailed 1\n")
+        .run()
+        .await;
+}
+
+#[tokio::test]
+async fn pwd_assignment() {
+    TestBuilder::new()
+        .directory("sub_dir")
+        .command("PWD=$PWD/sub_dir && pwd && echo $PWD")
+        .assert_stdout(&format!(
+            "$TEMP_DIR{FOLDER_SEPARATOR}sub_dir\n$TEMP_DIR{FOLDER_SEPARATOR}sub_dir\n"
+        ))
+        .run()
+        .await;
+
+    // relative, missing and non-directory paths are rejected
+    TestBuilder::new()
+        .directory("sub_dir")
+        .file("file.txt", "")
+        .command("PWD=sub_dir; PWD=$PWD/missing; PWD=$PWD/file.txt; pwd && echo $PWD")
+        .assert_stdout("$TEMP_DIR\n$TEMP_DIR\n")
+        .run()
+        .await;
+
+    // assigning after unsetting keeps the variable in sync
+    TestBuilder::new()
+        .directory("sub_dir")
+        .command("unset PWD; PWD=sub_dir; pwd")
+        .assert_stdout("$TEMP_DIR\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .directory("sub_dir")
+        .command("OLDPWD=$PWD/sub_dir; echo $OLDPWD; OLDPWD=sub_dir; echo $OLDPWD")
+        .assert_stdout(&format!(
+            "$TEMP_DIR{FOLDER_SEPARATOR}sub_dir\n$TEMP_DIR{FOLDER_SEPARATOR}sub_dir\n"
+        ))
+        .run()
+        .await;
+}
+
+#[tokio::test]
+async fn async_exit_status() {
+    // starting the job succeeds, but the list still waits for it at the
+    // end and reports its failure
+    TestBuilder::new()
+        .command("false & echo $?")
+        .assert_stdout("0\n")
+        .assert_exit_code(1)
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("set +e; false; true & echo $?")
+        .assert_stdout("0\n")
+        .run()
+        .await;
+}
+
+#[tokio::test]
+async fn brace_groups() {
+    // unlike subshells, brace groups run in the current shell
+    TestBuilder::new()
+        .directory("sub_dir")
+        .command("{ cd sub_dir; export A=1; } && pwd && echo $A")
+        .assert_stdout(&format!("$TEMP_DIR{FOLDER_SEPARATOR}sub_dir\n1\n"))
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .directory("sub_dir")
+        .command("(cd sub_dir; export A=1) && pwd && echo $A")
+        .assert_stdout("$TEMP_DIR\n\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("{ echo 1; echo 2; } > out.txt && { false || echo 3; }")
+        .assert_stdout("3\n")
+        .assert_file_equals("out.txt", "1\n2\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("{ exit 3; }; echo unreachable")
+        .assert_exit_code(3)
+        .run()
+        .await;
+}
+
+#[tokio::test]
+async fn compound_command_redirects() {
+    TestBuilder::new()
+        .command(r#"if [[ 1 == 1 ]]; then echo a; echo b; fi > out.txt"#)
+        .assert_stdout("")
+        .assert_file_equals("out.txt", "a\nb\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command(r#"(echo out; echo err 1>&2) &> all.txt && (echo err 1>&2) 2> err.txt"#)
+        .assert_stdout("")
+        .assert_stderr("")
+        .assert_file_equals("all.txt", "out\nerr\n")
+        .assert_file_equals("err.txt", "err\n")
+        .run()
+        .await;
+}
+
+#[tokio::test]
+async fn command_substitution_lists() {
+    // `set -e` doesn't apply inside the substitution
+    TestBuilder::new()
+        .command("echo $(false; echo ok); echo $?")
+        .assert_stdout("ok\n0\n")
+        .run()
+        .await;
+
+    // the exit code is the one of the last command
+    TestBuilder::new()
+        .command("x=$(false\necho ok); echo $x $?; x=$(echo ok; false) || echo failed")
+        .assert_stdout("ok 0\nfailed\n")
+        .run()
+        .await;
+
+    // exiting only ends the substitution
+    TestBuilder::new()
+        .command("echo $(exit 3; echo unreachable) after")
+        .assert_stdout("after\n")
+        .run()
+        .await;
+}
+
+#[tokio::test]
+async fn source_exit() {
+    TestBuilder::new()
+        .file("exit.sh", "echo before\nexit 0\necho unreachable\n")
+        .file("fail.sh", "exit 3\n")
+        .command("source exit.sh; echo after $?; source fail.sh || echo failed $?; exit 4; echo unreachable")
+        .assert_stdout("before\nafter 0\nfailed 3\n")
+        .assert_exit_code(4)
+        .run()
+        .await;
+
+    // the changes made before `exit` still apply
+    TestBuilder::new()
+        .directory("sub_dir")
+        .file("rc.sh", "export A=1; cd sub_dir && exit 0; export B=1\n")
+        .command("source rc.sh; echo $A $B; pwd")
+        .assert_stdout(&format!("1\n$TEMP_DIR{FOLDER_SEPARATOR}sub_dir\n"))
+        .run()
+        .await;
+
+    // an `exit` nested in the file or in a subshell of it
+    TestBuilder::new()
+        .file(
+            "nested.sh",
+            "(exit 1) || echo subshell\nif [[ 1 == 1 ]]; then A=1; { exit 5; }; fi\necho unreachable\n",
+        )
+        .command("source nested.sh || echo $? $A")
+        .assert_stdout("subshell\n5 1\n")
+        .run()
+        .await;
+}
+
+#[tokio::test]
+async fn bash_source() {
+    TestBuilder::new()
+        .directory("lib")
+        .file("lib/inner.sh", "echo inner $BASH_SOURCE\n")
+        .file(
+            "lib/outer.sh",
+            "echo outer ${BASH_SOURCE[0]} $(dirname \"${BASH_SOURCE[0]}\")\nsource lib/inner.sh\necho outer $BASH_SOURCE\n",
+        )
+        .command("echo top \"$BASH_SOURCE\"; source lib/outer.sh; echo top \"$BASH_SOURCE\"")
+        .assert_stdout("top \nouter lib/outer.sh lib\ninner lib/inner.sh\nouter lib/outer.sh\ntop \n")
+        .run()
+        .await;
+}
+
+#[tokio::test]
+async fn glob_options() {
+    TestBuilder::new()
+        .file(".hidden", "")
+        .file("visible", "")
+        .command("echo *; shopt -s dotglob; echo *; shopt -u dotglob; echo *; shopt dotglob")
+        .assert_stdout("visible\n.hidden visible\nvisible\ndotglob\toff\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .directory("sub_dir/sub")
+        .file("sub_dir/sub/1.txt", "1\n")
+        .file("sub_dir/2.txt", "2\n")
+        .file("3.txt", "3\n")
+        .command("cat **/*.txt; shopt -u globstar; cat **/*.txt")
+        .assert_stdout("3\n2\n1\n2\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("shopt -s failglob")
+        .assert_stderr("shopt: failglob: invalid shell option name\n")
+        .assert_exit_code(1)
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .file("a.txt", "")
+        .command("shopt -s nullglob; echo *.rs a*; echo *.rs | wc -c")
+        .assert_stdout("a.txt\n1\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .file("Upper.txt", "")
+        .command("echo u*; shopt -u nocaseglob; echo U*; echo u* || echo failed")
+        .assert_stdout("Upper.txt\nUpper.txt\nfailed\n")
+        .assert_stderr(&format!(
+            "glob: no matches found '$TEMP_DIR{FOLDER_SEPARATOR}u*'\n"
+        ))
+        .run()
+        .await;
+}
+
+#[tokio::test]
+async fn extended_globs() {
+    TestBuilder::new()
+        .file("a.txt", "")
+        .file("b.txt", "")
+        .file("c.txt", "")
+        .file("main.rs", "")
+        .file(".hidden", "")
+        .directory("sub")
+        .command(
+            "shopt -s extglob; echo !(*.txt); echo @(a|b).txt; echo +([a-b]).txt \"@(a)\" @\\(a\\); \
+             if [[ main.rs == !(*.txt) ]]; then echo matched; fi; \
+             if [[ a.txt == !(*.txt) ]]; then echo matched; else echo not matched; fi",
+        )
+        .assert_stdout("main.rs sub\na.txt b.txt\na.txt b.txt @(a) @(a)\nmatched\nnot matched\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("echo @(a|b).txt; if [[ a == @(a|b) ]]; then echo matched; else echo literal; fi")
+        .assert_stdout("@(a|b).txt\nliteral\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .file("a.txt", "")
+        .file("c.txt", "")
+        .command(
+            "shopt -s extglob; x=a; if [[ a.txt == @($x|b).txt ]]; then echo matched; fi; \
+             echo @(${x}|c).txt",
+        )
+        .assert_stdout("matched\na.txt c.txt\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .file("a.txt", "")
+        .command("shopt -s extglob; echo @(missing)")
+        .assert_stderr("glob: no matches found '$TEMP_DIR/@(missing)'\n")
+        .assert_exit_code(1)
+        .run()
+        .await;
+}
+
+#[tokio::test]
+async fn cd_home() {
+    TestBuilder::new()
+        .directory("home/sub")
+        .command(r#"export HOME="$PWD/home"; cd; pwd; cd /; cd ~; pwd; cd ~/sub; pwd"#)
+        .assert_stdout(&format!(
+            "$TEMP_DIR{0}home\n$TEMP_DIR{0}home\n$TEMP_DIR{0}home{0}sub\n",
+            FOLDER_SEPARATOR
+        ))
+        .run()
+        .await;
+
+    // without `$HOME`, it's the user's home directory
+    if let Some(home_dir) = dirs::home_dir() {
+        TestBuilder::new()
+            .command("unset HOME; cd; pwd")
+            .assert_stdout(&format!("{}\n", home_dir.display()))
+            .run()
+            .await;
+    }
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn cd_logical() {
+    TestBuilder::new()
+        .directory("main/sub")
+        .command(
+            "ln -s main link && cd link/sub && echo $PWD && cd .. && pwd && cd -P . && echo $PWD && \
+             cd $TEMP_DIR && export PWD=$TEMP_DIR/link && echo $PWD && pwd",
+        )
+        .assert_stdout("$TEMP_DIR/link/sub\n$TEMP_DIR/link\n$TEMP_DIR/main\n$TEMP_DIR/link\n$TEMP_DIR/link\n")
+        .run()
+        .await;
+}
+
+#[tokio::test]
+async fn compgen() {
+    TestBuilder::new()
+        .command("compgen -b ec")
+        .assert_stdout("echo\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .directory("sub_dir")
+        .file("sub_file", "")
+        .command("compgen -d sub; compgen -f sub; SUB_VAR=1 && compgen -v SUB_")
+        .assert_stdout("sub_dir\nsub_dir\nsub_file\nSUB_VAR\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("compgen -b missing || echo $?")
+        .assert_stdout("1\n")
+        .run()
+        .await;
+}
+
+#[tokio::test]
+async fn repeat() {
+    TestBuilder::new()
+        .command("repeat 3 echo x")
+        .assert_stdout("x\nx\nx\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .directory("a/b")
+        .command("cd a/b; repeat 2 cd ..; pwd; repeat 0 echo x; echo $?; repeat 2 false || echo $?")
+        .assert_stdout("$TEMP_DIR\n0\n1\n")
+        .run()
+        .await;
+
+    TestBuilder::new()
+        .command("repeat x echo")
+        .assert_stderr("repeat: x: invalid count\n")
+        .assert_exit_code(1)
+        .run()
+        .await;
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn producer_stops_when_consumer_exits() {
+    use std::time::Duration;
+
+    // `yes` never ends on its own, it stops once `head` closes the pipe
+    let mut builder = TestBuilder::new();
+    builder
+        .command("yes | head -n 2 && echo done")
+        .assert_stdout("y\ny\ndone\n");
+    tokio::time::timeout(Duration::from_secs(10), builder.run())
+        .await
+        .expect("the producer should stop once the consumer exits");
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn broken_pipe() {
+    TestBuilder::new()
+        .command("seq 1 1000000 | head -n 1")
+        .assert_stdout("1\n")
+        .assert_exit_code(0)
+        .run()
+        .await;
+
+    // `true` exits without reading, so `cat` writes to a closed pipe
+    TestBuilder::new()
+        .file("file.txt", "test\n")
+        .command("(sleep 0.2 && cat file.txt) | true && echo done")
+        .assert_stdout("done\n")
+        .assert_stderr("")
+        .run()
+        .await;
+
+    // the producer stops writing and exits cleanly
+    TestBuilder::new()
+        .file("file.txt", "test\n")
+        .command(
+            "(sleep 0.2; cat file.txt file.txt; echo $? > status.txt; nl file.txt; echo $? >> status.txt; \
+             uname; echo $? >> status.txt; printenv; echo $? >> status.txt) | true",
+        )
+        .assert_stderr("")
+        .assert_file_equals("status.txt", "0\n0\n0\n0\n")
         .run()
         .await;
 }
@@ -8,13 +8,16 @@ use std::path::PathBuf;
 use std::rc::Rc;
 use tokio::task::JoinHandle;
 
-use deno_task_shell::execute_with_pipes;
+use deno_task_shell::execute_sequential_list_with_source;
 use deno_task_shell::fs_util;
-use deno_task_shell::parser::parse;
+use deno_task_shell::parser::parse_with_source;
 use deno_task_shell::pipe;
+use deno_task_shell::AsyncCommandBehavior;
+use deno_task_shell::EnvChange;
 use deno_task_shell::ExecuteResult;
 use deno_task_shell::ShellCommand;
 use deno_task_shell::ShellCommandContext;
+use deno_task_shell::ShellOptions;
 use deno_task_shell::ShellPipeWriter;
 use deno_task_shell::ShellState;
 
@@ -60,6 +63,7 @@ pub struct TestBuilder {
     temp_dir: Option<TempDir>,
     env_vars: HashMap<String, String>,
     custom_commands: HashMap<String, Rc<dyn ShellCommand>>,
+    shell_options: Vec<(ShellOptions, bool)>,
     command: String,
     stdin: Vec<u8>,
     expected_exit_code: i32,
@@ -97,6 +101,7 @@ impl TestBuilder {
             temp_dir: None,
             env_vars,
             custom_commands: shell::commands::get_commands(),
+            shell_options: Default::default(),
             command: Default::default(),
             stdin: Default::default(),
             expected_exit_code: 0,
@@ -151,6 +156,13 @@ impl TestBuilder {
         self
     }
 
+    /// Sets a shell option before running the command, for the options
+    /// that can't be set with `set` or `shopt`.
+    pub fn shell_option(&mut self, option: ShellOptions, value: bool) -> &mut Self {
+        self.shell_options.push((option, value));
+        self
+    }
+
     pub fn custom_command(&mut self, name: &str, execute: FnShellCommandExecute) -> &mut Self {
         self.custom_commands
             .insert(name.to_string(), Rc::new(FnShellCommand(execute)));
@@ -224,7 +236,7 @@ impl TestBuilder {
     pub async fn run(&mut self) {
         std::env::set_var("NO_GRAPHICS", "1");
 
-        let list = parse(&self.command).unwrap();
+        let (list, item_texts) = parse_with_source(&self.command).unwrap();
         let cwd = if let Some(temp_dir) = &self.temp_dir {
             temp_dir.cwd.clone()
         } else {
@@ -238,14 +250,26 @@ impl TestBuilder {
 
         let local_set = tokio::task::LocalSet::new();
         self.env_var("TEMP_DIR", &cwd.display().to_string());
-        let state = ShellState::new(
+        let mut state = ShellState::new(
             self.env_vars.clone(),
             &cwd,
             self.custom_commands.drain().collect(),
         );
-        let exit_code = local_set
-            .run_until(execute_with_pipes(list, state, stdin, stdout, stderr))
-            .await;
+        for (option, value) in self.shell_options.drain(..) {
+            state.apply_change(&EnvChange::SetShellOptions(option, value));
+        }
+        let (exit_code, _) = local_set
+            .run_until(execute_sequential_list_with_source(
+                list,
+                item_texts,
+                state,
+                stdin,
+                stdout,
+                stderr,
+                AsyncCommandBehavior::Wait,
+            ))
+            .await
+            .into_exit_code_and_handles();
         let temp_dir = if let Some(temp_dir) = &self.temp_dir {
             temp_dir.cwd.display().to_string()
         } else {